@@ -0,0 +1,62 @@
+//! Play the day 25 text adventure, or let the droid find the password.
+//!
+//! Usage: `cryostasis [--interactive] <program file>`
+
+use advent_of_code_2019::day25::{explore, find_password, Console, Ship};
+use advent_of_code_2019::intcode::parse_program;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+fn main() {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    let interactive = args.iter().any(|arg| arg == "--interactive");
+    args.retain(|arg| arg != "--interactive");
+    let path = match &args[..] {
+        [path] => path,
+        _ => {
+            eprintln!("usage: cryostasis [--interactive] <program file>");
+            process::exit(2);
+        }
+    };
+    let program = match fs::read_to_string(path)
+        .ok()
+        .and_then(|s| parse_program(&s))
+    {
+        Some(program) => program,
+        None => {
+            eprintln!("{}: not an Intcode program", path);
+            process::exit(1);
+        }
+    };
+
+    let mut ship = Ship::new(&program);
+    let result = ship.start().map_err(Into::into).and_then(|intro| {
+        if interactive {
+            play(&mut ship, &intro)
+        } else {
+            explore(&mut ship, &intro)
+                .and_then(|expedition| find_password(&mut ship, &expedition))
+                .map(|password| println!("{}", password))
+        }
+    });
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+/// Pass lines from stdin to the droid until the game ends or stdin closes.
+fn play(ship: &mut Ship, intro: &str) -> Result<(), Box<dyn std::error::Error>> {
+    print!("{}", intro);
+    io::stdout().flush()?;
+    for line in io::stdin().lock().lines() {
+        print!("{}", ship.send(line?.trim())?);
+        io::stdout().flush()?;
+        if ship.is_halted() {
+            break;
+        }
+    }
+    Ok(())
+}
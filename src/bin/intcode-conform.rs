@@ -0,0 +1,79 @@
+//! Run an Intcode program here and through another interpreter, and report
+//! where they disagree.
+//!
+//! Usage: `intcode-conform <program file> [input...] -- <command> [arg...]`
+//!
+//! The interpreter is run with the program file as its last argument, reads
+//! inputs from stdin one per line, and prints outputs one per line. It may
+//! also print its final memory as `memory: 1,2,3` to have that compared.
+
+use advent_of_code_2019::intcode::conformance::{compare, run_local, Interpreter};
+use advent_of_code_2019::intcode::parse_program;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "usage: intcode-conform <program file> [input...] -- <command> [arg...]";
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let split = args.iter().position(|a| a == "--");
+    let (ours, theirs) = match split {
+        Some(i) if i > 0 && i + 1 < args.len() => (&args[..i], &args[i + 1..]),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let path = Path::new(&ours[0]);
+    let program = match fs::read_to_string(path)
+        .ok()
+        .and_then(|s| parse_program(&s))
+    {
+        Some(program) => program,
+        None => {
+            eprintln!("{}: not an Intcode program", path.display());
+            process::exit(1);
+        }
+    };
+    let inputs = match ours[1..]
+        .iter()
+        .map(|v| v.parse())
+        .collect::<Result<Vec<i64>, _>>()
+    {
+        Ok(inputs) => inputs,
+        Err(_) => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let interpreter = Interpreter::new(&theirs[0], &theirs[1..]);
+    let (local, external) = match (run_local(&program, &inputs), interpreter.run(path, &inputs)) {
+        (Ok(local), Ok(external)) => (local, external),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    let mismatches = compare(&local, &external);
+    if mismatches.is_empty() {
+        println!(
+            "agree on {} outputs{}",
+            local.outputs.len(),
+            if external.memory.is_some() {
+                " and final memory"
+            } else {
+                ""
+            }
+        );
+        return;
+    }
+    for mismatch in &mismatches {
+        println!("{}", mismatch);
+    }
+    process::exit(1);
+}
@@ -0,0 +1,181 @@
+//! Interactive debugger for Intcode programs.
+//!
+//! Usage: `intcode-debug <program file>`, then `help` for commands.
+
+use advent_of_code_2019::intcode::debugger::{Debugger, Stop};
+use advent_of_code_2019::intcode::{parse_program, IntcodeMachine, State};
+use std::env;
+use std::fs;
+use std::io;
+use std::io::{BufRead, Write};
+use std::process;
+
+const HELP: &str = "\
+s [n]           step n instructions (default 1)
+c               continue to the next breakpoint or watchpoint
+b <pc>          toggle a breakpoint
+w <address>     toggle a watchpoint
+l [n]           list n instructions from pc (default 5)
+x <address> [n] examine n memory words (default 1)
+set <address> <value>
+                write to memory
+pc <pc>         jump to an instruction
+rb [value]      show or set the relative base
+i <value>...    queue input
+q               quit";
+
+fn report(debugger: &mut Debugger, stop: Stop) {
+    match stop {
+        Stop::Step(State::Output(v)) => println!("output {}", v),
+        Stop::Step(_) => {}
+        Stop::Breakpoint(pc) => println!("breakpoint at {}", pc),
+        Stop::Watchpoint {
+            pc,
+            address,
+            old,
+            new,
+        } => println!("{} written by {}: {} -> {}", address, pc, old, new),
+        Stop::NeedsInput => println!("waiting for input"),
+        Stop::Halted => println!("halted"),
+    }
+    let output = debugger.machine_mut().drain_output();
+    if !output.is_empty() {
+        println!("output {:?}", output);
+    }
+}
+
+fn list(debugger: &mut Debugger, count: usize) {
+    if debugger.machine().is_halted() {
+        return;
+    }
+    let pc = debugger.machine().pc();
+    for _ in 0..count {
+        let at = debugger.machine().pc();
+        match debugger.peek() {
+            Ok(instruction) => {
+                let line = debugger
+                    .machine()
+                    .format_instruction(at, &instruction)
+                    .modes(true)
+                    .values(true)
+                    .color(true)
+                    .to_string();
+                let marker = if at == pc { ">" } else { " " };
+                println!("{}{}", marker, line);
+                debugger.set_pc(at + instruction.size());
+            }
+            Err(e) => {
+                println!(" {:>5}: {}", at, e);
+                break;
+            }
+        }
+    }
+    debugger.set_pc(pc);
+}
+
+fn toggle(set: bool, kind: &str, at: usize) {
+    let state = if set { "set" } else { "cleared" };
+    println!("{} {} at {}", kind, state, at);
+}
+
+fn command(debugger: &mut Debugger, line: &str) -> Option<bool> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    let arg = |i: usize| words.get(i).and_then(|w| w.parse::<i64>().ok());
+
+    match words.first().copied()? {
+        "s" | "step" => {
+            for _ in 0..arg(1).unwrap_or(1) {
+                match debugger.step() {
+                    Ok(Stop::Step(State::Running)) => {}
+                    Ok(stop) => {
+                        report(debugger, stop);
+                        break;
+                    }
+                    Err(e) => {
+                        println!("{}", e);
+                        break;
+                    }
+                }
+            }
+            list(debugger, 1);
+        }
+        "c" | "continue" => {
+            match debugger.resume() {
+                Ok(stop) => report(debugger, stop),
+                Err(e) => println!("{}", e),
+            }
+            list(debugger, 1);
+        }
+        "b" | "break" => {
+            let pc = arg(1)? as usize;
+            let set = debugger.add_breakpoint(pc) || !debugger.remove_breakpoint(pc);
+            toggle(set, "breakpoint", pc);
+        }
+        "w" | "watch" => {
+            let address = arg(1)? as usize;
+            let set = debugger.add_watchpoint(address) || !debugger.remove_watchpoint(address);
+            toggle(set, "watchpoint", address);
+        }
+        "l" | "list" => list(debugger, arg(1).unwrap_or(5) as usize),
+        "x" => {
+            let address = arg(1)? as usize;
+            let count = arg(2).unwrap_or(1) as usize;
+            let words = (address..address + count)
+                .map(|a| debugger.machine().load(a))
+                .collect::<Vec<_>>();
+            println!("{:>5}: {:?}", address, words);
+        }
+        "set" => {
+            let (address, value) = (arg(1)? as usize, arg(2)?);
+            debugger.machine_mut().store(address, value);
+        }
+        "pc" => debugger.set_pc(arg(1)? as usize),
+        "rb" => match arg(1) {
+            Some(relative_base) => debugger.set_relative_base(relative_base),
+            None => println!("{}", debugger.machine().relative_base()),
+        },
+        "i" | "input" => {
+            for value in words[1..].iter().filter_map(|w| w.parse().ok()) {
+                debugger.machine_mut().push_input(value);
+            }
+        }
+        "h" | "help" => println!("{}", HELP),
+        "q" | "quit" => return Some(false),
+        _ => return None,
+    }
+    Some(true)
+}
+
+fn main() {
+    let path = env::args().nth(1).unwrap_or_else(|| {
+        eprintln!("usage: intcode-debug <program file>");
+        process::exit(2);
+    });
+    let program = fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| parse_program(&s))
+        .unwrap_or_else(|| {
+            eprintln!("couldn't read a program from {}", path);
+            process::exit(1);
+        });
+
+    let mut debugger = Debugger::new(IntcodeMachine::new(&program, None, None));
+    list(&mut debugger, 1);
+
+    let stdin = io::stdin();
+    loop {
+        print!("(icdb) ");
+        io::stdout().flush().ok();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        match command(&mut debugger, line.trim()) {
+            Some(true) => {}
+            Some(false) => break,
+            None if line.trim().is_empty() => {}
+            None => println!("unknown command, try help"),
+        }
+    }
+}
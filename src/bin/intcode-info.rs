@@ -0,0 +1,35 @@
+//! Report what static analysis finds in an Intcode program.
+//!
+//! Usage: `intcode-info <program file>...`
+
+use advent_of_code_2019::intcode::analysis::analyze;
+use advent_of_code_2019::intcode::parse_program;
+use std::env;
+use std::fs;
+use std::process;
+
+fn main() {
+    let paths = env::args().skip(1).collect::<Vec<_>>();
+    if paths.is_empty() {
+        eprintln!("usage: intcode-info <program file>...");
+        process::exit(2);
+    }
+
+    for (i, path) in paths.iter().enumerate() {
+        let program = match fs::read_to_string(path)
+            .ok()
+            .and_then(|s| parse_program(&s))
+        {
+            Some(program) => program,
+            None => {
+                eprintln!("{}: not an Intcode program", path);
+                process::exit(1);
+            }
+        };
+        if i > 0 {
+            println!();
+        }
+        println!("{}", path);
+        print!("{}", analyze(&program));
+    }
+}
@@ -0,0 +1,48 @@
+//! Show a small deck after each day 22 technique, dealt for real next to the
+//! composed transform.
+//!
+//! Usage: `shuffle-frames <deck size> <techniques file>`
+
+use advent_of_code_2019::day22::{frames, render_frames, Technique};
+use std::env;
+use std::fs;
+use std::process;
+
+/// Largest deck that still fits on a line.
+const MAX_SIZE: i64 = 100;
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let (size, path) = match &args[..] {
+        [size, path] => (size, path),
+        _ => {
+            eprintln!("usage: shuffle-frames <deck size> <techniques file>");
+            process::exit(2);
+        }
+    };
+    let size = match size.parse::<i64>() {
+        Ok(size) if size > 0 && size <= MAX_SIZE => size,
+        _ => {
+            eprintln!("deck size must be between 1 and {}", MAX_SIZE);
+            process::exit(2);
+        }
+    };
+    let techniques = match fs::read_to_string(path).map(|s| {
+        s.lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<Technique>, _>>()
+    }) {
+        Ok(Ok(techniques)) => techniques,
+        Ok(Err(e)) => {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        }
+    };
+
+    print!("{}", render_frames(&frames(&techniques, size)));
+}
@@ -1,3 +1,6 @@
+use std::error::Error;
+use std::fmt;
+
 fn fuel_for_mass(mass: i32) -> i32 {
     mass / 3 - 2
 }
@@ -12,6 +15,114 @@ fn cumulative_fuel_for_mass(mass: i32) -> i32 {
     }
 }
 
+/// Heaviest module whose cumulative fuel fits within `budget`.
+///
+/// Cumulative fuel never decreases as mass grows, so a binary search over the
+/// mass finds the boundary. Returns `None` for a negative budget.
+pub fn max_mass_for_fuel(budget: i32) -> Option<i32> {
+    if budget < 0 {
+        return None;
+    }
+
+    // Fuel is at least a third of the mass, which bounds the search
+    let upper = (3 * (i64::from(budget) + 3)).min(i64::from(i32::MAX)) as i32;
+    if cumulative_fuel_for_mass(upper) <= budget {
+        return Some(upper);
+    }
+
+    // Invariant: `low` fits within the budget and `high` does not
+    let (mut low, mut high) = (0, upper);
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if cumulative_fuel_for_mass(mid) <= budget {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some(low)
+}
+
+/// Whether all the modules can launch with a combined fuel `budget`.
+pub fn can_launch(modules: &[i32], budget: i32) -> bool {
+    modules
+        .iter()
+        .map(|&mass| i64::from(cumulative_fuel_for_mass(mass)))
+        .sum::<i64>()
+        <= i64::from(budget)
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ParseMassError {
+    /// A mass starting at byte `offset` is negative.
+    Negative { offset: usize },
+    /// A mass starting at byte `offset` doesn't fit in an `i32`.
+    Overflow { offset: usize },
+}
+
+impl Error for ParseMassError {}
+
+impl fmt::Display for ParseMassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseMassError::Negative { offset } => write!(f, "negative mass at byte {}", offset),
+            ParseMassError::Overflow { offset } => write!(f, "mass too large at byte {}", offset),
+        }
+    }
+}
+
+/// Masses in `input`, one per line, read straight from the bytes without
+/// allocating. Anything but digits and `-` ends a number, so stray
+/// whitespace and `\r\n` endings are fine.
+pub fn masses(input: &[u8]) -> impl Iterator<Item = Result<i32, ParseMassError>> + '_ {
+    let mut bytes = input.iter().enumerate();
+    std::iter::from_fn(move || {
+        let mut mass: Option<(usize, i32)> = None;
+        for (offset, &b) in &mut bytes {
+            match (b, mass) {
+                (b'-', _) => return Some(Err(ParseMassError::Negative { offset })),
+                (b'0'..=b'9', _) => {
+                    let (start, m) = mass.unwrap_or((offset, 0));
+                    let m = m
+                        .checked_mul(10)
+                        .and_then(|m| m.checked_add(i32::from(b - b'0')));
+                    match m {
+                        Some(m) => mass = Some((start, m)),
+                        None => return Some(Err(ParseMassError::Overflow { offset: start })),
+                    }
+                }
+                (_, Some(_)) => break,
+                (_, None) => {}
+            }
+        }
+        mass.map(|(_, m)| Ok(m))
+    })
+}
+
+/// Cumulative fuel for `mass`, adding up each round of fuel in a loop rather
+/// than recursing.
+fn cumulative_fuel_fast(mass: i32) -> i64 {
+    let (mut extra, mut total) = (fuel_for_mass(mass), 0);
+    while extra > 0 {
+        total += i64::from(extra);
+        extra = fuel_for_mass(extra);
+    }
+    total
+}
+
+/// Both fuel totals in a single pass over `masses`, summed as `i64` so huge
+/// inputs don't overflow.
+pub fn fuel_totals<I: IntoIterator<Item = i32>>(masses: I) -> (i64, i64) {
+    masses
+        .into_iter()
+        .fold((0, 0), |(direct, cumulative), mass| {
+            (
+                direct + i64::from(fuel_for_mass(mass)),
+                cumulative + cumulative_fuel_fast(mass),
+            )
+        })
+}
+
 #[aoc_generator(day1)]
 fn load_modules(input: &str) -> Vec<i32> {
     input.lines().filter_map(|s| s.parse().ok()).collect()
@@ -30,6 +141,29 @@ fn total_cumulative_fuel(modules: &[i32]) -> i32 {
         .sum()
 }
 
+#[aoc_generator(day1, part1, Fused)]
+fn load_masses(input: &str) -> Result<Vec<i32>, ParseMassError> {
+    masses(input.as_bytes()).collect()
+}
+
+#[aoc(day1, part1, Fused)]
+fn total_fuel_fused(modules: &[i32]) -> i64 {
+    modules
+        .iter()
+        .map(|&mass| i64::from(fuel_for_mass(mass)))
+        .sum()
+}
+
+#[aoc_generator(day1, part2, Fused)]
+fn load_masses_part2(input: &str) -> Result<Vec<i32>, ParseMassError> {
+    load_masses(input)
+}
+
+#[aoc(day1, part2, Fused)]
+fn total_cumulative_fuel_fused(modules: &[i32]) -> i64 {
+    modules.iter().map(|&mass| cumulative_fuel_fast(mass)).sum()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -48,4 +182,58 @@ mod tests {
         assert_eq!(cumulative_fuel_for_mass(1969), 966);
         assert_eq!(cumulative_fuel_for_mass(100756), 50346);
     }
+
+    #[test]
+    fn test_fuel_totals() {
+        let input = "12\n14\r\n1969\n\n100756\n";
+        assert_eq!(load_masses(input), Ok(vec![12, 14, 1969, 100756]));
+        assert_eq!(masses(b"").count(), 0);
+        assert_eq!(
+            load_masses("12\n-5\n"),
+            Err(ParseMassError::Negative { offset: 3 })
+        );
+        assert_eq!(
+            load_masses("12\n99999999999\n"),
+            Err(ParseMassError::Overflow { offset: 3 })
+        );
+        assert_eq!(load_masses("2147483647"), Ok(vec![i32::MAX]));
+
+        let modules = load_modules("12\n14\n1969\n100756\n");
+        assert_eq!(
+            fuel_totals(load_masses(input).unwrap()),
+            (
+                i64::from(total_fuel(&modules)),
+                i64::from(total_cumulative_fuel(&modules))
+            )
+        );
+        assert_eq!(total_fuel_fused(&modules), 2 + 2 + 654 + 33583);
+        assert_eq!(total_cumulative_fuel_fused(&modules), 51316);
+
+        // More fuel than fits in an i32
+        let huge = std::iter::repeat_n(300_000_000, 100);
+        assert_eq!(fuel_totals(huge).0, 9_999_999_800);
+    }
+
+    #[test]
+    fn test_max_mass_for_fuel() {
+        assert_eq!(max_mass_for_fuel(-1), None);
+        assert_eq!(max_mass_for_fuel(0), Some(8));
+        assert_eq!(max_mass_for_fuel(2), Some(14));
+        assert_eq!(max_mass_for_fuel(966), Some(1970));
+        assert_eq!(cumulative_fuel_for_mass(1971), 967);
+
+        let mass = max_mass_for_fuel(50346).unwrap();
+        assert!(cumulative_fuel_for_mass(mass) <= 50346);
+        assert!(cumulative_fuel_for_mass(mass + 1) > 50346);
+
+        assert!(max_mass_for_fuel(i32::MAX).is_some());
+    }
+
+    #[test]
+    fn test_can_launch() {
+        let modules = [12, 14, 1969, 100756];
+        assert!(can_launch(&modules, 51316));
+        assert!(!can_launch(&modules, 51315));
+        assert!(can_launch(&[], 0));
+    }
 }
@@ -1,37 +1,224 @@
-use crate::intcode::{parse_program, IntcodeMachine};
+use crate::intcode::diff::MemoryDiff;
+use crate::intcode::{parse_program, IntcodeError, IntcodeMachine, RunResult};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Most instructions a noun and verb get before they're taken to loop forever.
+const FUEL: u64 = 10_000;
+
+#[allow(clippy::inconsistent_digit_grouping)]
+const TARGET: i64 = 1969_07_20;
 
 #[aoc_generator(day2)]
 fn load_program(input: &str) -> Vec<i64> {
     parse_program(input).unwrap()
 }
 
+/// Run the gravity assist program with a noun and verb, and return what it
+/// changed in memory, the noun and verb included.
+pub fn memory_diff(program: &[i64], noun: i64, verb: i64) -> Result<MemoryDiff, IntcodeError> {
+    let mut im = IntcodeMachine::new(program, None, None);
+    im.store(1, noun);
+    im.store(2, verb);
+    im.run()?;
+    Ok(MemoryDiff::new(program, &im.mem))
+}
+
 #[aoc(day2, part1)]
-fn restored_program_state(program: &[i64]) -> i64 {
+fn restored_program_state(program: &[i64]) -> Result<i64, IntcodeError> {
     let (noun, verb) = (12, 2);
 
     let mut im = IntcodeMachine::new(program, None, None);
     im.store(1, noun);
     im.store(2, verb);
-    im.run();
-    im.load(0)
+    im.run()?;
+    Ok(im.load(0))
+}
+
+/// What the program leaves at address 0 with this noun and verb, if it halts.
+fn output(program: &[i64], noun: i64, verb: i64) -> Option<i64> {
+    let mut im = IntcodeMachine::new(program, None, None);
+    im.store(1, noun);
+    im.store(2, verb);
+    // Some combinations fault or never halt, and can't be the answer
+    if im.run_for(FUEL) != Ok(RunResult::Halted) {
+        return None;
+    }
+    Some(im.load(0))
+}
+
+fn produces(program: &[i64], noun: i64, verb: i64, target: i64) -> bool {
+    output(program, noun, verb) == Some(target)
+}
+
+/// The first noun and verb, in order, that produce `target`.
+pub fn find_noun_verb(program: &[i64], target: i64) -> Option<(i64, i64)> {
+    (0..=99)
+        .flat_map(|noun| (0..=99).map(move |verb| (noun, verb)))
+        .find(|&(noun, verb)| produces(program, noun, verb, target))
+}
+
+/// Like [`find_noun_verb`], with the grid shared out between `threads`.
+///
+/// Each thread takes every `threads`th pair, and gives up once a pair before
+/// its next one has been found, so the answer is the same as searching in
+/// order.
+pub fn find_noun_verb_parallel(program: &[i64], target: i64, threads: usize) -> Option<(i64, i64)> {
+    const PAIRS: usize = 100 * 100;
+    let threads = threads.max(1);
+    let found = AtomicUsize::new(PAIRS);
+    thread::scope(|scope| {
+        for start in 0..threads {
+            let found = &found;
+            scope.spawn(move || {
+                for i in (start..PAIRS).step_by(threads) {
+                    if i >= found.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let (noun, verb) = ((i / 100) as i64, (i % 100) as i64);
+                    if produces(program, noun, verb, target) {
+                        found.fetch_min(i, Ordering::Relaxed);
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    match found.into_inner() {
+        PAIRS => None,
+        i => Some(((i / 100) as i64, (i % 100) as i64)),
+    }
+}
+
+/// Like [`find_noun_verb`], assuming address 0 ends up as
+/// `a * noun + b * verb + c`.
+///
+/// Three runs fit the model, which then gives the verb for each noun. Every
+/// answer it gives is checked by running the program, and if none checks out
+/// the grid is searched instead.
+pub fn find_noun_verb_affine(program: &[i64], target: i64) -> Option<(i64, i64)> {
+    let model = (|| {
+        let c = output(program, 0, 0)?;
+        Some((output(program, 1, 0)? - c, output(program, 0, 1)? - c, c))
+    })();
+    let solved = model.and_then(|(a, b, c)| {
+        (0..=99).find_map(|noun| {
+            let rest = target - c - a * noun;
+            let verb = match b {
+                0 if rest == 0 => 0,
+                0 => return None,
+                b if rest % b == 0 => rest / b,
+                _ => return None,
+            };
+            Some((noun, verb)).filter(|_| (0..=99).contains(&verb))
+        })
+    });
+    match solved {
+        Some((noun, verb)) if produces(program, noun, verb, target) => Some((noun, verb)),
+        _ => find_noun_verb(program, target),
+    }
+}
+
+/// How to search for the noun and verb in part 2.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Day2Strategy {
+    /// Every pair in order.
+    BruteForce,
+    /// Every pair, split between this many threads.
+    Parallel(usize),
+    /// Solved from an affine model, searching if that's wrong.
+    Affine,
+}
+
+impl Day2Strategy {
+    pub fn find_noun_verb(self, program: &[i64], target: i64) -> Option<(i64, i64)> {
+        match self {
+            Day2Strategy::BruteForce => find_noun_verb(program, target),
+            Day2Strategy::Parallel(threads) => find_noun_verb_parallel(program, target, threads),
+            Day2Strategy::Affine => find_noun_verb_affine(program, target),
+        }
+    }
+}
+
+fn fuzz_program_state_with(program: &[i64], strategy: Day2Strategy) -> i64 {
+    strategy
+        .find_noun_verb(program, TARGET)
+        .map_or(0, |(noun, verb)| 100 * noun + verb)
 }
 
 #[aoc(day2, part2)]
 fn fuzz_program_state(program: &[i64]) -> i64 {
-    #[allow(clippy::inconsistent_digit_grouping)]
-    let target = 1969_07_20;
-
-    for noun in 0..=99 {
-        for verb in 0..=99 {
-            let mut im = IntcodeMachine::new(program, None, None);
-            im.store(1, noun);
-            im.store(2, verb);
-            im.run();
-
-            if im.load(0) == target {
-                return 100 * noun + verb;
+    fuzz_program_state_with(program, Day2Strategy::BruteForce)
+}
+
+#[aoc(day2, part2, Parallel)]
+fn fuzz_program_state_parallel(program: &[i64]) -> i64 {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    fuzz_program_state_with(program, Day2Strategy::Parallel(threads))
+}
+
+#[aoc(day2, part2, Affine)]
+fn fuzz_program_state_affine(program: &[i64]) -> i64 {
+    fuzz_program_state_with(program, Day2Strategy::Affine)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_diff() {
+        let program = parse_program("1,9,10,3,2,3,11,0,99,30,40,50").unwrap();
+        let diff = memory_diff(&program, 9, 10).unwrap();
+        assert_eq!(diff.to_string(), "    0: 1 -> 3500\n    3: 3 -> 70\n");
+
+        // The noun and verb show up too
+        let diff = memory_diff(&program, 10, 11).unwrap();
+        assert_eq!(
+            diff.to_string(),
+            "    0: 1 -> 4500\n    1: 9 -> 10\n    2: 10 -> 11\n    3: 3 -> 90\n"
+        );
+    }
+
+    #[test]
+    fn test_find_noun_verb() {
+        // Address 0 ends up as the product of the words the noun and verb
+        // point at
+        let program = parse_program("2,0,0,0,99").unwrap();
+        assert_eq!(find_noun_verb(&program, 0), Some((0, 1)));
+        assert_eq!(find_noun_verb(&program, 2), Some((1, 0)));
+        assert_eq!(find_noun_verb(&program, 9801), Some((4, 4)));
+        assert_eq!(find_noun_verb(&program, -1), None);
+        for threads in 1..=5 {
+            for &target in &[0, 2, 9801, -1] {
+                assert_eq!(
+                    find_noun_verb_parallel(&program, target, threads),
+                    find_noun_verb(&program, target)
+                );
             }
         }
     }
-    0
+
+    #[test]
+    fn test_affine() {
+        // Address 0 ends up as 3 * noun + verb + 5
+        let program = parse_program("1102,0,3,20,1,20,2,20,1001,20,5,0,99").unwrap();
+        for &target in &[5, 6, 8, 3 * 99 + 99 + 5, 1000, -1] {
+            for &strategy in &[
+                Day2Strategy::BruteForce,
+                Day2Strategy::Parallel(3),
+                Day2Strategy::Affine,
+            ] {
+                assert_eq!(
+                    strategy.find_noun_verb(&program, target),
+                    find_noun_verb(&program, target)
+                );
+            }
+        }
+        assert_eq!(find_noun_verb_affine(&program, 8), Some((0, 3)));
+
+        // Not affine, so the model's answer is wrong and the grid is searched
+        let program = parse_program("2,0,0,0,99").unwrap();
+        assert_eq!(find_noun_verb_affine(&program, 9801), Some((4, 4)));
+    }
 }
@@ -1,47 +1,90 @@
+use crate::geometry::Point2;
 use itertools::Itertools;
+use std::collections::BTreeMap;
 use std::num::ParseIntError;
 use std::str::FromStr;
 
-#[derive(Copy, Clone, Debug, Default, PartialEq)]
-struct Point {
-    x: i32,
-    y: i32,
-}
-
-impl Point {
-    fn new(x: i32, y: i32) -> Self {
-        Point { x, y }
+type Point = Point2<i32>;
+
+/// Find the grid point where segments `a`-`b` and `c`-`d` cross.
+///
+/// All arithmetic stays in integers, so any mix of axis-aligned and
+/// 45-degree segments is exact. Parallel segments are never reported, and
+/// neither are two diagonals crossing between grid points.
+#[allow(clippy::many_single_char_names)]
+fn overlap(a: Point, b: Point, c: Point, d: Point) -> Option<Point> {
+    let (r_x, r_y) = (
+        i64::from(b.x) - i64::from(a.x),
+        i64::from(b.y) - i64::from(a.y),
+    );
+    let (s_x, s_y) = (
+        i64::from(d.x) - i64::from(c.x),
+        i64::from(d.y) - i64::from(c.y),
+    );
+    let (q_x, q_y) = (
+        i64::from(c.x) - i64::from(a.x),
+        i64::from(c.y) - i64::from(a.y),
+    );
+
+    let determinant = r_x * s_y - r_y * s_x;
+    if determinant == 0 {
+        return None;
     }
 
-    fn distance(self, other: Self) -> i32 {
-        (self.x - other.x).abs() + (self.y - other.y).abs()
+    // Positions along each segment, scaled by the determinant
+    let t = q_x * s_y - q_y * s_x;
+    let u = q_x * r_y - q_y * r_x;
+    let (t, u, determinant) = if determinant < 0 {
+        (-t, -u, -determinant)
+    } else {
+        (t, u, determinant)
+    };
+
+    if !(0..=determinant).contains(&t) || !(0..=determinant).contains(&u) {
+        return None;
     }
 
-    #[allow(clippy::many_single_char_names)]
-    fn overlap(a: Self, b: Self, c: Self, d: Self) -> Option<Self> {
-        let (a_x, a_y) = (a.x as f32, a.y as f32);
-        let (b_x, b_y) = (b.x as f32, b.y as f32);
-        let (c_x, c_y) = (c.x as f32, c.y as f32);
-        let (d_x, d_y) = (d.x as f32, d.y as f32);
-
-        let a1 = b_y - a_y;
-        let b1 = b_x - a_x;
-        let a2 = d_y - c_y;
-        let b2 = d_x - c_x;
-
-        let determinant = a2 * b1 - a1 * b2;
+    let (x, y) = (r_x * t, r_y * t);
+    if x % determinant != 0 || y % determinant != 0 {
+        return None;
+    }
+    Some(Point::new(
+        a.x + (x / determinant) as i32,
+        a.y + (y / determinant) as i32,
+    ))
+}
 
-        let s = (-a1 * (a_x - c_x) + b1 * (a_y - c_y)) / determinant;
-        let t = (b2 * (a_y - c_y) - a2 * (a_x - c_x)) / determinant;
+/// Every grid point segments `a`-`b` and `c`-`d` share.
+///
+/// Crossing segments share at most one, but collinear segments share every
+/// point where they run together.
+fn overlaps(a: Point, b: Point, c: Point, d: Point) -> Vec<Point> {
+    if let Some(p) = overlap(a, b, c, d) {
+        return vec![p];
+    }
+    collinear_overlap(a, b, c, d)
+}
 
-        if s >= 0.0 && s <= 1.0 && t >= 0.0 && t <= 1.0 {
-            let x = (a_x + (t * b1)) as i32;
-            let y = (a_y + (t * a1)) as i32;
-            Some(Point::new(x, y))
-        } else {
-            None
-        }
+/// The grid points along the shared part of two segments lying on the same
+/// straight or diagonal line, or none if they don't.
+fn collinear_overlap(a: Point, b: Point, c: Point, d: Point) -> Vec<Point> {
+    let cross =
+        |p: Point, q: Point| i64::from(p.x) * i64::from(q.y) - i64::from(p.y) * i64::from(q.x);
+    let r = b - a;
+    if r == Point::default() || cross(r, d - c) != 0 || cross(r, c - a) != 0 {
+        return Vec::new();
     }
+
+    // Measure positions in steps from `a` towards `b`
+    let unit = Point::new(r.x.signum(), r.y.signum());
+    let along = |p: Point| {
+        let offset = p - a;
+        (offset.x * unit.x + offset.y * unit.y) / (unit.x * unit.x + unit.y * unit.y)
+    };
+    let (c, d) = (along(c), along(d));
+    let start = c.min(d).max(0);
+    let end = c.max(d).min(along(b));
+    (start..=end).map(|t| a + unit * t).collect()
 }
 
 #[derive(Debug, PartialEq)]
@@ -50,24 +93,65 @@ enum Vector {
     Down(u16),
     Left(u16),
     Right(u16),
+    UpLeft(u16),
+    UpRight(u16),
+    DownLeft(u16),
+    DownRight(u16),
 }
 
-impl FromStr for Vector {
-    type Err = ParseIntError;
+impl Vector {
+    /// Offset travelled by the vector, and the number of steps it takes.
+    fn delta(&self) -> (Point, i32) {
+        let (dx, dy, v) = match *self {
+            Vector::Up(v) => (0, 1, v),
+            Vector::Down(v) => (0, -1, v),
+            Vector::Left(v) => (-1, 0, v),
+            Vector::Right(v) => (1, 0, v),
+            Vector::UpLeft(v) => (-1, 1, v),
+            Vector::UpRight(v) => (1, 1, v),
+            Vector::DownLeft(v) => (-1, -1, v),
+            Vector::DownRight(v) => (1, -1, v),
+        };
+        (Point::new(dx, dy) * i32::from(v), i32::from(v))
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (direction, distance) = s.split_at(1);
+    /// Like parsing with [`FromStr`], also accepting diagonals written with
+    /// two direction letters, e.g. `UR10`.
+    fn parse_with_diagonals(s: &str) -> Result<Self, ParseIntError> {
+        let split = if s.starts_with(['U', 'D']) && s[1..].starts_with(['L', 'R']) {
+            2
+        } else {
+            1
+        };
+        Vector::parse_split(s, split)
+    }
+
+    fn parse_split(s: &str, split: usize) -> Result<Self, ParseIntError> {
+        let (direction, distance) = s.split_at(split);
 
         match direction {
             "U" => Ok(Vector::Up(distance.parse()?)),
             "D" => Ok(Vector::Down(distance.parse()?)),
             "L" => Ok(Vector::Left(distance.parse()?)),
             "R" => Ok(Vector::Right(distance.parse()?)),
+            "UL" => Ok(Vector::UpLeft(distance.parse()?)),
+            "UR" => Ok(Vector::UpRight(distance.parse()?)),
+            "DL" => Ok(Vector::DownLeft(distance.parse()?)),
+            "DR" => Ok(Vector::DownRight(distance.parse()?)),
             _ => unreachable!(),
         }
     }
 }
 
+/// The puzzle's vectors, going up, down, left or right only.
+impl FromStr for Vector {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Vector::parse_split(s, 1)
+    }
+}
+
 struct Wire {
     vectors: Vec<Vector>,
 }
@@ -81,12 +165,7 @@ impl Wire {
         self.vectors
             .iter()
             .scan(Point::default(), |origin, vector| {
-                match *vector {
-                    Vector::Up(v) => origin.y += v as i32,
-                    Vector::Down(v) => origin.y -= v as i32,
-                    Vector::Left(v) => origin.x -= v as i32,
-                    Vector::Right(v) => origin.x += v as i32,
-                };
+                *origin += vector.delta().0;
                 Some(*origin)
             })
             .collect()
@@ -96,24 +175,9 @@ impl Wire {
         self.vectors
             .iter()
             .scan((Point::default(), 0), |(origin, distance), vector| {
-                match *vector {
-                    Vector::Up(v) => {
-                        origin.y += v as i32;
-                        *distance += v as i32;
-                    }
-                    Vector::Down(v) => {
-                        origin.y -= v as i32;
-                        *distance += v as i32;
-                    }
-                    Vector::Left(v) => {
-                        origin.x -= v as i32;
-                        *distance += v as i32;
-                    }
-                    Vector::Right(v) => {
-                        origin.x += v as i32;
-                        *distance += v as i32;
-                    }
-                };
+                let (delta, steps) = vector.delta();
+                *origin += delta;
+                *distance += steps;
                 Some((*origin, *distance))
             })
             .collect()
@@ -126,14 +190,13 @@ impl Wire {
         points1
             .iter()
             .zip(points1[1..].iter())
-            .map(|(&a, &b)| {
+            .flat_map(|(&a, &b)| {
                 points2
                     .iter()
                     .zip(points2[1..].iter())
-                    .filter_map(|(&c, &d)| Point::overlap(a, b, c, d))
+                    .flat_map(|(&c, &d)| overlaps(a, b, c, d))
                     .collect_vec()
             })
-            .flatten()
             .collect()
     }
 
@@ -144,43 +207,240 @@ impl Wire {
         points1
             .iter()
             .zip(points1[1..].iter())
-            .map(|(&(a, ad), &(b, _))| {
+            .flat_map(|(&(a, ad), &(b, _))| {
                 points2
                     .iter()
                     .zip(points2[1..].iter())
-                    .filter_map(|(&(c, cd), &(d, _))| {
-                        let intersection = Point::overlap(a, b, c, d)?;
-                        Some(ad + cd + a.distance(intersection) + c.distance(intersection))
+                    .flat_map(|(&(c, cd), &(d, _))| {
+                        overlaps(a, b, c, d)
+                            .into_iter()
+                            .map(move |p| ad + cd + a.chebyshev(p) + c.chebyshev(p))
                     })
                     .collect_vec()
             })
-            .flatten()
             .collect()
     }
+
+    /// Find intersections with a sweep line moving along `x`, returning each
+    /// crossing along with the combined wire length needed to reach it.
+    ///
+    /// Horizontal segments become active while the sweep is within their `x`
+    /// range, so a vertical segment only has to visit the active segments that
+    /// fall inside its own `y` range instead of every segment of the other wire.
+    /// Collinear horizontal segments are met when the second one becomes
+    /// active, and collinear vertical ones when the sweep reaches their `x`.
+    /// Diagonal segments don't fit the sweep and are checked pairwise instead.
+    fn sweep_intersections(&self, other: &Wire) -> Vec<(Point, i32)> {
+        let segments = [self, other]
+            .iter()
+            .enumerate()
+            .flat_map(|(wire, w)| {
+                w.as_points_with_length()
+                    .windows(2)
+                    .map(|pair| (wire, pair[0], pair[1].0))
+                    .collect_vec()
+            })
+            .collect_vec();
+
+        let mut events = segments
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &(_, (a, _), b))| {
+                if a.y == b.y {
+                    vec![
+                        (a.x.min(b.x), SweepEvent::Enter, i),
+                        (a.x.max(b.x), SweepEvent::Leave, i),
+                    ]
+                } else if a.x == b.x {
+                    vec![(a.x, SweepEvent::Cross, i)]
+                } else {
+                    vec![]
+                }
+            })
+            .collect_vec();
+        events.sort();
+
+        let mut active: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+        let mut verticals: Vec<usize> = Vec::new();
+        let mut intersections = Vec::new();
+        let shared = |i: usize, j: usize, intersections: &mut Vec<(Point, i32)>| {
+            let (wire, (a, ad), b) = segments[i];
+            let (other_wire, (c, cd), d) = segments[j];
+            if wire != other_wire {
+                for p in collinear_overlap(a, b, c, d) {
+                    intersections.push((p, ad + cd + a.chebyshev(p) + c.chebyshev(p)));
+                }
+            }
+        };
+        for (x, event, i) in events {
+            let (wire, (a, ad), b) = segments[i];
+            match event {
+                SweepEvent::Enter => {
+                    let horizontals = active.entry(a.y).or_default();
+                    for &h in horizontals.iter() {
+                        shared(i, h, &mut intersections);
+                    }
+                    horizontals.push(i);
+                }
+                SweepEvent::Leave => {
+                    if let Some(horizontals) = active.get_mut(&a.y) {
+                        horizontals.retain(|&h| h != i);
+                        if horizontals.is_empty() {
+                            active.remove(&a.y);
+                        }
+                    }
+                }
+                SweepEvent::Cross => {
+                    verticals.retain(|&v| {
+                        let (_, (c, _), _) = segments[v];
+                        c.x == x
+                    });
+                    for &v in &verticals {
+                        shared(i, v, &mut intersections);
+                    }
+                    verticals.push(i);
+
+                    let range = a.y.min(b.y)..=a.y.max(b.y);
+                    for (&y, horizontals) in active.range(range) {
+                        for &h in horizontals {
+                            let (h_wire, (c, cd), _) = segments[h];
+                            if h_wire != wire {
+                                let p = Point::new(x, y);
+                                intersections.push((p, ad + cd + a.chebyshev(p) + c.chebyshev(p)));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let is_diagonal = |a: Point, b: Point| a.x != b.x && a.y != b.y;
+        for &(wire, (a, ad), b) in &segments {
+            if !is_diagonal(a, b) {
+                continue;
+            }
+            for &(other_wire, (c, cd), d) in &segments {
+                // Diagonal pairs are visited from both sides, only keep the first
+                if other_wire == wire || (wire != 0 && is_diagonal(c, d)) {
+                    continue;
+                }
+                for p in overlaps(a, b, c, d) {
+                    intersections.push((p, ad + cd + a.chebyshev(p) + c.chebyshev(p)));
+                }
+            }
+        }
+        intersections
+    }
+}
+
+/// Sweep line events, ordered so segments ending or starting on the same `x`
+/// as a vertical segment are still active when it is checked.
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum SweepEvent {
+    Enter,
+    Cross,
+    Leave,
 }
 
 #[aoc_generator(day3)]
 fn load_wires(input: &str) -> (Wire, Wire) {
+    parse_wires(input, str::parse)
+}
+
+/// Wires that may also run diagonally, which the puzzle's never do.
+fn load_wires_with_diagonals(input: &str) -> (Wire, Wire) {
+    parse_wires(input, Vector::parse_with_diagonals)
+}
+
+/// Both parts' answers for wires that may also run diagonally, the closest
+/// intersection's distance and the fewest combined steps to one.
+pub fn solve_with_diagonals(input: &str) -> (Option<i32>, Option<i32>) {
+    let wires = load_wires_with_diagonals(input);
+    (manhattan_distance(&wires), shortest_path_sweep(&wires))
+}
+
+fn parse_wires<F>(input: &str, vector: F) -> (Wire, Wire)
+where
+    F: Fn(&str) -> Result<Vector, ParseIntError>,
+{
     input
         .lines()
-        .map(|s| Wire::new(s.split(',').filter_map(|v| v.parse().ok()).collect()))
+        .map(|s| Wire::new(s.split(',').filter_map(|v| vector(v).ok()).collect()))
         .collect_tuple()
         .unwrap()
 }
 
+/// Export both wires of a puzzle input and their intersections as a GeoJSON
+/// `FeatureCollection`, using the grid coordinates as-is.
+///
+/// Wires become `LineString` features starting at the central port, and each
+/// intersection is a `Point` feature with its distance and combined steps.
+pub fn export_geojson(input: &str) -> String {
+    let (wire1, wire2) = load_wires(input);
+
+    let wires = [&wire1, &wire2];
+    let lines = wires.iter().enumerate().map(|(i, wire)| {
+        let coordinates = std::iter::once(Point::default())
+            .chain(wire.as_points())
+            .map(|p| format!("[{},{}]", p.x, p.y))
+            .join(",");
+        format!(
+            r#"{{"type":"Feature","properties":{{"wire":{}}},"geometry":{{"type":"LineString","coordinates":[{}]}}}}"#,
+            i + 1,
+            coordinates
+        )
+    });
+
+    let mut intersections = wire1.sweep_intersections(&wire2);
+    intersections.sort_by_key(|&(p, steps)| (p.x, p.y, steps));
+    let points = intersections.into_iter().map(|(p, steps)| {
+        format!(
+            r#"{{"type":"Feature","properties":{{"distance":{},"steps":{}}},"geometry":{{"type":"Point","coordinates":[{},{}]}}}}"#,
+            p.manhattan(Point::default()),
+            steps,
+            p.x,
+            p.y
+        )
+    });
+
+    format!(
+        r#"{{"type":"FeatureCollection","features":[{}]}}"#,
+        lines.chain(points).join(",")
+    )
+}
+
 #[aoc(day3, part1)]
 fn manhattan_distance((wire1, wire2): &(Wire, Wire)) -> Option<i32> {
     let origin = Point::default();
     wire1
-        .intersections(&wire2)
+        .intersections(wire2)
         .into_iter()
-        .map(|p| p.distance(origin))
+        .map(|p| p.manhattan(origin))
         .min()
 }
 
 #[aoc(day3, part2)]
 fn shortest_path((wire1, wire2): &(Wire, Wire)) -> Option<i32> {
-    wire1.intersection_lengths(&wire2).into_iter().min()
+    wire1.intersection_lengths(wire2).into_iter().min()
+}
+
+#[aoc(day3, part1, SweepLine)]
+fn manhattan_distance_sweep((wire1, wire2): &(Wire, Wire)) -> Option<i32> {
+    let origin = Point::default();
+    wire1
+        .sweep_intersections(wire2)
+        .into_iter()
+        .map(|(p, _)| p.manhattan(origin))
+        .min()
+}
+
+#[aoc(day3, part2, SweepLine)]
+fn shortest_path_sweep((wire1, wire2): &(Wire, Wire)) -> Option<i32> {
+    wire1
+        .sweep_intersections(wire2)
+        .into_iter()
+        .map(|(_, length)| length)
+        .min()
 }
 
 #[cfg(test)]
@@ -279,6 +539,116 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_diagonal() {
+        let vectors: Vec<Vector> = "UR10,DL3,U4,DR7,UL1"
+            .split(',')
+            .filter_map(|v| Vector::parse_with_diagonals(v).ok())
+            .collect();
+        assert_eq!(
+            vectors,
+            vec![
+                Vector::UpRight(10),
+                Vector::DownLeft(3),
+                Vector::Up(4),
+                Vector::DownRight(7),
+                Vector::UpLeft(1),
+            ]
+        );
+
+        // The puzzle's own parser still rejects them
+        assert!("UR10".parse::<Vector>().is_err());
+        assert_eq!("U4".parse(), Ok(Vector::Up(4)));
+    }
+
+    #[test]
+    fn test_overlap_diagonal() {
+        // Diagonal against vertical and horizontal segments
+        let (a, b) = (Point::new(0, 0), Point::new(10, 10));
+        assert_eq!(
+            overlap(a, b, Point::new(3, -5), Point::new(3, 5)),
+            Some(Point::new(3, 3))
+        );
+        assert_eq!(
+            overlap(a, b, Point::new(-5, 7), Point::new(20, 7)),
+            Some(Point::new(7, 7))
+        );
+        assert_eq!(overlap(a, b, Point::new(11, 0), Point::new(11, 20)), None);
+
+        // Perpendicular diagonals only count when they cross on a grid point
+        assert_eq!(
+            overlap(a, b, Point::new(0, 4), Point::new(4, 0)),
+            Some(Point::new(2, 2))
+        );
+        assert_eq!(overlap(a, b, Point::new(0, 3), Point::new(3, 0)), None);
+    }
+
+    #[test]
+    fn test_diagonal_wires() {
+        let (w1, w2) = load_wires_with_diagonals("R2,UR6,L10\nU3,R4,U5,DR4\n");
+        let mut expected = w1.intersections(&w2);
+        expected.sort_by_key(|p| (p.x, p.y));
+        assert_eq!(
+            expected,
+            vec![Point::new(4, 6), Point::new(6, 6), Point::new(7, 5)]
+        );
+
+        let mut found = w1
+            .sweep_intersections(&w2)
+            .into_iter()
+            .map(|(p, _)| p)
+            .collect_vec();
+        found.sort_by_key(|p| (p.x, p.y));
+        assert_eq!(found, expected);
+
+        let wires = (w1, w2);
+        assert_eq!(manhattan_distance(&wires), Some(10));
+        assert_eq!(shortest_path(&wires), Some(22));
+        assert_eq!(
+            solve_with_diagonals("R2,UR6,L10\nU3,R4,U5,DR4\n"),
+            (Some(10), Some(22))
+        );
+    }
+
+    #[test]
+    fn test_export_geojson() {
+        assert_eq!(
+            export_geojson("R2,U2\nU1,R3\n"),
+            concat!(
+                r#"{"type":"FeatureCollection","features":["#,
+                r#"{"type":"Feature","properties":{"wire":1},"geometry":{"type":"LineString","coordinates":[[0,0],[2,0],[2,2]]}},"#,
+                r#"{"type":"Feature","properties":{"wire":2},"geometry":{"type":"LineString","coordinates":[[0,0],[0,1],[3,1]]}},"#,
+                r#"{"type":"Feature","properties":{"distance":3,"steps":6},"geometry":{"type":"Point","coordinates":[2,1]}}"#,
+                r#"]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_sweep_intersections() {
+        for (wire1, wire2) in [(wire1(), wire2()), (wire3(), wire4())] {
+            let mut expected = wire1.intersections(&wire2);
+            let mut found = wire1
+                .sweep_intersections(&wire2)
+                .into_iter()
+                .map(|(p, _)| p)
+                .collect_vec();
+            expected.sort_by_key(|p| (p.x, p.y));
+            found.sort_by_key(|p| (p.x, p.y));
+            assert_eq!(found, expected);
+
+            let mut expected = wire1.intersection_lengths(&wire2);
+            let mut found = wire1
+                .sweep_intersections(&wire2)
+                .into_iter()
+                .map(|(_, length)| length)
+                .collect_vec();
+            expected.sort();
+            found.sort();
+            assert_eq!(found, expected);
+        }
+    }
+
     #[test]
     fn test_manhattan_distance() {
         let wires = (wire1(), wire2());
@@ -286,6 +656,7 @@ mod tests {
 
         let wires = (wire3(), wire4());
         assert_eq!(manhattan_distance(&wires), Some(135));
+        assert_eq!(manhattan_distance_sweep(&wires), Some(135));
     }
 
     #[test]
@@ -295,5 +666,66 @@ mod tests {
 
         let wires = (wire3(), wire4());
         assert_eq!(shortest_path(&wires), Some(410));
+        assert_eq!(shortest_path_sweep(&wires), Some(410));
+    }
+
+    #[test]
+    fn test_collinear_overlap() {
+        let p = Point::new;
+        assert_eq!(
+            overlaps(p(0, 0), p(5, 0), p(7, 0), p(3, 0)),
+            vec![p(3, 0), p(4, 0), p(5, 0)]
+        );
+        assert_eq!(
+            overlaps(p(0, 0), p(0, -4), p(0, -4), p(0, -9)),
+            vec![p(0, -4)]
+        );
+        assert_eq!(
+            overlaps(p(0, 0), p(-4, 4), p(-1, 1), p(-2, 2)),
+            vec![p(-1, 1), p(-2, 2)]
+        );
+        assert_eq!(overlaps(p(0, 0), p(5, 0), p(6, 0), p(9, 0)), vec![]);
+        assert_eq!(overlaps(p(0, 0), p(5, 0), p(0, 1), p(5, 1)), vec![]);
+        assert_eq!(overlaps(p(0, 0), p(4, 4), p(1, 0), p(5, 4)), vec![]);
+    }
+
+    #[test]
+    fn test_collinear_wires() {
+        // The wires run together along y = 2 from x = 3 to 6, and up x = 8
+        // from y = 2 to 6, meeting at the ends of the shared stretches more
+        // than once
+        let (w1, w2) = load_wires("U2,R2,R6,U5\nR3,U2,R3,D1,R2,U3,U2\n");
+        let mut expected = w1.intersections(&w2);
+        expected.sort_by_key(|p| (p.x, p.y));
+        expected.dedup();
+        let p = Point::new;
+        assert_eq!(
+            expected,
+            vec![
+                p(3, 2),
+                p(4, 2),
+                p(5, 2),
+                p(6, 2),
+                p(8, 2),
+                p(8, 3),
+                p(8, 4),
+                p(8, 5),
+                p(8, 6),
+            ]
+        );
+
+        let mut found = w1
+            .sweep_intersections(&w2)
+            .into_iter()
+            .map(|(p, _)| p)
+            .collect_vec();
+        found.sort_by_key(|p| (p.x, p.y));
+        found.dedup();
+        assert_eq!(found, expected);
+
+        // The first shared point is (3, 2), five steps along each wire
+        let wires = (w1, w2);
+        assert_eq!(shortest_path(&wires), Some(10));
+        assert_eq!(shortest_path_sweep(&wires), Some(10));
     }
 }
@@ -51,7 +51,7 @@ fn password_2_or_more(password: u32) -> bool {
 
 fn password_has_double(password: u32) -> bool {
     let heuristic = password_heuristic(password);
-    heuristic.iter().sum::<u8>() == 6 && heuristic.iter().any(|&c| c == 2)
+    heuristic.iter().sum::<u8>() == 6 && heuristic.contains(&2)
 }
 
 #[aoc_generator(day4)]
@@ -92,15 +92,15 @@ mod tests {
 
     #[test]
     fn test_part1_valid() {
-        assert_eq!(password_2_or_more(111111), true);
-        assert_eq!(password_2_or_more(223450), false);
-        assert_eq!(password_2_or_more(123789), false);
+        assert!(password_2_or_more(111111));
+        assert!(!password_2_or_more(223450));
+        assert!(!password_2_or_more(123789));
     }
 
     #[test]
     fn test_part2_valid() {
-        assert_eq!(password_has_double(112233), true);
-        assert_eq!(password_has_double(123444), false);
-        assert_eq!(password_has_double(111122), true);
+        assert!(password_has_double(112233));
+        assert!(!password_has_double(123444));
+        assert!(password_has_double(111122));
     }
 }
@@ -1,6 +1,9 @@
-use crate::intcode::{parse_program, IntcodeMachine};
-use itertools::Itertools;
-use std::sync::mpsc::channel;
+use crate::diagnostics;
+use crate::intcode::parse_program;
+use std::error::Error;
+
+const AIR_CONDITIONER: i64 = 1;
+const THERMAL_RADIATOR: i64 = 5;
 
 #[aoc_generator(day5)]
 fn load_program(input: &str) -> Vec<i64> {
@@ -8,23 +11,11 @@ fn load_program(input: &str) -> Vec<i64> {
 }
 
 #[aoc(day5, part1)]
-fn part1(program: &[i64]) -> String {
-    let (tx_input, rx_input) = channel();
-    let (tx_output, rx_output) = channel();
-    tx_input.send(1).unwrap();
-
-    let mut im = IntcodeMachine::new(program, Some(rx_input), Some(tx_output));
-    im.run();
-    format!("{:?}", rx_output.iter().collect_vec())
+fn part1(program: &[i64]) -> Result<i64, Box<dyn Error>> {
+    Ok(diagnostics::run(program, AIR_CONDITIONER)?.code()?)
 }
 
 #[aoc(day5, part2)]
-fn part2(program: &[i64]) -> String {
-    let (tx_input, rx_input) = channel();
-    let (tx_output, rx_output) = channel();
-    tx_input.send(5).unwrap();
-
-    let mut im = IntcodeMachine::new(program, Some(rx_input), Some(tx_output));
-    im.run();
-    format!("{:?}", rx_output.iter().collect_vec())
+fn part2(program: &[i64]) -> Result<i64, Box<dyn Error>> {
+    Ok(diagnostics::run(program, THERMAL_RADIATOR)?.code()?)
 }
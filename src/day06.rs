@@ -1,46 +1,23 @@
+use crate::tree::Tree;
 use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
-use std::iter::FromIterator;
 
-fn count_orbits(
-    memoizer: &mut HashMap<String, u32>,
-    orbits: &HashMap<String, String>,
-    planet: &str,
-) -> u32 {
-    if let Some(&m) = memoizer.get(planet) {
-        return m;
-    }
-    let v = if let Some(p) = orbits.get(planet) {
-        1 + count_orbits(memoizer, orbits, p)
-    } else {
-        0
-    };
-    memoizer.insert(planet.to_owned(), v);
-    v
+/// Orbital transfers needed to move from orbiting `a` to orbiting `b`.
+///
+/// Returns `None` if either object is unknown or the two are not connected.
+pub fn transfers_between(orbits: &Tree<String>, a: &str, b: &str) -> Option<u32> {
+    orbits.distance(a, b).map(|d| d as u32)
 }
 
-fn shortest_transfer(planets: &HashMap<&str, HashSet<&str>>, start: &str, end: &str) -> u32 {
-    let mut depth = 0;
-    let mut queue = vec![vec![start]];
-    let mut visited = HashSet::new();
-
-    'outer: while let Some(mut planet_set) = queue.pop() {
-        let mut next_set = HashSet::new();
-        while let Some(planet) = planet_set.pop() {
-            visited.insert(planet);
-            if planet == end {
-                break 'outer;
-            }
-            next_set.extend(planets[planet].iter());
-        }
-        queue.push(next_set.difference(&visited).cloned().collect());
-        depth += 1;
-    }
-    depth
+/// Answer many `(a, b)` transfer queries, in the order they were given.
+pub fn transfers_between_many(orbits: &Tree<String>, queries: &[(&str, &str)]) -> Vec<Option<u32>> {
+    queries
+        .iter()
+        .map(|&(a, b)| transfers_between(orbits, a, b))
+        .collect()
 }
 
 #[aoc_generator(day6)]
-fn load_orbits(input: &str) -> HashMap<String, String> {
+fn load_orbits(input: &str) -> Tree<String> {
     input
         .lines()
         .map(|s| {
@@ -52,31 +29,15 @@ fn load_orbits(input: &str) -> HashMap<String, String> {
 }
 
 #[aoc(day6, part1)]
-fn total_orbits(orbits: &HashMap<String, String>) -> u32 {
-    let mut memoizer: HashMap<String, u32> = HashMap::new();
-    let planets: HashSet<&str> = HashSet::from_iter(
-        orbits
-            .keys()
-            .map(|k| k.as_str())
-            .chain(orbits.values().map(|v| v.as_str())),
-    );
-    planets
-        .into_iter()
-        .map(|planet| count_orbits(&mut memoizer, orbits, planet))
-        .sum()
+fn total_orbits(orbits: &Tree<String>) -> u32 {
+    orbits.depths().values().sum::<usize>() as u32
 }
 
 #[aoc(day6, part2)]
-fn orbital_transfers(orbits: &HashMap<String, String>) -> u32 {
-    let planets: HashMap<&str, HashSet<&str>> =
-        orbits
-            .iter()
-            .fold(HashMap::new(), |mut acc, (parent, child)| {
-                acc.entry(parent).or_insert_with(HashSet::new).insert(child);
-                acc.entry(child).or_insert_with(HashSet::new).insert(parent);
-                acc
-            });
-    shortest_transfer(&planets, &orbits["YOU"], &orbits["SAN"])
+fn orbital_transfers(orbits: &Tree<String>) -> Option<u32> {
+    let you = orbits.parent("YOU")?;
+    let san = orbits.parent("SAN")?;
+    transfers_between(orbits, you, san)
 }
 
 #[cfg(test)]
@@ -93,6 +54,22 @@ mod tests {
     fn test_orbital_transfers() {
         let o =
             load_orbits("COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN\n");
-        assert_eq!(orbital_transfers(&o), 4);
+        assert_eq!(orbital_transfers(&o), Some(4));
+    }
+
+    #[test]
+    fn test_transfers_between() {
+        let o =
+            load_orbits("COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN\n");
+        assert_eq!(transfers_between(&o, "K", "I"), Some(4));
+        assert_eq!(transfers_between(&o, "H", "L"), Some(8));
+        assert_eq!(transfers_between(&o, "COM", "COM"), Some(0));
+        assert_eq!(transfers_between(&o, "COM", "Z"), None);
+        assert_eq!(transfers_between(&o, "Z", "COM"), None);
+
+        assert_eq!(
+            transfers_between_many(&o, &[("K", "I"), ("H", "L"), ("K", "COM"), ("Z", "B")]),
+            vec![Some(4), Some(8), Some(6), None]
+        );
     }
 }
@@ -1,110 +1,119 @@
+use crate::intcode::pipeline::{Pipeline, PipelineBuilder};
 use crate::intcode::{parse_program, IntcodeMachine};
 use itertools::Itertools;
-use std::sync::mpsc::{channel, Receiver, RecvError, SendError, Sender};
-use std::thread;
-use std::thread::JoinHandle;
-
-struct AmplificationCircuit {
-    amplifiers: Vec<JoinHandle<()>>,
-    inputs: Vec<Sender<i64>>,
-    output: Receiver<i64>,
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Duration;
+
+fn amplifiers(program: &[i64], phases: &[i64]) -> (PipelineBuilder, Vec<String>) {
+    let names = (0..phases.len())
+        .map(|i| format!("Amplifier {}", i))
+        .collect_vec();
+
+    let builder = names
+        .iter()
+        .zip(phases)
+        .fold(PipelineBuilder::new(), |builder, (name, &phase)| {
+            builder.machine(name, program).seed(name, &[phase])
+        });
+    (builder, names)
 }
 
-impl AmplificationCircuit {
-    fn new(program: &[i64], phases: Vec<i64>) -> Option<Self> {
-        let last_index = phases.len().checked_sub(1)?;
-
-        // Setup initial input channel for the chain
-        let (tx_input, rx_input) = channel();
-        let mut rx_output = None;
-
-        let (amplifiers, inputs): (Vec<_>, Vec<_>) = phases
-            .into_iter()
-            .enumerate()
-            .scan((Some(tx_input), Some(rx_input)), |(tx, rx), (i, v)| {
-                let builder = thread::Builder::new().name(format!("Amplifier {}", i));
-
-                // Setup an output for each instance
-                let (tx_link, rx_link) = channel();
-
-                let input = Some(rx.replace(rx_link)?);
-                let output = Some(tx_link.clone());
-                let mut im = IntcodeMachine::new(program, input, output);
-                let amplifier = builder
-                    .spawn(move || {
-                        im.run();
-                    })
-                    .unwrap();
-
-                // Seed amplifier initialization input
-                if let Some(sender) = tx {
-                    sender.send(v).ok()?;
-                }
-                // Grab the channel recv for the last amplifier
-                if i == last_index {
-                    rx_output = rx.take();
-                }
-
-                // Grab the channel send for the amplifier
-                tx.replace(tx_link).map(|sender| (amplifier, sender))
-            })
-            .unzip();
-
-        rx_output.map(|output| AmplificationCircuit {
-            amplifiers,
-            inputs,
-            output,
-        })
+fn feedback_circuit(
+    program: &[i64],
+    phases: &[i64],
+    timeout: Option<Duration>,
+) -> Option<Pipeline> {
+    let (builder, names) = amplifiers(program, phases);
+    let names = names.iter().map(|s| s.as_str()).collect_vec();
+    let (first, last) = (names.first()?, names.last()?);
+
+    let builder = builder.ring(&names).input("in", first).output(last, "out");
+    match timeout {
+        Some(timeout) => builder.timeout(timeout),
+        None => builder,
     }
+    .build()
+}
+
+/// Amplifier outputs by phase setting and input signal.
+///
+/// Without feedback an amplifier's output only depends on those two, so
+/// permutations that share a prefix of phases share its runs too.
+pub struct Amplifiers<'a> {
+    program: &'a [i64],
+    outputs: HashMap<(i64, i64), Option<i64>>,
+    runs: usize,
+    reused: usize,
+}
 
-    fn join_amplifies(self) -> Vec<thread::Result<()>> {
-        self.amplifiers
-            .into_iter()
-            .map(|amplifier| amplifier.join())
-            .collect()
+impl<'a> Amplifiers<'a> {
+    pub fn new(program: &'a [i64]) -> Self {
+        Amplifiers {
+            program,
+            outputs: HashMap::new(),
+            runs: 0,
+            reused: 0,
+        }
     }
 
-    fn send_input(&self, t: i64) -> Result<(), SendError<i64>> {
-        self.inputs
-            .first()
-            .ok_or_else(|| SendError(0))
-            .and_then(|sender| sender.send(t))
+    fn output(&mut self, phase: i64, input: i64) -> Option<i64> {
+        if let Some(&output) = self.outputs.get(&(phase, input)) {
+            self.reused += 1;
+            return output;
+        }
+        self.runs += 1;
+        let output = IntcodeMachine::evaluate(self.program, &[phase, input])
+            .ok()
+            .flatten();
+        self.outputs.insert((phase, input), output);
+        output
     }
 
-    fn recv_output(&self) -> Result<i64, RecvError> {
-        self.output.recv()
+    /// The signal out of amplifiers in series, starting from 0.
+    pub fn circuit(&mut self, phases: &[i64]) -> Option<i64> {
+        phases
+            .iter()
+            .try_fold(0, |signal, &phase| self.output(phase, signal))
     }
-}
 
-fn amplification_circuit(program: &[i64], phases: Vec<i64>) -> Option<i64> {
-    let circuit = AmplificationCircuit::new(&program, phases)?;
-    circuit.send_input(0).ok()?;
+    /// Amplifier programs run so far.
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
 
-    let last_output = circuit.recv_output();
-    circuit.join_amplifies();
-    last_output.ok()
+    /// Amplifier runs avoided by reusing an earlier output.
+    pub fn reused(&self) -> usize {
+        self.reused
+    }
 }
 
-fn feedback_loop(program: &[i64], phases: Vec<i64>) -> Option<i64> {
-    let circuit = AmplificationCircuit::new(&program, phases)?;
-    let mut last_output = 0;
-
-    // Send initial input
-    circuit.send_input(last_output).ok()?;
-
-    // Loop until we stop receiving output
-    while let Ok(output) = circuit.recv_output() {
-        last_output = output;
-
-        // Send next input and break if the amplifies have shutdown
-        if circuit.send_input(last_output).is_err() {
-            break;
-        }
+/// Run the amplifiers in a feedback loop. With a `timeout`, a loop where an
+/// amplifier waits longer than that for a signal is reported as deadlocked.
+fn feedback_loop(
+    program: &[i64],
+    phases: Vec<i64>,
+    timeout: Option<Duration>,
+) -> Result<i64, Box<dyn Error>> {
+    let mut circuit = feedback_circuit(program, &phases, timeout).ok_or("no amplifiers")?;
+
+    // Send initial input, the ring carries every value after that
+    circuit.send("in", 0)?;
+    circuit.close("in");
+
+    // Loop until the last amplifier stops producing output
+    let last_output = circuit.iter("out").last();
+    let stalled = circuit.stalled();
+    circuit.join();
+    if !stalled.is_empty() {
+        return Err(format!(
+            "deadlocked with phases {:?}: {}",
+            phases,
+            stalled.join(", ")
+        )
+        .into());
     }
-
-    circuit.join_amplifies();
-
-    Some(last_output)
+    Ok(last_output.ok_or("no signal")?)
 }
 
 #[aoc_generator(day7)]
@@ -114,9 +123,10 @@ fn load_program(input: &str) -> Vec<i64> {
 
 #[aoc(day7, part1)]
 fn max_amplification_circuit(program: &[i64]) -> Option<i64> {
+    let mut amplifiers = Amplifiers::new(program);
     (0..=4)
         .permutations(5)
-        .filter_map(|phases| amplification_circuit(&program, phases))
+        .filter_map(|phases| amplifiers.circuit(&phases))
         .max()
 }
 
@@ -124,13 +134,14 @@ fn max_amplification_circuit(program: &[i64]) -> Option<i64> {
 fn max_feedback_loop(program: &[i64]) -> Option<i64> {
     (5..=9)
         .permutations(5)
-        .filter_map(|phases| feedback_loop(program, phases))
+        .filter_map(|phases| feedback_loop(program, phases, None).ok())
         .max()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::intcode::asm::assemble;
 
     #[test]
     fn test_amplification_circuit() {
@@ -138,21 +149,42 @@ mod tests {
             3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
         ];
         let phases = vec![4, 3, 2, 1, 0];
-        assert_eq!(amplification_circuit(&program, phases), Some(43210));
+        assert_eq!(Amplifiers::new(&program).circuit(&phases), Some(43210));
 
         let program = vec![
             3, 23, 3, 24, 1002, 24, 10, 24, 1002, 23, -1, 23, 101, 5, 23, 23, 1, 24, 23, 23, 4, 23,
             99, 0, 0,
         ];
         let phases = vec![0, 1, 2, 3, 4];
-        assert_eq!(amplification_circuit(&program, phases), Some(54321));
+        assert_eq!(Amplifiers::new(&program).circuit(&phases), Some(54321));
 
         let program = vec![
             3, 31, 3, 32, 1002, 32, 10, 32, 1001, 31, -2, 31, 1007, 31, 0, 33, 1002, 33, 7, 33, 1,
             33, 31, 31, 1, 32, 31, 31, 4, 31, 99, 0, 0, 0,
         ];
         let phases = vec![1, 0, 4, 3, 2];
-        assert_eq!(amplification_circuit(&program, phases), Some(65210));
+        assert_eq!(Amplifiers::new(&program).circuit(&phases), Some(65210));
+    }
+
+    #[test]
+    fn test_reused_runs() {
+        let program = vec![
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        let mut amplifiers = Amplifiers::new(&program);
+        let best = (0..=4)
+            .permutations(5)
+            .filter_map(|phases| amplifiers.circuit(&phases))
+            .max();
+        assert_eq!(best, Some(43210));
+        assert_eq!(max_amplification_circuit(&program), best);
+
+        // Every permutation runs five amplifiers, but at most one machine
+        // runs per distinct prefix of phases, of which there are 325. Fewer
+        // still here, as phase 0 passes a signal of 0 along unchanged.
+        assert_eq!(amplifiers.runs() + amplifiers.reused(), 120 * 5);
+        assert_eq!(amplifiers.runs(), 261);
+        assert_eq!(amplifiers.reused(), 339);
     }
 
     #[test]
@@ -162,7 +194,7 @@ mod tests {
             28, 1005, 28, 6, 99, 0, 0, 5,
         ];
         let phases = vec![9, 8, 7, 6, 5];
-        assert_eq!(feedback_loop(&program, phases), Some(139629729));
+        assert_eq!(feedback_loop(&program, phases, None).unwrap(), 139629729);
 
         let program = vec![
             3, 52, 1001, 52, -5, 52, 3, 53, 1, 52, 56, 54, 1007, 54, 5, 55, 1005, 55, 26, 1001, 54,
@@ -170,6 +202,45 @@ mod tests {
             53, 1001, 56, -1, 56, 1005, 56, 6, 99, 0, 0, 0, 0, 10,
         ];
         let phases = vec![9, 7, 8, 5, 6];
-        assert_eq!(feedback_loop(&program, phases), Some(18216));
+        assert_eq!(feedback_loop(&program, phases, None).unwrap(), 18216);
+    }
+
+    #[test]
+    fn test_deadlock() {
+        // Each amplifier wants a third value, which nothing ever sends.
+        // Whichever times out first closes its link and the rest follow
+        let program = vec![3, 0, 3, 0, 3, 0, 99];
+        let phases = vec![5, 6, 7, 8, 9];
+        let error = feedback_loop(&program, phases, Some(Duration::from_millis(20))).unwrap_err();
+        assert!(error.to_string().starts_with("deadlocked"));
+    }
+
+    #[test]
+    fn test_failing_phases() {
+        // Amplifiers double their input and add their phase, except that
+        // phase 9 faults when it goes first. Those permutations are skipped
+        // rather than ending the search
+        let program = assemble(
+            "      in [p]
+                   in [x]
+                   jnz [x], ok
+                   eq [p], 9, [t]
+                   jnz [t], bad
+             ok:   mul [x], 2, [x]
+                   add [x], [p], [x]
+                   out [x]
+                   hlt
+             bad:  .data 42
+             p:    .data 0
+             x:    .data 0
+             t:    .data 0",
+        )
+        .unwrap();
+        assert!(feedback_loop(&program, vec![9, 8, 7, 6, 5], None).is_err());
+        assert_eq!(
+            feedback_loop(&program, vec![8, 9, 7, 6, 5], None).unwrap(),
+            245
+        );
+        assert_eq!(max_feedback_loop(&program), Some(245));
     }
 }
@@ -1,14 +1,100 @@
+use crate::geometry::Point2;
+use crate::grid::Grid;
 use ansi_term::Color;
 use itertools::Itertools;
+use std::collections::HashMap;
 
 const IMG_W: usize = 25;
 const IMG_H: usize = 6;
 const PIXEL: &str = "█";
+const TRANSPARENT: u8 = 2;
+
+/// How each decoded pixel value is drawn.
+///
+/// Values are mapped to a glyph with an optional terminal color, so images can
+/// be rendered with any colors, as plain text, or with more than two visible
+/// values. Values without a mapping are drawn as `?`.
+#[derive(Clone, Debug)]
+pub struct Palette {
+    swatches: HashMap<u8, (Option<Color>, String)>,
+    transparent: u8,
+}
+
+impl Palette {
+    /// An empty palette, treating `2` as transparent.
+    pub fn empty() -> Self {
+        Palette {
+            swatches: HashMap::new(),
+            transparent: TRANSPARENT,
+        }
+    }
+
+    /// Uncolored glyphs, readable without color and when copied as text.
+    pub fn text() -> Self {
+        Palette::empty().glyph(0, None, " ").glyph(1, None, "#")
+    }
+
+    /// Draw `value` as `glyph`, painted in `color` when given.
+    pub fn glyph(mut self, value: u8, color: Option<Color>, glyph: &str) -> Self {
+        self.swatches.insert(value, (color, glyph.to_owned()));
+        self
+    }
+
+    /// Change which value lets lower layers show through.
+    pub fn transparent(mut self, value: u8) -> Self {
+        self.transparent = value;
+        self
+    }
+
+    fn paint(&self, pixel: u8) -> String {
+        match self.swatches.get(&pixel) {
+            Some((Some(color), glyph)) => color.paint(glyph.as_str()).to_string(),
+            Some((None, glyph)) => glyph.to_owned(),
+            None => String::from("?"),
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::empty()
+            .glyph(0, Some(Color::Black), PIXEL)
+            .glyph(1, Some(Color::White), PIXEL)
+    }
+}
 
 fn image_layers(image: &[u8], width: usize, height: usize) -> Vec<Vec<u8>> {
     image.chunks(width * height).map(|c| c.to_owned()).collect()
 }
 
+fn composite_image(image: &[u8], width: usize, height: usize, transparent: u8) -> Grid<u8> {
+    let mut composite = Grid::new(width, height, transparent);
+    for layer in image_layers(image, width, height) {
+        for (i, pixel) in layer.into_iter().enumerate() {
+            let cell = &mut composite[Point2::new(i % width, i / width)];
+            if *cell == transparent {
+                *cell = pixel;
+            }
+        }
+    }
+    composite
+}
+
+/// Flatten the image layers and draw the result with the given palette.
+pub fn render_image(image: &[u8], width: usize, height: usize, palette: &Palette) -> String {
+    let composite_image = composite_image(image, width, height, palette.transparent);
+
+    let mut output = String::from("\n\n");
+    output.extend(composite_image.rows().map(|row| {
+        let mut line = String::from("\t");
+        line.extend(row.iter().map(|&pixel| palette.paint(pixel)));
+        line.push('\n');
+        line
+    }));
+    output.push('\n');
+    output
+}
+
 #[aoc_generator(day8)]
 fn load_image(input: &str) -> Vec<u8> {
     input
@@ -38,40 +124,7 @@ fn image_checksum(image: &[u8]) -> Option<u32> {
 
 #[aoc(day8, part2)]
 fn image_decode(image: &[u8]) -> String {
-    let composite_image =
-        image_layers(image, IMG_W, IMG_H)
-            .into_iter()
-            .fold([2; IMG_W * IMG_H], |mut acc, layer| {
-                for (i, pixel) in layer.into_iter().enumerate() {
-                    if acc[i] == 2 {
-                        acc[i] = pixel;
-                    }
-                }
-                acc
-            });
-
-    let mut output = String::from("\n\n");
-    let output_lines = composite_image
-        .iter()
-        .map(|pixel| {
-            match pixel {
-                0 => Color::Black,
-                1 => Color::White,
-                _ => unreachable!(),
-            }
-            .paint(PIXEL)
-            .to_string()
-        })
-        .chunks(IMG_W);
-
-    output.extend(output_lines.into_iter().map(|row| {
-        let mut line = String::from("\t");
-        line.extend(row);
-        line.push('\n');
-        line
-    }));
-    output.push('\n');
-    output
+    render_image(image, IMG_W, IMG_H, &Palette::default())
 }
 
 #[cfg(test)]
@@ -93,4 +146,41 @@ mod tests {
             vec![vec![1, 2, 3, 4, 5, 6], vec![7, 8, 9, 0, 1, 2],]
         );
     }
+
+    #[test]
+    fn test_render_image() {
+        let image = load_image("0222112222120000\n");
+        assert_eq!(composite_image(&image, 2, 2, 2).to_string(), "01\n10\n");
+        assert_eq!(
+            render_image(&image, 2, 2, &Palette::text()),
+            "\n\n\t #\n\t# \n\n"
+        );
+        assert_eq!(
+            render_image(&image, 2, 2, &Palette::empty()),
+            "\n\n\t??\n\t??\n\n"
+        );
+
+        let palette = Palette::default();
+        assert_eq!(
+            render_image(&image, 2, 2, &palette),
+            format!(
+                "\n\n\t{}{}\n\t{}{}\n\n",
+                Color::Black.paint(PIXEL),
+                Color::White.paint(PIXEL),
+                Color::White.paint(PIXEL),
+                Color::Black.paint(PIXEL)
+            )
+        );
+
+        // Three visible values, with 9 letting lower layers through
+        let image = vec![9, 2, 3, 9, 1, 1, 1, 9];
+        let palette = Palette::text()
+            .glyph(2, Some(Color::RGB(0, 114, 178)), "+")
+            .glyph(3, None, "*")
+            .transparent(9);
+        assert_eq!(
+            render_image(&image, 2, 2, &palette),
+            format!("\n\n\t#{}\n\t*?\n\n", Color::RGB(0, 114, 178).paint("+"))
+        );
+    }
 }
@@ -1,5 +1,9 @@
-use crate::intcode::{parse_program, IntcodeMachine};
-use std::sync::mpsc::{channel, RecvError};
+use crate::diagnostics;
+use crate::intcode::parse_program;
+use std::error::Error;
+
+const TEST_MODE: i64 = 1;
+const SENSOR_BOOST: i64 = 2;
 
 #[aoc_generator(day9)]
 fn load_program(input: &str) -> Vec<i64> {
@@ -7,27 +11,11 @@ fn load_program(input: &str) -> Vec<i64> {
 }
 
 #[aoc(day9, part1)]
-fn part1(program: &[i64]) -> Result<i64, RecvError> {
-    let (tx_input, rx_input) = channel();
-    let (tx_output, rx_output) = channel();
-
-    tx_input.send(1).unwrap();
-
-    let mut im = IntcodeMachine::new(program, Some(rx_input), Some(tx_output));
-    im.run();
-
-    rx_output.recv()
+fn part1(program: &[i64]) -> Result<i64, Box<dyn Error>> {
+    Ok(diagnostics::run(program, TEST_MODE)?.code()?)
 }
 
 #[aoc(day9, part2)]
-fn part2(program: &[i64]) -> Result<i64, RecvError> {
-    let (tx_input, rx_input) = channel();
-    let (tx_output, rx_output) = channel();
-
-    tx_input.send(2).unwrap();
-
-    let mut im = IntcodeMachine::new(program, Some(rx_input), Some(tx_output));
-    im.run();
-
-    rx_output.recv()
+fn part2(program: &[i64]) -> Result<i64, Box<dyn Error>> {
+    Ok(diagnostics::run(program, SENSOR_BOOST)?.code()?)
 }
@@ -1,62 +1,200 @@
+use crate::geometry::Point2;
+use crate::grid::Grid;
+use ansi_term::Style;
 use itertools::Itertools;
+use num::integer::gcd;
+use std::cmp::{Ordering, Reverse};
 use std::collections::HashMap;
-use std::convert::TryInto;
-
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
-struct Point {
-    x: i32,
-    y: i32,
-}
-
-impl Point {
-    fn angle(self, other: Self) -> f64 {
-        let delta_y: f64 = (self.y - other.y).into();
-        let delta_x: f64 = (other.x - self.x).into();
-        let result = delta_x.atan2(delta_y).to_degrees();
-        if result < 0.0 {
-            result + 360.0
-        } else {
-            result
-        }
-    }
+use std::convert::{TryFrom, TryInto};
 
-    fn distance(self, other: Self) -> i32 {
-        (self.x - other.x).abs() + (self.y - other.y).abs()
-    }
+pub type Point = Point2<i32>;
+
+/// The smallest step from `from` towards `to` that lands on whole
+/// coordinates, the same for everything on one line of sight.
+fn direction(from: Point, to: Point) -> Point {
+    let delta = to - from;
+    let divisor = gcd(delta.x, delta.y).max(1);
+    Point::new(delta.x / divisor, delta.y / divisor)
+}
+
+/// Orders directions by bearing, clockwise from straight up the map.
+fn clockwise(a: &Point, b: &Point) -> Ordering {
+    // Up and everything right of it comes before everything left of it
+    let half = |d: &Point| d.x < 0 || (d.x == 0 && d.y > 0);
+    half(a)
+        .cmp(&half(b))
+        .then_with(|| 0.cmp(&(a.x * b.y - a.y * b.x)))
+}
+
+/// Asteroids a station at `station` has a line of sight to.
+pub fn visible_count(asteroids: &[Point], station: Point) -> usize {
+    asteroids
+        .iter()
+        .filter(|&&asteroid| asteroid != station)
+        .map(|&asteroid| direction(station, asteroid))
+        .unique()
+        .count()
 }
 
 fn asteroid_with_max_los(asteroids: &[Point]) -> Option<(Point, usize)> {
     asteroids
         .iter()
-        .map(|&origin| {
-            let count = asteroids
-                .iter()
-                .map(|&asteroid| (origin.angle(asteroid) * 100_000.0) as i32)
-                .unique()
-                .count();
-            (origin, count)
-        })
+        .map(|&origin| (origin, visible_count(asteroids, origin)))
         .max_by(|&a, &b| a.1.cmp(&b.1))
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Candidate {
+    pub station: Point,
+    pub visible: usize,
+}
+
+/// Every asteroid as a station, the most asteroids visible first and ties in
+/// reading order.
+pub fn rank_stations(asteroids: &[Point]) -> Vec<Candidate> {
+    asteroids
+        .iter()
+        .map(|&station| Candidate {
+            station,
+            visible: visible_count(asteroids, station),
+        })
+        .sorted_by_key(|c| (Reverse(c.visible), c.station.y, c.station.x))
+        .collect()
+}
+
+/// How a station's count changes without some asteroid.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Removal {
+    pub station: Point,
+    pub before: usize,
+    pub after: usize,
+}
+
+/// What every other station sees once `removed` is gone, ranked as by
+/// [`rank_stations`] afterwards.
+///
+/// A station loses one only if nothing was hidden behind the removed
+/// asteroid.
+pub fn without_asteroid(asteroids: &[Point], removed: Point) -> Vec<Removal> {
+    let remaining = asteroids
+        .iter()
+        .copied()
+        .filter(|&a| a != removed)
+        .collect_vec();
+    rank_stations(&remaining)
+        .into_iter()
+        .map(|c| Removal {
+            station: c.station,
+            before: visible_count(asteroids, c.station),
+            after: c.visible,
+        })
+        .collect()
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Visibility {
+    Station,
+    Visible,
+    /// Hidden behind the next nearer asteroid on the same line of sight.
+    Blocked {
+        by: Point,
+    },
+}
+
+/// What a monitoring station can and can't see.
+pub struct VisibilityMap {
+    pub station: Point,
+    pub asteroids: HashMap<Point, Visibility>,
+}
+
+impl VisibilityMap {
+    pub fn new(asteroids: &[Point], station: Point) -> Self {
+        let mut rays = HashMap::new();
+        for &asteroid in asteroids.iter().filter(|&&a| a != station) {
+            rays.entry(direction(station, asteroid))
+                .or_insert_with(Vec::new)
+                .push(asteroid);
+        }
+
+        let mut map = HashMap::new();
+        map.insert(station, Visibility::Station);
+        for ray in rays.values_mut() {
+            ray.sort_by_key(|a| a.manhattan(station));
+            map.insert(ray[0], Visibility::Visible);
+            for pair in ray.windows(2) {
+                map.insert(pair[1], Visibility::Blocked { by: pair[0] });
+            }
+        }
+        VisibilityMap {
+            station,
+            asteroids: map,
+        }
+    }
+
+    pub fn visible(&self) -> impl Iterator<Item = Point> + '_ {
+        self.asteroids
+            .iter()
+            .filter(|(_, &v)| v == Visibility::Visible)
+            .map(|(&p, _)| p)
+    }
+
+    /// Draw the map with the station as `X`, visible asteroids as `#` and
+    /// blocked ones as `o`, or dimmed `#` when colored.
+    pub fn render(&self, color: bool) -> String {
+        // Asteroids off the top or left of the map aren't drawn
+        let on_map = self
+            .asteroids
+            .iter()
+            .filter_map(|(p, &v)| {
+                let x = usize::try_from(p.x).ok()?;
+                let y = usize::try_from(p.y).ok()?;
+                Some((Point2::new(x, y), v))
+            })
+            .collect_vec();
+        let width = on_map.iter().map(|(p, _)| p.x).max().unwrap_or(0) + 1;
+        let height = on_map.iter().map(|(p, _)| p.y).max().unwrap_or(0) + 1;
+        let mut grid = Grid::new(width, height, None);
+        for (position, visibility) in on_map {
+            grid[position] = Some(visibility);
+        }
+
+        let lines = grid
+            .rows()
+            .map(|row| {
+                row.iter()
+                    .map(|visibility| match visibility {
+                        Some(Visibility::Station) if color => Style::new().bold().paint("X"),
+                        Some(Visibility::Station) => "X".into(),
+                        Some(Visibility::Visible) => "#".into(),
+                        Some(Visibility::Blocked { .. }) if color => {
+                            Style::new().dimmed().paint("#")
+                        }
+                        Some(Visibility::Blocked { .. }) => "o".into(),
+                        None => ".".into(),
+                    })
+                    .join("")
+            })
+            .collect_vec();
+        lines.join("\n")
+    }
+}
+
+/// The visibility map from the best place for a monitoring station.
+pub fn station_visibility(asteroids: &[Point]) -> Option<VisibilityMap> {
+    let (station, _) = asteroid_with_max_los(asteroids)?;
+    Some(VisibilityMap::new(asteroids, station))
+}
+
 #[aoc_generator(day10)]
 fn load_map(input: &str) -> Vec<Point> {
-    input
-        .lines()
-        .enumerate()
-        .map(|(y, s)| {
-            s.chars()
-                .enumerate()
-                .filter_map(|(x, c)| match c {
-                    '#' => Some(Point {
-                        x: x.try_into().unwrap(),
-                        y: y.try_into().unwrap(),
-                    }),
-                    _ => None,
-                })
-                .collect_vec()
+    Grid::parse(input, |c| Some(c == '#'))
+        .unwrap()
+        .iter()
+        .filter(|&(_, &asteroid)| asteroid)
+        .map(|(p, _)| Point {
+            x: p.x.try_into().unwrap(),
+            y: p.y.try_into().unwrap(),
         })
-        .flatten()
         .collect()
 }
 
@@ -66,31 +204,41 @@ fn max_los(asteroids: &[Point]) -> Option<usize> {
     Some(max)
 }
 
-#[aoc(day10, part2)]
-fn two_hundredth_asteroid(asteroids: &[Point]) -> Option<i32> {
-    let (origin, _) = asteroid_with_max_los(asteroids)?;
-
-    let mut radial_map = asteroids.iter().fold(HashMap::new(), |mut acc, &asteroid| {
-        let ang = (origin.angle(asteroid) * 100_000.0) as i32;
-        acc.entry(ang).or_insert_with(Vec::new).push(asteroid);
-        acc
-    });
+/// Every other asteroid in the order a laser at `origin` vaporizes them,
+/// sweeping clockwise from straight up and hitting the nearest on each line
+/// of sight per rotation.
+pub fn vaporization_order(asteroids: &[Point], origin: Point) -> impl Iterator<Item = Point> {
+    let mut radial_map = asteroids
+        .iter()
+        .filter(|&&asteroid| asteroid != origin)
+        .fold(HashMap::new(), |mut acc, &asteroid| {
+            let ray = direction(origin, asteroid);
+            acc.entry(ray).or_insert_with(Vec::new).push(asteroid);
+            acc
+        });
 
     // Sort all asteroids in descending order by distance from origin
     radial_map.values_mut().for_each(|v| {
-        v.sort_by(|a, b| b.distance(origin).cmp(&a.distance(origin)));
+        v.sort_by_key(|a| Reverse(a.manhattan(origin)));
     });
 
-    // Copy all the radians, so we can run a cycling iteration on it
-    let mut radians = radial_map.keys().copied().collect_vec();
-    radians.sort();
+    let mut rays = radial_map.into_iter().collect_vec();
+    rays.sort_by(|a, b| clockwise(&a.0, &b.0));
 
-    radians
-        .iter()
-        .cycle()
-        .filter_map(|ang| radial_map.get_mut(ang)?.pop())
-        .take(200)
-        .last()
+    let mut order = Vec::new();
+    while !rays.is_empty() {
+        order.extend(rays.iter_mut().filter_map(|(_, ray)| ray.pop()));
+        rays.retain(|(_, ray)| !ray.is_empty());
+    }
+    order.into_iter()
+}
+
+#[aoc(day10, part2)]
+fn two_hundredth_asteroid(asteroids: &[Point]) -> Option<i32> {
+    let (origin, _) = asteroid_with_max_los(asteroids)?;
+
+    vaporization_order(asteroids, origin)
+        .nth(199)
         .map(|asteroid| asteroid.x * 100 + asteroid.y)
 }
 
@@ -120,12 +268,48 @@ mod tests {
     }
 
     #[test]
-    fn test_find_angle() {
+    fn test_direction() {
         let a = Point { x: 0, y: 0 };
-        let b = Point { x: 1, y: 1 };
+        let b = Point { x: 2, y: 2 };
 
-        assert_eq!(a.angle(b), 135.0);
-        assert_eq!(b.angle(a), 315.0);
+        assert_eq!(direction(a, b), Point { x: 1, y: 1 });
+        assert_eq!(direction(b, a), Point { x: -1, y: -1 });
+        assert_eq!(direction(a, Point { x: 0, y: -6 }), Point { x: 0, y: -1 });
+
+        // Up, then round clockwise through each quadrant
+        let bearings = vec![
+            Point { x: 0, y: -1 },
+            Point { x: 1, y: -3 },
+            Point { x: 1, y: 0 },
+            Point { x: 1, y: 1 },
+            Point { x: 0, y: 1 },
+            Point { x: -1, y: 1 },
+            Point { x: -1, y: 0 },
+            Point { x: -1000, y: -999 },
+            Point { x: -999, y: -1000 },
+        ];
+        let mut shuffled = bearings.iter().rev().copied().collect_vec();
+        shuffled.sort_by(clockwise);
+        assert_eq!(shuffled, bearings);
+    }
+
+    #[test]
+    fn test_visibility_map() {
+        let map = load_map(".#..#\n.....\n#####\n....#\n...##\n");
+        let visibility = station_visibility(&map).unwrap();
+
+        assert_eq!(visibility.station, Point { x: 3, y: 4 });
+        assert_eq!(visibility.visible().count(), 8);
+        assert_eq!(
+            visibility.asteroids[&Point { x: 1, y: 0 }],
+            Visibility::Blocked {
+                by: Point { x: 2, y: 2 }
+            }
+        );
+        assert_eq!(
+            visibility.render(false),
+            ".o..#\n.....\n#####\n....#\n...X#"
+        );
     }
 
     #[test]
@@ -150,4 +334,71 @@ mod tests {
         );
         assert_eq!(max_los(&map), Some(210));
     }
+
+    #[test]
+    fn test_part2() {
+        let map = load_map(
+            ".#..##.###...#######\n##.############..##.\n.#.######.########.#\n.###.#######.####.#.\n#####.##.#.##.###.##\n..#####..#.#########\n####################\n#.####....###.#.#.##\n##.#################\n#####.##.###..####..\n..######..##.#######\n####.##.####...##..#\n.#####..#.######.###\n##...#.##########...\n#.##########.#######\n.####.#.###.###.#.##\n....##.##.###..#####\n.#.#.###########.###\n#.#.#.#####.####.###\n###.##.####.##.#..##\n",
+        );
+        assert_eq!(two_hundredth_asteroid(&map), Some(802));
+
+        let order = vaporization_order(&map, Point { x: 11, y: 13 }).collect_vec();
+        assert_eq!(order.len(), 299);
+        for &(n, x, y) in &[
+            (1, 11, 12),
+            (2, 12, 1),
+            (3, 12, 2),
+            (10, 12, 8),
+            (20, 16, 0),
+            (50, 16, 9),
+            (100, 10, 16),
+            (199, 9, 6),
+            (200, 8, 2),
+            (201, 10, 9),
+            (299, 11, 1),
+        ] {
+            assert_eq!(order[n - 1], Point { x, y }, "{}th asteroid", n);
+        }
+    }
+
+    #[test]
+    fn test_rank_stations() {
+        let map = load_map(".#..#\n.....\n#####\n....#\n...##\n");
+        let ranking = rank_stations(&map);
+        assert_eq!(ranking.len(), map.len());
+        assert_eq!(
+            ranking[..3],
+            [
+                Candidate {
+                    station: Point { x: 3, y: 4 },
+                    visible: 8
+                },
+                Candidate {
+                    station: Point { x: 1, y: 0 },
+                    visible: 7
+                },
+                Candidate {
+                    station: Point { x: 4, y: 0 },
+                    visible: 7
+                },
+            ]
+        );
+        assert_eq!(ranking.last().unwrap().visible, 5);
+
+        // The best station sees (2, 2) with (1, 0) behind it, so losing it
+        // costs nothing, but losing (4, 3) does
+        let removal = without_asteroid(&map, Point { x: 2, y: 2 });
+        assert_eq!(removal.len(), map.len() - 1);
+        assert_eq!(
+            removal[0],
+            Removal {
+                station: Point { x: 3, y: 4 },
+                before: 8,
+                after: 8
+            }
+        );
+        let removal = without_asteroid(&map, Point { x: 4, y: 3 });
+        let best = removal.iter().find(|r| r.station == Point { x: 3, y: 4 });
+        assert_eq!(best.map(|r| (r.before, r.after)), Some((8, 7)));
+    }
 }
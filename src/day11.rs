@@ -1,14 +1,18 @@
+use crate::geometry::{Direction, Point2};
+use crate::grid::Grid;
 use crate::intcode::{parse_program, IntcodeMachine};
 use ansi_term::Color as TermColor;
 use itertools::Itertools;
-use std::collections::HashMap;
-use std::sync::mpsc::channel;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::thread;
 
 const PIXEL: &str = "█";
 
-#[derive(Copy, Clone)]
-enum Color {
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Color {
     Black,
     White,
 }
@@ -32,55 +36,22 @@ impl From<Color> for i64 {
     }
 }
 
-#[derive(Copy, Clone)]
-enum Direction {
-    Up,
-    Down,
+pub type Point = Point2<i32>;
+
+enum Rotation {
     Left,
     Right,
 }
 
-impl Direction {
-    fn translate_rotation(&mut self, rotation: Rotation) {
-        *self = match rotation {
-            Rotation::Left => match self {
-                Direction::Up => Direction::Left,
-                Direction::Down => Direction::Right,
-                Direction::Left => Direction::Down,
-                Direction::Right => Direction::Up,
-            },
-            Rotation::Right => match self {
-                Direction::Up => Direction::Right,
-                Direction::Down => Direction::Left,
-                Direction::Left => Direction::Up,
-                Direction::Right => Direction::Down,
-            },
-        };
-    }
-}
-
-#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, PartialOrd)]
-struct Point {
-    x: i32,
-    y: i32,
-}
-
-impl Point {
-    fn translate_direction(&mut self, direction: Direction) {
-        match direction {
-            Direction::Up => self.y += 1,
-            Direction::Right => self.x += 1,
-            Direction::Down => self.y -= 1,
-            Direction::Left => self.x -= 1,
+impl Rotation {
+    fn turn(self, direction: Direction) -> Direction {
+        match self {
+            Rotation::Left => direction.turn_left(),
+            Rotation::Right => direction.turn_right(),
         }
     }
 }
 
-enum Rotation {
-    Left,
-    Right,
-}
-
 impl From<i64> for Rotation {
     fn from(rotation: i64) -> Self {
         match rotation {
@@ -91,28 +62,29 @@ impl From<i64> for Rotation {
     }
 }
 
-fn hull_painting_robot(program: &[i64], input: Color) -> HashMap<Point, Color> {
+/// Run the robot from the origin of `hull`, returning the hull with every
+/// panel it painted or looked at.
+pub fn paint_hull(program: &[i64], hull: HashMap<Point, Color>) -> HashMap<Point, Color> {
     let (tx_input, rx_input) = channel();
     let (tx_output, rx_output) = channel();
 
-    tx_input.send(input.into()).unwrap();
-
-    let mut robot = IntcodeMachine::new(program, Some(rx_input), Some(tx_output));
-    thread::spawn(move || {
-        robot.run();
-    });
-
-    let mut painted = HashMap::new();
+    let mut painted = hull;
     let mut direction = Direction::Up;
     let mut origin = Point::default();
 
+    let panel = painted.get(&origin).copied().unwrap_or(Color::Black);
+    tx_input.send(panel.into()).unwrap();
+
+    let mut robot = IntcodeMachine::new(program, Some(rx_input), Some(tx_output));
+    thread::spawn(move || robot.run());
+
     while let (Ok(color), Ok(rotation)) = (rx_output.recv(), rx_output.recv()) {
         // Set the painted color for the current position
         *painted.entry(origin.to_owned()).or_insert(Color::Black) = color.into();
 
         // Move to the next position
-        direction.translate_rotation(rotation.into());
-        origin.translate_direction(direction);
+        direction = Rotation::from(rotation).turn(direction);
+        origin += direction.offset();
 
         // Find the input color of the next position
         let panel = *painted.entry(origin.to_owned()).or_insert(Color::Black);
@@ -124,6 +96,189 @@ fn hull_painting_robot(program: &[i64], input: Color) -> HashMap<Point, Color> {
     painted
 }
 
+fn hull_painting_robot(program: &[i64], input: Color) -> HashMap<Point, Color> {
+    let mut hull = HashMap::new();
+    hull.insert(Point::default(), input);
+    paint_hull(program, hull)
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseHullError(String);
+
+impl Error for ParseHullError {}
+
+impl fmt::Display for ParseHullError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid panel {:?}", self.0)
+    }
+}
+
+/// Write a hull one panel per line as `x,y,color`, with the robot's color
+/// codes, from the top row down.
+pub fn export_hull(hull: &HashMap<Point, Color>) -> String {
+    hull.iter()
+        .sorted_by_key(|(point, _)| (-point.y, point.x))
+        .map(|(point, &color)| format!("{},{},{}\n", point.x, point.y, i64::from(color)))
+        .collect()
+}
+
+/// Read a hull written by [`export_hull`], skipping blank lines.
+pub fn import_hull(s: &str) -> Result<HashMap<Point, Color>, ParseHullError> {
+    s.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let error = || ParseHullError(line.to_owned());
+            let (x, y, color) = line.split(',').collect_tuple().ok_or_else(error)?;
+            let color = match color.trim() {
+                "0" => Color::Black,
+                "1" => Color::White,
+                _ => return Err(error()),
+            };
+            let x = x.trim().parse().map_err(|_| error())?;
+            let y = y.trim().parse().map_err(|_| error())?;
+            Ok((Point { x, y }, color))
+        })
+        .collect()
+}
+
+/// Which paint sticks when several robots paint the same panel in one round.
+#[derive(Copy, Clone, Debug)]
+pub enum ConflictPolicy {
+    /// The robot listed first wins.
+    First,
+    /// The robot listed last wins.
+    Last,
+    /// White wins if any robot painted white.
+    White,
+    /// Black wins if any robot painted black.
+    Black,
+}
+
+impl ConflictPolicy {
+    fn resolve(self, paints: &[Color]) -> Option<Color> {
+        match self {
+            ConflictPolicy::First => paints.first().copied(),
+            ConflictPolicy::Last => paints.last().copied(),
+            ConflictPolicy::White => paints
+                .iter()
+                .find(|&&c| c == Color::White)
+                .or_else(|| paints.first())
+                .copied(),
+            ConflictPolicy::Black => paints
+                .iter()
+                .find(|&&c| c == Color::Black)
+                .or_else(|| paints.first())
+                .copied(),
+        }
+    }
+}
+
+/// Outcome of several robots painting the same hull.
+#[derive(Debug)]
+pub struct SwarmReport {
+    /// Final color of every panel on the hull.
+    pub hull: HashMap<Point, Color>,
+    /// Number of distinct panels each robot painted, in robot order.
+    pub painted: Vec<usize>,
+    /// Number of distinct panels painted by any robot.
+    pub coverage: usize,
+    /// Number of times robots painted the same panel in the same round.
+    pub conflicts: usize,
+}
+
+struct Robot {
+    input: Sender<i64>,
+    output: Receiver<i64>,
+    position: Point,
+    direction: Direction,
+}
+
+/// Run several painting robots on one hull, each with its own program and
+/// starting panel.
+///
+/// Robots take turns in rounds: every robot still running paints and turns,
+/// paints on the same panel are resolved with `policy`, and then every robot
+/// moves and reads the updated hull through its camera.
+pub fn hull_painting_swarm(
+    robots: &[(&[i64], Point)],
+    hull: HashMap<Point, Color>,
+    policy: ConflictPolicy,
+) -> SwarmReport {
+    let mut hull = hull;
+    let mut painted = vec![HashSet::new(); robots.len()];
+    let mut conflicts = 0;
+
+    let mut swarm = robots
+        .iter()
+        .enumerate()
+        .map(|(i, &(program, position))| {
+            let (tx_input, rx_input) = channel();
+            let (tx_output, rx_output) = channel();
+
+            let panel = hull.get(&position).copied().unwrap_or(Color::Black);
+            tx_input.send(panel.into()).unwrap();
+
+            let mut robot = IntcodeMachine::new(program, Some(rx_input), Some(tx_output));
+            thread::Builder::new()
+                .name(format!("Robot {}", i))
+                .spawn(move || robot.run())
+                .unwrap();
+
+            Some(Robot {
+                input: tx_input,
+                output: rx_output,
+                position,
+                direction: Direction::Up,
+            })
+        })
+        .collect_vec();
+
+    while swarm.iter().any(Option::is_some) {
+        // Collect this round's paint, dropping robots that have halted
+        let mut paints: HashMap<Point, Vec<Color>> = HashMap::new();
+        for (i, slot) in swarm.iter_mut().enumerate() {
+            if let Some(robot) = slot {
+                if let (Ok(color), Ok(rotation)) = (robot.output.recv(), robot.output.recv()) {
+                    paints.entry(robot.position).or_default().push(color.into());
+                    painted[i].insert(robot.position);
+                    robot.direction = Rotation::from(rotation).turn(robot.direction);
+                } else {
+                    *slot = None;
+                }
+            }
+        }
+
+        for (point, colors) in paints {
+            if colors.len() > 1 {
+                conflicts += 1;
+            }
+            if let Some(color) = policy.resolve(&colors) {
+                hull.insert(point, color);
+            }
+        }
+
+        // Move and report the color under each camera
+        for slot in swarm.iter_mut() {
+            if let Some(robot) = slot {
+                robot.position += robot.direction.offset();
+                let panel = hull.get(&robot.position).copied().unwrap_or(Color::Black);
+                if robot.input.send(panel.into()).is_err() {
+                    *slot = None;
+                }
+            }
+        }
+    }
+
+    let coverage = painted.iter().flatten().unique().count();
+    SwarmReport {
+        hull,
+        painted: painted.iter().map(HashSet::len).collect(),
+        coverage,
+        conflicts,
+    }
+}
+
 fn render_painted(painted: HashMap<Point, Color>) -> Option<String> {
     let (x1_iter, x2_iter) = painted.keys().map(|p| p.x).tee();
     let (y1_iter, y2_iter) = painted.keys().map(|p| p.y).tee();
@@ -142,17 +297,17 @@ fn render_painted(painted: HashMap<Point, Color>) -> Option<String> {
     let width = (x1.abs() + x_offset + 1) as usize;
     let height = (y1.abs() + y_offset + 1) as usize;
 
-    let mut grid = vec![vec![Color::Black; width]; height];
+    let mut grid = Grid::new(width, height, Color::Black);
     for (point, color) in painted {
         let x = (point.x + x_offset) as usize;
         let y = (height - 1) - (point.y + y_offset) as usize;
 
-        grid[y][x] = color;
+        grid[Point2::new(x, y)] = color;
     }
 
-    let lines = grid.into_iter().map(|row| {
+    let lines = grid.rows().map(|row| {
         let mut line = String::from("\t");
-        line.extend(row.into_iter().map(|color| {
+        line.extend(row.iter().map(|color| {
             match color {
                 Color::Black => TermColor::Black,
                 Color::White => TermColor::White,
@@ -189,3 +344,102 @@ fn unique_square2(program: &[i64]) -> Option<String> {
 
     render_painted(painted)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Paint the current panel, turn left and repeat four times
+    fn painter(color: i64) -> Vec<i64> {
+        vec![
+            3, 15, 104, color, 104, 0, 1001, 14, -1, 14, 1005, 14, 0, 99, 4, 0,
+        ]
+    }
+
+    #[test]
+    fn test_export_import() {
+        let mut hull = HashMap::new();
+        hull.insert(Point { x: 0, y: 0 }, Color::White);
+        hull.insert(Point { x: -2, y: 1 }, Color::Black);
+        hull.insert(Point { x: 3, y: 0 }, Color::White);
+
+        let text = export_hull(&hull);
+        assert_eq!(text, "-2,1,0\n0,0,1\n3,0,1\n");
+        assert_eq!(import_hull(&text), Ok(hull));
+        assert_eq!(import_hull("\n 1, 2, 1 \n").map(|h| h.len()), Ok(1));
+        assert_eq!(
+            import_hull("1,2,3"),
+            Err(ParseHullError("1,2,3".to_owned()))
+        );
+        assert!(import_hull("1,2").is_err());
+        assert!(import_hull("a,2,0").is_err());
+    }
+
+    #[test]
+    fn test_render_painted() {
+        let mut hull = HashMap::new();
+        hull.insert(Point { x: 0, y: 0 }, Color::White);
+        hull.insert(Point { x: 1, y: 1 }, Color::White);
+        let (black, white) = (TermColor::Black.paint(PIXEL), TermColor::White.paint(PIXEL));
+        assert_eq!(
+            render_painted(hull),
+            Some(format!(
+                "\n\n\t{}{}\n\t{}{}\n\n",
+                black, white, white, black
+            ))
+        );
+        assert_eq!(render_painted(HashMap::new()), None);
+    }
+
+    #[test]
+    fn test_paint_existing_hull() {
+        // The black painter paints over the white origin and the three
+        // panels it turns through, leaving the rest of the hull alone
+        let hull = import_hull("0,0,1\n5,5,1\n").unwrap();
+        let painted = paint_hull(&painter(0), hull);
+        assert_eq!(
+            export_hull(&painted),
+            "5,5,1\n-1,0,0\n0,0,0\n-1,-1,0\n0,-1,0\n"
+        );
+    }
+
+    #[test]
+    fn test_swarm_coverage() {
+        let white = painter(1);
+        let robots = [
+            (white.as_slice(), Point { x: 0, y: 0 }),
+            (white.as_slice(), Point { x: 5, y: 0 }),
+        ];
+        let report = hull_painting_swarm(&robots, HashMap::new(), ConflictPolicy::First);
+
+        assert_eq!(report.painted, vec![4, 4]);
+        assert_eq!(report.coverage, 8);
+        assert_eq!(report.conflicts, 0);
+        assert_eq!(
+            report.hull.get(&Point { x: -1, y: -1 }),
+            Some(&Color::White)
+        );
+        assert_eq!(report.hull.get(&Point { x: 4, y: -1 }), Some(&Color::White));
+    }
+
+    #[test]
+    fn test_swarm_conflicts() {
+        let (white, black) = (painter(1), painter(0));
+        let robots = [
+            (white.as_slice(), Point::default()),
+            (black.as_slice(), Point::default()),
+        ];
+
+        for &(policy, expected) in &[
+            (ConflictPolicy::First, Color::White),
+            (ConflictPolicy::Last, Color::Black),
+            (ConflictPolicy::White, Color::White),
+            (ConflictPolicy::Black, Color::Black),
+        ] {
+            let report = hull_painting_swarm(&robots, HashMap::new(), policy);
+            assert_eq!(report.coverage, 4);
+            assert_eq!(report.conflicts, 4);
+            assert!(report.hull.values().all(|&c| c == expected));
+        }
+    }
+}
@@ -1,92 +1,169 @@
+use crate::geometry::Point3;
 use itertools::Itertools;
 use num::integer::Integer;
 use regex::Regex;
-use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::ops::{AddAssign, SubAssign};
 
-#[derive(Clone, Copy)]
-struct CmpResult {
-    x: Ordering,
-    y: Ordering,
-    z: Ordering,
-}
+pub type Moon = Point3<i32>;
+pub type Velocity = Point3<i32>;
 
-#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, PartialOrd)]
-struct Moon {
-    x: i32,
-    y: i32,
-    z: i32,
-}
+/// Rule for how much one moon pulls on another during a single step.
+pub trait ForceLaw {
+    /// Velocity change of the moon at `body` caused by the moon at `other`.
+    fn pull(&self, body: Moon, other: Moon) -> Velocity;
 
-impl Moon {
-    fn abs(self) -> Self {
-        Moon {
-            x: self.x.abs(),
-            y: self.y.abs(),
-            z: self.z.abs(),
-        }
+    /// Whether the rule acts on each axis independently and keeps the
+    /// simulation reversible, which the cycle detection depends on.
+    fn is_reversible(&self) -> bool {
+        false
     }
+}
 
-    fn cmp(&self, other: &Self) -> CmpResult {
-        CmpResult {
-            x: self.x.cmp(&other.x),
-            y: self.y.cmp(&other.y),
-            z: self.z.cmp(&other.z),
-        }
-    }
+/// The puzzle's gravity, pulling each axis one unit towards the other moon.
+pub struct SignGravity;
 
-    fn sum(self) -> i32 {
-        self.x + self.y + self.z
+impl ForceLaw for SignGravity {
+    fn pull(&self, body: Moon, other: Moon) -> Velocity {
+        (other - body).signum()
     }
-}
 
-impl AddAssign<Velocity> for Moon {
-    fn add_assign(&mut self, rhs: Velocity) {
-        self.x += rhs.vx;
-        self.y += rhs.vy;
-        self.z += rhs.vz;
+    fn is_reversible(&self) -> bool {
+        true
     }
 }
 
-#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, PartialOrd)]
-struct Velocity {
-    vx: i32,
-    vy: i32,
-    vz: i32,
+/// Newtonian style gravity, falling off with the square of the distance and
+/// rounded to whole velocity units.
+pub struct InverseSquare {
+    pub strength: f64,
 }
 
-impl Velocity {
-    fn abs(self) -> Self {
+impl ForceLaw for InverseSquare {
+    fn pull(&self, body: Moon, other: Moon) -> Velocity {
+        let (dx, dy, dz) = (
+            f64::from(other.x - body.x),
+            f64::from(other.y - body.y),
+            f64::from(other.z - body.z),
+        );
+        let distance = (dx * dx + dy * dy + dz * dz).sqrt();
+        if distance == 0.0 {
+            return Velocity::default();
+        }
+
+        let scale = self.strength / distance.powi(3);
         Velocity {
-            vx: self.vx.abs(),
-            vy: self.vy.abs(),
-            vz: self.vz.abs(),
+            x: (dx * scale).round() as i32,
+            y: (dy * scale).round() as i32,
+            z: (dz * scale).round() as i32,
         }
     }
+}
+
+impl<F> ForceLaw for F
+where
+    F: Fn(Moon, Moon) -> Velocity,
+{
+    fn pull(&self, body: Moon, other: Moon) -> Velocity {
+        self(body, other)
+    }
+}
 
-    fn sum(self) -> i32 {
-        self.vx + self.vy + self.vz
+/// Advance the moons by one step under the given force law.
+fn step<L: ForceLaw>(law: &L, moons: &mut [Moon], velocities: &mut [Velocity]) {
+    // Apply gravity
+    for i in 0..moons.len() {
+        for j in (i + 1)..moons.len() {
+            velocities[i] += law.pull(moons[i], moons[j]);
+            velocities[j] += law.pull(moons[j], moons[i]);
+        }
     }
+
+    // Apply velocity
+    moons
+        .iter_mut()
+        .zip_eq(velocities.iter())
+        .for_each(|(moon, &velocity)| {
+            *moon += velocity;
+        });
 }
 
-impl AddAssign<CmpResult> for Velocity {
-    fn add_assign(&mut self, rhs: CmpResult) {
-        self.vx += rhs.x as i32;
-        self.vy += rhs.y as i32;
-        self.vz += rhs.z as i32;
+/// Run the simulation for a number of steps, returning the final positions
+/// and velocities of the moons.
+pub fn simulate<L: ForceLaw>(law: &L, moons: &[Moon], steps: usize) -> (Vec<Moon>, Vec<Velocity>) {
+    let mut moons = moons.to_owned();
+    let mut velocities = vec![Velocity::default(); moons.len()];
+
+    for _ in 0..steps {
+        step(law, &mut moons, &mut velocities);
     }
+    (moons, velocities)
+}
+
+/// The velocity change [`SignGravity`] gives each body along one axis: the
+/// number of bodies ahead of it less the number behind, counted in a sorted
+/// copy rather than pair by pair.
+fn sign_pulls(axis: &[i32]) -> Vec<i32> {
+    let mut sorted = axis.to_owned();
+    sorted.sort_unstable();
+    axis.iter()
+        .map(|&v| {
+            let behind = sorted.partition_point(|&o| o < v);
+            let ahead = sorted.len() - sorted.partition_point(|&o| o <= v);
+            ahead as i32 - behind as i32
+        })
+        .collect()
 }
 
-impl SubAssign<CmpResult> for Velocity {
-    fn sub_assign(&mut self, rhs: CmpResult) {
-        self.vx -= rhs.x as i32;
-        self.vy -= rhs.y as i32;
-        self.vz -= rhs.z as i32;
+/// Like [`simulate`] under [`SignGravity`], taking O(n log n) per step
+/// instead of O(n²) so that thousands of moons stay feasible.
+pub fn simulate_sorted(moons: &[Moon], steps: usize) -> (Vec<Moon>, Vec<Velocity>) {
+    let mut moons = moons.to_owned();
+    let mut velocities = vec![Velocity::default(); moons.len()];
+
+    for _ in 0..steps {
+        let xs = sign_pulls(&moons.iter().map(|m| m.x).collect_vec());
+        let ys = sign_pulls(&moons.iter().map(|m| m.y).collect_vec());
+        let zs = sign_pulls(&moons.iter().map(|m| m.z).collect_vec());
+        for (i, (moon, velocity)) in moons.iter_mut().zip_eq(velocities.iter_mut()).enumerate() {
+            *velocity += Velocity::new(xs[i], ys[i], zs[i]);
+            *moon += *velocity;
+        }
     }
+    (moons, velocities)
+}
+
+fn energy(moons: &[Moon], velocities: &[Velocity]) -> i32 {
+    moons
+        .iter()
+        .zip_eq(velocities.iter())
+        .map(|(&moon, &velocity)| moon.manhattan_length() * velocity.manhattan_length())
+        .sum()
+}
+
+/// Total energy of the system after a number of steps.
+pub fn system_energy<L: ForceLaw>(law: &L, moons: &[Moon], steps: usize) -> i32 {
+    let (moons, velocities) = simulate(law, moons, steps);
+    energy(&moons, &velocities)
 }
 
-fn simulate_moon_axis(moon_axis: &[i32]) -> (usize, usize) {
+fn simulate_moon_axis<L: ForceLaw>(law: &L, moon_axis: &[i32]) -> (usize, usize) {
+    // Embed the axis on x, which a reversible law treats on its own
+    let pull = |body: i32, other: i32| {
+        law.pull(
+            Moon {
+                x: body,
+                y: 0,
+                z: 0,
+            },
+            Moon {
+                x: other,
+                y: 0,
+                z: 0,
+            },
+        )
+        .x
+    };
+
     let mut moons = moon_axis.to_owned();
     let mut velocities = vec![0; moons.len()];
 
@@ -102,9 +179,8 @@ fn simulate_moon_axis(moon_axis: &[i32]) -> (usize, usize) {
         // Apply gravity
         for i in 0..moons.len() {
             for j in (i + 1)..moons.len() {
-                let diff = moons[i].cmp(&moons[j]);
-                velocities[i] -= diff as i32;
-                velocities[j] += diff as i32;
+                velocities[i] += pull(moons[i], moons[j]);
+                velocities[j] += pull(moons[j], moons[i]);
             }
         }
 
@@ -137,46 +213,41 @@ fn load_moons(input: &str) -> Vec<Moon> {
 fn total_system_energy(moons: &[Moon]) -> i32 {
     const STEPS: usize = 1000;
 
-    let mut moons = moons.to_owned();
-    let mut velocities = vec![Velocity::default(); moons.len()];
-
-    for _ in 0..STEPS {
-        // Apply gravity
-        for i in 0..moons.len() {
-            for j in (i + 1)..moons.len() {
-                let diffs = moons[i].cmp(&moons[j]);
-                velocities[i] -= diffs;
-                velocities[j] += diffs;
-            }
-        }
+    system_energy(&SignGravity, moons, STEPS)
+}
 
-        // Apply velocity
-        moons
-            .iter_mut()
-            .zip_eq(velocities.iter())
-            .for_each(|(moon, &velocity)| {
-                *moon += velocity;
-            });
-    }
+#[aoc(day12, part1, Sorted)]
+fn total_system_energy_sorted(moons: &[Moon]) -> i32 {
+    const STEPS: usize = 1000;
 
-    moons
-        .iter()
-        .zip_eq(velocities.iter())
-        .map(|(&moon, &velocity)| moon.abs().sum() * velocity.abs().sum())
-        .sum()
+    let (moons, velocities) = simulate_sorted(moons, STEPS);
+    energy(&moons, &velocities)
 }
 
-#[aoc(day12, part2)]
-fn equal_state(moons: &[Moon]) -> usize {
-    let (x_step, x_diff) = simulate_moon_axis(&moons.iter().map(|m| m.x).collect_vec());
-    let (y_step, y_diff) = simulate_moon_axis(&moons.iter().map(|m| m.y).collect_vec());
-    let (z_step, z_diff) = simulate_moon_axis(&moons.iter().map(|m| m.z).collect_vec());
+/// Find the step at which the system first repeats a previous state.
+///
+/// Each axis is simulated on its own and the cycles are combined, which is
+/// only valid for reversible, axis independent laws such as [`SignGravity`].
+pub fn repeat_step<L: ForceLaw>(law: &L, moons: &[Moon]) -> usize {
+    assert!(
+        law.is_reversible(),
+        "cycle detection requires a reversible force law"
+    );
+
+    let (x_step, x_diff) = simulate_moon_axis(law, &moons.iter().map(|m| m.x).collect_vec());
+    let (y_step, y_diff) = simulate_moon_axis(law, &moons.iter().map(|m| m.y).collect_vec());
+    let (z_step, z_diff) = simulate_moon_axis(law, &moons.iter().map(|m| m.z).collect_vec());
 
     let cycle = x_diff.lcm(&y_diff).lcm(&z_diff);
 
     (x_step + cycle).max(y_step + cycle).max(z_step + cycle)
 }
 
+#[aoc(day12, part2)]
+fn equal_state(moons: &[Moon]) -> usize {
+    repeat_step(&SignGravity, moons)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -204,5 +275,60 @@ mod tests {
     #[test]
     fn test_part1() {
         let moons = moons();
+        assert_eq!(system_energy(&SignGravity, &moons, 10), 179);
+    }
+
+    #[test]
+    fn test_simulate_sorted() {
+        assert_eq!(
+            total_system_energy_sorted(&moons()),
+            total_system_energy(&moons())
+        );
+        assert_eq!(sign_pulls(&[3, -1, 3, 7]), vec![0, 3, 0, -3]);
+
+        // Plenty of moons sharing coordinates
+        let moons = (0..60)
+            .map(|i| Moon::new(i % 7 - 3, (i * 13) % 11, -(i % 5)))
+            .collect_vec();
+        assert_eq!(
+            simulate_sorted(&moons, 25),
+            simulate(&SignGravity, &moons, 25)
+        );
+    }
+
+    #[test]
+    fn test_part2() {
+        let moons = moons();
+        assert_eq!(equal_state(&moons), 2772);
+    }
+
+    #[test]
+    fn test_force_laws() {
+        let moons = moons();
+
+        // A closure reproducing the default rule gives the same system
+        let sign = |body: Moon, other: Moon| SignGravity.pull(body, other);
+        assert_eq!(
+            simulate(&sign, &moons, 10),
+            simulate(&SignGravity, &moons, 10)
+        );
+
+        let law = InverseSquare { strength: 100.0 };
+        let a = Moon { x: 0, y: 0, z: 0 };
+        assert_eq!(
+            law.pull(a, Moon { x: 5, y: 0, z: 0 }),
+            Velocity { x: 4, y: 0, z: 0 }
+        );
+        assert_eq!(
+            law.pull(a, Moon { x: 0, y: -10, z: 0 }),
+            Velocity { x: 0, y: -1, z: 0 }
+        );
+        assert_eq!(law.pull(a, a), Velocity::default());
+    }
+
+    #[test]
+    #[should_panic(expected = "reversible")]
+    fn test_repeat_step_requires_reversible() {
+        repeat_step(&InverseSquare { strength: 1.0 }, &moons());
     }
 }
@@ -0,0 +1,341 @@
+use crate::intcode::{parse_program, Event, IntcodeError, IntcodeMachine, State};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Tile {
+    Empty,
+    Wall,
+    Block,
+    Paddle,
+    Ball,
+}
+
+impl From<i64> for Tile {
+    fn from(tile: i64) -> Self {
+        match tile {
+            1 => Tile::Wall,
+            2 => Tile::Block,
+            3 => Tile::Paddle,
+            4 => Tile::Ball,
+            _ => Tile::Empty,
+        }
+    }
+}
+
+/// The arcade cabinet, driving the game one frame at a time.
+///
+/// A frame lasts until the game reads the joystick, so the screen and score
+/// are always up to date when deciding the next move.
+pub struct Arcade {
+    machine: IntcodeMachine,
+    pub screen: HashMap<(i64, i64), Tile>,
+    pub score: i64,
+    ball: Option<(i64, i64)>,
+    paddle: Option<(i64, i64)>,
+}
+
+impl Arcade {
+    /// Load the game, inserting quarters to play for free if asked.
+    pub fn new(program: &[i64], free_play: bool) -> Self {
+        let mut machine = IntcodeMachine::new(program, None, None);
+        if free_play {
+            machine.store(0, 2);
+        }
+        Arcade {
+            machine,
+            screen: HashMap::new(),
+            score: 0,
+            ball: None,
+            paddle: None,
+        }
+    }
+
+    /// Run until the game wants the joystick, or it's over. Returns whether
+    /// the game is still going.
+    pub fn frame(&mut self) -> Result<bool, IntcodeError> {
+        let state = self.machine.run_until(Event::Input)?;
+
+        for chunk in self.machine.drain_output().chunks(3) {
+            match *chunk {
+                [-1, 0, score] => self.score = score,
+                [x, y, tile] => {
+                    let tile = Tile::from(tile);
+                    match tile {
+                        Tile::Ball => self.ball = Some((x, y)),
+                        Tile::Paddle => self.paddle = Some((x, y)),
+                        _ => {}
+                    }
+                    self.screen.insert((x, y), tile);
+                }
+                _ => {}
+            }
+        }
+        Ok(state == State::NeedsInput)
+    }
+
+    /// Play until the game is over, asking `joystick` for each move, and
+    /// return the final score.
+    pub fn play<F>(&mut self, mut joystick: F) -> Result<i64, IntcodeError>
+    where
+        F: FnMut(&Arcade) -> i64,
+    {
+        self.play_with(&mut joystick).map(|(score, _)| score)
+    }
+
+    /// Play until the game is over with `strategy`, returning the final score
+    /// and how many moves tilted the joystick.
+    pub fn play_with(
+        &mut self,
+        strategy: &mut dyn PaddleStrategy,
+    ) -> Result<(i64, usize), IntcodeError> {
+        let mut moves = 0;
+        while self.frame()? {
+            let tilt = strategy.tilt(self);
+            if tilt != 0 {
+                moves += 1;
+            }
+            self.machine.push_input(tilt);
+        }
+        Ok((self.score, moves))
+    }
+
+    pub fn blocks(&self) -> usize {
+        self.screen.values().filter(|&&t| t == Tile::Block).count()
+    }
+
+    /// Where the ball was last drawn.
+    pub fn ball(&self) -> Option<(i64, i64)> {
+        self.ball
+    }
+
+    /// Where the paddle was last drawn.
+    pub fn paddle(&self) -> Option<(i64, i64)> {
+        self.paddle
+    }
+}
+
+/// How to tilt the joystick each frame, -1 for left, 1 for right, 0 to stay.
+pub trait PaddleStrategy {
+    fn tilt(&mut self, arcade: &Arcade) -> i64;
+}
+
+impl<F> PaddleStrategy for F
+where
+    F: FnMut(&Arcade) -> i64,
+{
+    fn tilt(&mut self, arcade: &Arcade) -> i64 {
+        self(arcade)
+    }
+}
+
+fn towards(from: i64, to: i64) -> i64 {
+    match to.cmp(&from) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    }
+}
+
+/// Keep the paddle under the ball.
+pub fn follow_ball(arcade: &Arcade) -> i64 {
+    match (arcade.ball, arcade.paddle) {
+        (Some((ball, _)), Some((paddle, _))) => towards(paddle, ball),
+        _ => 0,
+    }
+}
+
+/// Where a ball at `x` moving `dx` a row lands after `rows` rows, bouncing
+/// between columns `left` and `right`.
+pub fn landing_column(x: i64, dx: i64, rows: i64, left: i64, right: i64) -> i64 {
+    let width = right - left;
+    if width <= 0 {
+        return left;
+    }
+    let offset = (x - left + dx * rows).rem_euclid(2 * width);
+    left + if offset > width {
+        2 * width - offset
+    } else {
+        offset
+    }
+}
+
+/// Move the paddle to where the ball will come down, working out its
+/// direction from frame to frame and bouncing it off the side walls, and
+/// follow the ball while it's going up.
+#[derive(Clone, Debug, Default)]
+pub struct Predictive {
+    last: Option<(i64, i64)>,
+}
+
+impl PaddleStrategy for Predictive {
+    fn tilt(&mut self, arcade: &Arcade) -> i64 {
+        let last = self.last.take();
+        self.last = arcade.ball;
+        let (ball, paddle, last) = match (arcade.ball, arcade.paddle, last) {
+            (Some(ball), Some(paddle), Some(last)) => (ball, paddle, last),
+            _ => return follow_ball(arcade),
+        };
+        let (dx, dy) = (ball.0 - last.0, ball.1 - last.1);
+        if dy <= 0 || ball.1 >= paddle.1 {
+            return follow_ball(arcade);
+        }
+
+        let walls = arcade
+            .screen
+            .iter()
+            .filter(|&(_, &t)| t == Tile::Wall)
+            .map(|(&(x, _), _)| x);
+        let right = walls.max().unwrap_or(paddle.0) - 1;
+        let target = landing_column(ball.0, dx, paddle.1 - 1 - ball.1, 1, right);
+        towards(paddle.0, target)
+    }
+}
+
+/// How a strategy did in a [`tournament`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Standing {
+    pub name: String,
+    pub score: i64,
+    /// Frames where the joystick was tilted.
+    pub moves: usize,
+    pub blocks_left: usize,
+}
+
+/// Play a free game with each strategy, ranking them by score and then by
+/// fewest moves.
+pub fn tournament(
+    program: &[i64],
+    entrants: &mut [(&str, &mut dyn PaddleStrategy)],
+) -> Result<Vec<Standing>, IntcodeError> {
+    let mut standings = entrants
+        .iter_mut()
+        .map(|(name, strategy)| {
+            let mut arcade = Arcade::new(program, true);
+            let (score, moves) = arcade.play_with(*strategy)?;
+            Ok(Standing {
+                name: name.to_string(),
+                score,
+                moves,
+                blocks_left: arcade.blocks(),
+            })
+        })
+        .collect::<Result<Vec<_>, IntcodeError>>()?;
+    standings.sort_by_key(|s| (-s.score, s.moves));
+    Ok(standings)
+}
+
+#[aoc_generator(day13)]
+fn load_program(input: &str) -> Vec<i64> {
+    parse_program(input).unwrap()
+}
+
+#[aoc(day13, part1)]
+fn block_tiles(program: &[i64]) -> Result<usize, IntcodeError> {
+    let mut arcade = Arcade::new(program, false);
+    arcade.frame()?;
+    Ok(arcade.blocks())
+}
+
+#[aoc(day13, part2)]
+fn final_score(program: &[i64]) -> Result<i64, IntcodeError> {
+    Arcade::new(program, true).play(follow_ball)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    // Draws a wall, two blocks, the paddle and the ball, then scores 1000 if
+    // the joystick moves toward the ball
+    fn game() -> Vec<i64> {
+        assemble(
+            "
+                  add [zero], [zero], [zero] ; multiply in free play
+                  out 0
+                  out 0
+                  out 1
+                  out 1
+                  out 0
+                  out 2
+                  out 2
+                  out 0
+                  out 2
+                  out 1
+                  out 2
+                  out 3
+                  out 3
+                  out 1
+                  out 4
+                  in [joy]
+                  eq [joy], 1, [ok]
+                  mul [ok], 1000, [score]
+                  out -1
+                  out 0
+                  out [score]
+                  hlt
+            zero: .data 0
+            joy:  .data 0
+            ok:   .data 0
+            score: .data 0
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(block_tiles(&game()), Ok(2));
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(final_score(&game()), Ok(1000));
+
+        let mut arcade = Arcade::new(&game(), true);
+        assert_eq!(arcade.play(|_| -1), Ok(0));
+        assert_eq!(arcade.screen[&(1, 2)], Tile::Paddle);
+    }
+
+    #[test]
+    fn test_landing_column() {
+        // Straight down, and across without reaching a wall
+        assert_eq!(landing_column(5, 0, 10, 1, 9), 5);
+        assert_eq!(landing_column(5, 1, 3, 1, 9), 8);
+        // Off the right wall and back, and off both walls
+        assert_eq!(landing_column(8, 1, 3, 1, 9), 7);
+        assert_eq!(landing_column(2, -1, 3, 1, 9), 3);
+        assert_eq!(landing_column(5, 1, 18, 1, 9), 7);
+    }
+
+    #[test]
+    fn test_tournament() {
+        let mut follow = follow_ball;
+        let mut predictive = Predictive::default();
+        let mut left = |_: &Arcade| -1;
+        let mut entrants: [(&str, &mut dyn PaddleStrategy); 3] = [
+            ("left", &mut left),
+            ("predictive", &mut predictive),
+            ("follow", &mut follow),
+        ];
+        let standings = tournament(&game(), &mut entrants).unwrap();
+        assert_eq!(
+            standings
+                .iter()
+                .map(|s| (s.name.as_str(), s.score, s.moves))
+                .collect::<Vec<_>>(),
+            vec![("predictive", 1000, 1), ("follow", 1000, 1), ("left", 0, 1)]
+        );
+        assert_eq!(standings[0].blocks_left, 2);
+    }
+
+    #[test]
+    fn test_frame() {
+        let mut arcade = Arcade::new(&game(), true);
+        assert_eq!(arcade.frame(), Ok(true));
+        assert_eq!(follow_ball(&arcade), 1);
+        assert_eq!(arcade.screen.len(), 5);
+        assert_eq!(arcade.score, 0);
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::{HashMap, HashSet};
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+const ORE: &str = "ORE";
+const FUEL: &str = "FUEL";
+
+/// An amount of a chemical, written as `7 A`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Chemical {
+    pub quantity: u64,
+    pub name: String,
+}
+
+impl FromStr for Chemical {
+    type Err = ParseRecipeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseRecipeError(s.to_owned());
+        let mut words = s.split_whitespace();
+        let quantity = words
+            .next()
+            .ok_or_else(error)?
+            .parse()
+            .map_err(|_| error())?;
+        if quantity == 0 {
+            return Err(error());
+        }
+        let name = words.next().ok_or_else(error)?.to_owned();
+        if words.next().is_some() {
+            return Err(error());
+        }
+        Ok(Chemical { quantity, name })
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseRecipeError(String);
+
+impl error::Error for ParseRecipeError {}
+
+impl fmt::Display for ParseRecipeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid reaction {:?}", self.0)
+    }
+}
+
+/// One reaction, written as `7 A, 1 E => 1 FUEL`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Recipe {
+    pub inputs: Vec<Chemical>,
+    pub output: Chemical,
+}
+
+impl FromStr for Recipe {
+    type Err = ParseRecipeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut sides = s.split("=>");
+        let (inputs, output) = match (sides.next(), sides.next(), sides.next()) {
+            (Some(inputs), Some(output), None) => (inputs, output),
+            _ => return Err(ParseRecipeError(s.to_owned())),
+        };
+        Ok(Recipe {
+            inputs: inputs
+                .split(',')
+                .map(str::parse)
+                .collect::<Result<_, _>>()?,
+            output: output.parse()?,
+        })
+    }
+}
+
+/// Reactions keyed by the chemical they produce, along with an order where
+/// every chemical comes before the ones it's made from.
+pub struct Reactions {
+    recipes: HashMap<String, Recipe>,
+    order: Vec<String>,
+}
+
+impl Reactions {
+    /// Returns `None` if a recipe makes nothing, a chemical has more than
+    /// one recipe, or the reactions form a cycle.
+    pub fn new(recipes: Vec<Recipe>) -> Option<Self> {
+        if recipes.iter().any(|recipe| recipe.output.quantity == 0) {
+            return None;
+        }
+        let count = recipes.len();
+        let recipes = recipes
+            .into_iter()
+            .map(|recipe| (recipe.output.name.clone(), recipe))
+            .collect::<HashMap<_, _>>();
+        if recipes.len() != count {
+            return None;
+        }
+
+        // Depth first post-order from every chemical, reversed
+        fn visit(
+            name: &str,
+            recipes: &HashMap<String, Recipe>,
+            visiting: &mut HashSet<String>,
+            done: &mut HashSet<String>,
+            order: &mut Vec<String>,
+        ) -> Option<()> {
+            if done.contains(name) {
+                return Some(());
+            }
+            if !visiting.insert(name.to_owned()) {
+                return None;
+            }
+            if let Some(recipe) = recipes.get(name) {
+                for input in &recipe.inputs {
+                    visit(&input.name, recipes, visiting, done, order)?;
+                }
+            }
+            done.insert(name.to_owned());
+            order.push(name.to_owned());
+            Some(())
+        }
+
+        let mut names = recipes.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        let (mut visiting, mut done, mut order) = (HashSet::new(), HashSet::new(), Vec::new());
+        for name in &names {
+            visit(name, &recipes, &mut visiting, &mut done, &mut order)?;
+        }
+        order.reverse();
+
+        Some(Reactions { recipes, order })
+    }
+
+    pub fn recipe(&self, name: &str) -> Option<&Recipe> {
+        self.recipes.get(name)
+    }
+
+    /// Chemicals from the most refined down to raw ones.
+    pub fn order(&self) -> &[String] {
+        &self.order
+    }
+
+    /// Work out how much of each raw chemical is needed to make `quantity`
+    /// of `name`, along with what's left over from rounding up reactions.
+    ///
+    /// Panics if the amounts overflow, see
+    /// [`checked_requirements`](Reactions::checked_requirements).
+    pub fn requirements(
+        &self,
+        name: &str,
+        quantity: u64,
+    ) -> (HashMap<String, u64>, HashMap<String, u64>) {
+        self.checked_requirements(name, quantity)
+            .expect("chemical quantities overflow")
+    }
+
+    /// Like [`requirements`](Reactions::requirements), or `None` if the
+    /// amounts overflow.
+    pub fn checked_requirements(
+        &self,
+        name: &str,
+        quantity: u64,
+    ) -> Option<(HashMap<String, u64>, HashMap<String, u64>)> {
+        let mut needed = HashMap::new();
+        needed.insert(name.to_owned(), quantity);
+        let mut leftover = HashMap::new();
+
+        // All consumers of a chemical come before it, so its total is known
+        for chemical in &self.order {
+            let (amount, recipe) = match (needed.get(chemical), self.recipes.get(chemical)) {
+                (Some(&amount), Some(recipe)) => (amount, recipe),
+                _ => continue,
+            };
+            let runs = amount.div_ceil(recipe.output.quantity);
+            let spare = runs.checked_mul(recipe.output.quantity)? - amount;
+            if spare > 0 {
+                leftover.insert(chemical.to_owned(), spare);
+            }
+
+            needed.remove(chemical);
+            for input in &recipe.inputs {
+                let total = needed.entry(input.name.to_owned()).or_insert(0);
+                *total = total.checked_add(runs.checked_mul(input.quantity)?)?;
+            }
+        }
+        Some((needed, leftover))
+    }
+
+    pub fn ore_for_fuel(&self, fuel: u64) -> u64 {
+        let (raw, _) = self.requirements(FUEL, fuel);
+        raw.get(ORE).copied().unwrap_or(0)
+    }
+
+    /// The most fuel that can be made from `ore`, or `None` if fuel takes no
+    /// ore at all.
+    pub fn max_fuel(&self, ore: u64) -> Option<u64> {
+        if self.ore_for_fuel(1) == 0 {
+            return None;
+        }
+        // Amounts too large to count need more ore than there can be
+        let fits = |fuel| {
+            self.checked_requirements(FUEL, fuel)
+                .is_some_and(|(raw, _)| raw.get(ORE).copied().unwrap_or(0) <= ore)
+        };
+
+        let (mut low, mut high) = (0, 1);
+        while fits(high) {
+            low = high;
+            high = match high.checked_mul(2) {
+                Some(high) => high,
+                None if fits(u64::MAX) => return Some(u64::MAX),
+                None => u64::MAX,
+            };
+        }
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if fits(mid) {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Some(low)
+    }
+}
+
+#[aoc_generator(day14)]
+fn load_reactions(input: &str) -> Vec<Recipe> {
+    input.lines().map(|s| s.parse().unwrap()).collect()
+}
+
+#[aoc(day14, part1)]
+fn ore_for_one_fuel(recipes: &[Recipe]) -> Option<u64> {
+    Some(Reactions::new(recipes.to_vec())?.ore_for_fuel(1))
+}
+
+#[aoc(day14, part2)]
+fn fuel_for_a_trillion_ore(recipes: &[Recipe]) -> Option<u64> {
+    Reactions::new(recipes.to_vec())?.max_fuel(1_000_000_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMALL: &str = "10 ORE => 10 A\n1 ORE => 1 B\n7 A, 1 B => 1 C\n7 A, 1 C => 1 D\n7 A, 1 D => 1 E\n7 A, 1 E => 1 FUEL";
+    const MEDIUM: &str = "9 ORE => 2 A\n8 ORE => 3 B\n7 ORE => 5 C\n3 A, 4 B => 1 AB\n5 B, 7 C => 1 BC\n4 C, 1 A => 1 CA\n2 AB, 3 BC, 4 CA => 1 FUEL";
+    const LARGE: &str = "157 ORE => 5 NZVS\n165 ORE => 6 DCFZ\n44 XJWVT, 5 KHKGT, 1 QDVJ, 29 NZVS, 9 GPVTF, 48 HKGWZ => 1 FUEL\n12 HKGWZ, 1 GPVTF, 8 PSHF => 9 QDVJ\n179 ORE => 7 PSHF\n177 ORE => 5 HKGWZ\n7 DCFZ, 7 PSHF => 2 XJWVT\n165 ORE => 2 GPVTF\n3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT";
+    const LARGER: &str = "2 VPVL, 7 FWMGM, 2 CXFTF, 11 MNCFX => 1 STKFG\n17 NVRVD, 3 JNWZP => 8 VPVL\n53 STKFG, 6 MNCFX, 46 VJHF, 81 HVMC, 68 CXFTF, 25 GNMV => 1 FUEL\n22 VJHF, 37 MNCFX => 5 FWMGM\n139 ORE => 4 NVRVD\n144 ORE => 7 JNWZP\n5 MNCFX, 7 RFSQX, 2 FWMGM, 2 VPVL, 19 CXFTF => 3 HVMC\n5 VJHF, 7 MNCFX, 9 VPVL, 37 CXFTF => 6 GNMV\n145 ORE => 6 MNCFX\n1 NVRVD => 8 CXFTF\n1 VJHF, 6 MNCFX => 4 RFSQX\n176 ORE => 6 VJHF";
+    const LARGEST: &str = "171 ORE => 8 CNZTR\n7 ZLQW, 3 BMBT, 9 XCVML, 26 XMNCP, 1 WPTQ, 2 MZWV, 1 RJRHP => 4 PLWSL\n114 ORE => 4 BHXH\n14 VRPVC => 6 BMBT\n6 BHXH, 18 KTJDG, 12 WPTQ, 7 PLWSL, 31 FHTLT, 37 ZDVW => 1 FUEL\n6 WPTQ, 2 BMBT, 8 ZLQW, 18 KTJDG, 1 XMNCP, 6 MZWV, 1 RJRHP => 6 FHTLT\n15 XDBXC, 2 LTCX, 1 VRPVC => 6 ZLQW\n13 WPTQ, 10 LTCX, 3 RJRHP, 14 XMNCP, 2 MZWV, 1 ZLQW => 1 ZDVW\n5 BMBT => 4 WPTQ\n189 ORE => 9 KTJDG\n1 MZWV, 17 XDBXC, 3 XCVML => 2 XMNCP\n12 VRPVC, 27 CNZTR => 2 XDBXC\n15 KTJDG, 12 BHXH => 5 XCVML\n3 BHXH, 2 VRPVC => 7 MZWV\n121 ORE => 7 VRPVC\n7 XCVML => 6 RJRHP\n5 BHXH, 4 VRPVC => 5 LTCX";
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "7 A, 1 E => 1 FUEL".parse(),
+            Ok(Recipe {
+                inputs: vec![
+                    Chemical {
+                        quantity: 7,
+                        name: "A".to_owned()
+                    },
+                    Chemical {
+                        quantity: 1,
+                        name: "E".to_owned()
+                    }
+                ],
+                output: Chemical {
+                    quantity: 1,
+                    name: "FUEL".to_owned()
+                }
+            })
+        );
+        assert!("7 A => 1 FUEL => 2 B".parse::<Recipe>().is_err());
+        assert!("A => 1 FUEL".parse::<Recipe>().is_err());
+        assert!("7 A B => 1 FUEL".parse::<Recipe>().is_err());
+        assert!("10 ORE => 0 A".parse::<Recipe>().is_err());
+        assert!("0 ORE => 1 A".parse::<Recipe>().is_err());
+    }
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(ore_for_one_fuel(&load_reactions(SMALL)), Some(31));
+        assert_eq!(ore_for_one_fuel(&load_reactions(MEDIUM)), Some(165));
+        assert_eq!(ore_for_one_fuel(&load_reactions(LARGE)), Some(13312));
+        assert_eq!(ore_for_one_fuel(&load_reactions(LARGER)), Some(180_697));
+        assert_eq!(ore_for_one_fuel(&load_reactions(LARGEST)), Some(2_210_736));
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(
+            fuel_for_a_trillion_ore(&load_reactions(LARGE)),
+            Some(82_892_753)
+        );
+        assert_eq!(
+            fuel_for_a_trillion_ore(&load_reactions(LARGER)),
+            Some(5_586_022)
+        );
+        assert_eq!(
+            fuel_for_a_trillion_ore(&load_reactions(LARGEST)),
+            Some(460_664)
+        );
+    }
+
+    #[test]
+    fn test_max_fuel_limits() {
+        // Nothing makes A, so fuel takes no ore and there's no limit
+        let free = Reactions::new(load_reactions("1 A => 1 FUEL")).unwrap();
+        assert_eq!(free.ore_for_fuel(1), 0);
+        assert_eq!(free.max_fuel(1_000), None);
+
+        // So much ore that the amounts would overflow
+        let cheap = Reactions::new(load_reactions("1 ORE => 1000 FUEL")).unwrap();
+        assert_eq!(cheap.max_fuel(u64::MAX), Some(u64::MAX / 1000 * 1000));
+        let small = Reactions::new(load_reactions(SMALL)).unwrap();
+        let fuel = small.max_fuel(u64::MAX).unwrap();
+        assert!(small.checked_requirements(FUEL, fuel).is_some());
+        assert!(small
+            .checked_requirements(FUEL, fuel + 1)
+            .is_none_or(|(raw, _)| raw[ORE] == u64::MAX));
+    }
+
+    #[test]
+    fn test_leftovers() {
+        let reactions = Reactions::new(load_reactions(SMALL)).unwrap();
+        let (raw, leftover) = reactions.requirements(FUEL, 1);
+        assert_eq!(raw, vec![(ORE.to_owned(), 31)].into_iter().collect());
+        assert_eq!(leftover, vec![("A".to_owned(), 2)].into_iter().collect());
+        assert_eq!(reactions.order().first().map(String::as_str), Some(FUEL));
+        assert_eq!(reactions.order().last().map(String::as_str), Some(ORE));
+    }
+
+    #[test]
+    fn test_invalid_graph() {
+        assert!(Reactions::new(load_reactions("1 A => 1 B\n1 B => 1 A")).is_none());
+        assert!(Reactions::new(load_reactions("1 ORE => 1 A\n2 ORE => 1 A")).is_none());
+        let nothing = Recipe {
+            inputs: vec!["10 ORE".parse().unwrap()],
+            output: Chemical {
+                quantity: 0,
+                name: "A".to_owned(),
+            },
+        };
+        assert!(Reactions::new(vec![nothing]).is_none());
+    }
+}
@@ -0,0 +1,389 @@
+use crate::intcode::{parse_program, Event, IntcodeError, IntcodeMachine, State};
+use itertools::Itertools;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+pub type Position = (i32, i32);
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Direction {
+    North,
+    South,
+    West,
+    East,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::South,
+        Direction::West,
+        Direction::East,
+    ];
+
+    fn command(self) -> i64 {
+        match self {
+            Direction::North => 1,
+            Direction::South => 2,
+            Direction::West => 3,
+            Direction::East => 4,
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+            Direction::East => Direction::West,
+        }
+    }
+
+    pub fn step(self, (x, y): Position) -> Position {
+        match self {
+            Direction::North => (x, y - 1),
+            Direction::South => (x, y + 1),
+            Direction::West => (x - 1, y),
+            Direction::East => (x + 1, y),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Cell {
+    Wall,
+    Open,
+    Oxygen,
+}
+
+/// The repair droid, driven one movement command at a time.
+pub struct Droid {
+    machine: IntcodeMachine,
+    position: Position,
+    commands: usize,
+}
+
+impl Droid {
+    pub fn new(program: &[i64]) -> Self {
+        Droid {
+            machine: IntcodeMachine::new(program, None, None),
+            position: (0, 0),
+            commands: 0,
+        }
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Number of movement commands sent so far.
+    pub fn commands(&self) -> usize {
+        self.commands
+    }
+
+    /// Try to move, returning what's in that direction. A droid that stops
+    /// responding can't move anywhere, so it reports walls.
+    pub fn go(&mut self, direction: Direction) -> Result<Cell, IntcodeError> {
+        self.machine.push_input(direction.command());
+        self.commands += 1;
+
+        let cell = match self.machine.run_until(Event::Output)? {
+            State::Output(1) => Cell::Open,
+            State::Output(2) => Cell::Oxygen,
+            _ => Cell::Wall,
+        };
+        if cell != Cell::Wall {
+            self.position = direction.step(self.position);
+        }
+        Ok(cell)
+    }
+}
+
+/// The explored part of the ship, with the droid starting at the origin.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Maze {
+    pub cells: HashMap<Position, Cell>,
+}
+
+impl Maze {
+    /// Map every reachable cell with a depth first search, backtracking the
+    /// droid along its own path.
+    pub fn explore(droid: &mut Droid) -> Result<Self, IntcodeError> {
+        let mut cells = HashMap::new();
+        cells.insert(droid.position(), Cell::Open);
+        let mut path = Vec::new();
+
+        loop {
+            let position = droid.position();
+            let unknown = Direction::ALL
+                .iter()
+                .copied()
+                .find(|d| !cells.contains_key(&d.step(position)));
+
+            match unknown {
+                Some(direction) => {
+                    let cell = droid.go(direction)?;
+                    cells.insert(direction.step(position), cell);
+                    if cell != Cell::Wall {
+                        path.push(direction);
+                    }
+                }
+                None => match path.pop() {
+                    Some(direction) => {
+                        droid.go(direction.opposite())?;
+                    }
+                    None => break,
+                },
+            }
+        }
+        Ok(Maze { cells })
+    }
+
+    pub fn oxygen(&self) -> Option<Position> {
+        self.cells
+            .iter()
+            .find(|(_, &cell)| cell == Cell::Oxygen)
+            .map(|(&position, _)| position)
+    }
+
+    /// Steps to every open cell reachable from `start`.
+    pub fn distances(&self, start: Position) -> HashMap<Position, usize> {
+        let mut distances = HashMap::new();
+        let mut queue = VecDeque::new();
+        distances.insert(start, 0);
+        queue.push_back(start);
+
+        while let Some(position) = queue.pop_front() {
+            let distance = distances[&position];
+            for direction in &Direction::ALL {
+                let next = direction.step(position);
+                let open = matches!(self.cells.get(&next), Some(Cell::Open) | Some(Cell::Oxygen));
+                if open && !distances.contains_key(&next) {
+                    distances.insert(next, distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    pub fn shortest_path(&self, from: Position, to: Position) -> Option<usize> {
+        self.distances(from).get(&to).copied()
+    }
+
+    /// Minutes for oxygen to spread from the oxygen system to every open cell.
+    pub fn fill_time(&self) -> Option<usize> {
+        self.distances(self.oxygen()?).values().max().copied()
+    }
+
+    /// The open cells as a JSON adjacency list, keyed by `"x,y"`, along with
+    /// where the droid started and the oxygen system is.
+    pub fn to_json(&self) -> String {
+        let key = |(x, y): Position| format!("\"{},{}\"", x, y);
+        let open = |position| {
+            matches!(
+                self.cells.get(&position),
+                Some(Cell::Open) | Some(Cell::Oxygen)
+            )
+        };
+        let adjacency = self
+            .cells
+            .keys()
+            .copied()
+            .filter(|&position| open(position))
+            .sorted_by_key(|&(x, y)| (y, x))
+            .map(|position| {
+                let neighbours = Direction::ALL
+                    .iter()
+                    .map(|d| d.step(position))
+                    .filter(|&next| open(next))
+                    .map(key)
+                    .join(", ");
+                format!("    {}: [{}]", key(position), neighbours)
+            })
+            .join(",\n");
+        format!(
+            "{{\n  \"start\": {},\n  \"oxygen\": {},\n  \"adjacency\": {{\n{}\n  }}\n}}\n",
+            key((0, 0)),
+            self.oxygen().map_or_else(|| "null".to_owned(), key),
+            adjacency
+        )
+    }
+}
+
+/// Draws the map in the format it's parsed from, with the droid's starting
+/// cell as `D`.
+impl fmt::Display for Maze {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let xs = self.cells.keys().map(|&(x, _)| x).minmax().into_option();
+        let ys = self.cells.keys().map(|&(_, y)| y).minmax().into_option();
+        let ((min_x, max_x), (min_y, max_y)) = match (xs, ys) {
+            (Some(xs), Some(ys)) => (xs, ys),
+            _ => return Ok(()),
+        };
+        for y in min_y..=max_y {
+            let line = (min_x..=max_x)
+                .map(|x| match self.cells.get(&(x, y)) {
+                    Some(Cell::Wall) => '#',
+                    Some(Cell::Oxygen) => 'O',
+                    Some(Cell::Open) if (x, y) == (0, 0) => 'D',
+                    Some(Cell::Open) => '.',
+                    None => ' ',
+                })
+                .collect::<String>();
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseMazeError(char);
+
+impl Error for ParseMazeError {}
+
+impl fmt::Display for ParseMazeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unexpected {:?} in maze", self.0)
+    }
+}
+
+/// Reads a drawn map, with `#` for walls, `.` open, `O` the oxygen system
+/// and spaces unexplored. The origin is an open cell drawn as `D` where the
+/// droid starts, or the top left corner if there isn't one.
+impl FromStr for Maze {
+    type Err = ParseMazeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cells = HashMap::new();
+        let mut start = (0, 0);
+        for (y, line) in s.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let position = (x as i32, y as i32);
+                let cell = match c {
+                    '#' => Cell::Wall,
+                    '.' => Cell::Open,
+                    'O' => Cell::Oxygen,
+                    'D' => {
+                        start = position;
+                        Cell::Open
+                    }
+                    ' ' => continue,
+                    _ => return Err(ParseMazeError(c)),
+                };
+                cells.insert(position, cell);
+            }
+        }
+        let cells = cells
+            .into_iter()
+            .map(|((x, y), cell)| ((x - start.0, y - start.1), cell))
+            .collect();
+        Ok(Maze { cells })
+    }
+}
+
+#[aoc_generator(day15)]
+fn load_program(input: &str) -> Vec<i64> {
+    parse_program(input).unwrap()
+}
+
+#[aoc(day15, part1)]
+fn steps_to_oxygen(program: &[i64]) -> Result<usize, Box<dyn Error>> {
+    let maze = Maze::explore(&mut Droid::new(program))?;
+    let oxygen = maze.oxygen().ok_or("no oxygen system found")?;
+    Ok(maze
+        .shortest_path((0, 0), oxygen)
+        .ok_or("oxygen system unreachable")?)
+}
+
+#[aoc(day15, part2)]
+fn oxygen_fill_time(program: &[i64]) -> Result<usize, Box<dyn Error>> {
+    let maze = Maze::explore(&mut Droid::new(program))?;
+    Ok(maze.fill_time().ok_or("no oxygen system found")?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    // A corridor two cells long, with the oxygen system at the east end
+    fn corridor() -> Vec<i64> {
+        assemble(
+            "
+            loop:    in [cmd]
+                     eq [cmd], 4, [east]
+                     jnz [east], go_east
+                     eq [cmd], 3, [west]
+                     jnz [west], go_west
+            wall:    out 0
+                     jz 0, loop
+            go_east: jnz [x], wall
+                     add 1, 0, [x]
+                     out 2
+                     jz 0, loop
+            go_west: jz [x], wall
+                     add 0, 0, [x]
+                     out 1
+                     jz 0, loop
+            x:       .data 0
+            cmd:     .data 0
+            east:    .data 0
+            west:    .data 0
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_droid() {
+        let mut droid = Droid::new(&corridor());
+        assert_eq!(droid.go(Direction::North), Ok(Cell::Wall));
+        assert_eq!(droid.go(Direction::East), Ok(Cell::Oxygen));
+        assert_eq!(droid.position(), (1, 0));
+        assert_eq!(droid.go(Direction::East), Ok(Cell::Wall));
+        assert_eq!(droid.go(Direction::West), Ok(Cell::Open));
+        assert_eq!(droid.position(), (0, 0));
+        assert_eq!(droid.commands(), 4);
+    }
+
+    #[test]
+    fn test_explore() {
+        let mut droid = Droid::new(&corridor());
+        let maze = Maze::explore(&mut droid).unwrap();
+
+        assert_eq!(maze.cells.len(), 8);
+        assert_eq!(maze.oxygen(), Some((1, 0)));
+        assert_eq!(droid.position(), (0, 0));
+        assert_eq!(steps_to_oxygen(&corridor()).unwrap(), 1);
+        assert_eq!(oxygen_fill_time(&corridor()).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_fill_time() {
+        let maze: Maze = " ##   \n#..## \n#.#..#\n#.O.# \n ###  ".parse().unwrap();
+        assert_eq!(maze.fill_time(), Some(4));
+        assert_eq!(maze.shortest_path((1, 1), (2, 3)), Some(3));
+        assert_eq!(maze.shortest_path((1, 1), (0, 0)), None);
+    }
+
+    #[test]
+    fn test_export() {
+        let maze = Maze::explore(&mut Droid::new(&corridor())).unwrap();
+        let drawn = maze.to_string();
+        assert_eq!(drawn, " ##\n#DO#\n ##\n");
+        assert_eq!(drawn.parse::<Maze>().unwrap(), maze);
+        assert_eq!(
+            maze.to_json(),
+            "{\n  \"start\": \"0,0\",\n  \"oxygen\": \"1,0\",\n  \"adjacency\": {\n    \"0,0\": [\"1,0\"],\n    \"1,0\": [\"0,0\"]\n  }\n}\n"
+        );
+
+        // A hand-drawn maze keeps the droid's start as the origin
+        let maze: Maze = "#####\n#D.O#\n#####".parse().unwrap();
+        assert_eq!(maze.oxygen(), Some((2, 0)));
+        assert_eq!(maze.cells.get(&(-1, 0)), Some(&Cell::Wall));
+        assert_eq!(maze.fill_time(), Some(2));
+    }
+}
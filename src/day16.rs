@@ -0,0 +1,122 @@
+use itertools::Itertools;
+
+const PHASES: usize = 100;
+const REPEAT: usize = 10_000;
+
+fn digits(signal: &[i32]) -> String {
+    signal.iter().take(8).join("")
+}
+
+/// Run one phase over the part of a signal starting at `offset`.
+///
+/// Output digits only depend on input digits at or after the same position,
+/// so a tail of the signal can be transformed on its own. Each output digit
+/// is summed block by block from prefix sums, which is linear in the length
+/// of the tail once it's past the middle of the signal.
+fn phase(tail: &[i32], offset: usize) -> Vec<i32> {
+    let mut prefix = Vec::with_capacity(tail.len() + 1);
+    prefix.push(0i64);
+    for &digit in tail {
+        prefix.push(prefix[prefix.len() - 1] + i64::from(digit));
+    }
+    let sum =
+        |start: usize, end: usize| prefix[end.min(tail.len())] - prefix[start.min(tail.len())];
+
+    (0..tail.len())
+        .map(|i| {
+            // The repeating 0, 1, 0, -1 pattern, stretched to blocks of
+            // `size` and shifted so the first 1 block starts at `i`
+            let size = offset + i + 1;
+            let total = (i..tail.len())
+                .step_by(2 * size)
+                .zip([1, -1].iter().cycle())
+                .map(|(start, sign)| sign * sum(start, start + size))
+                .sum::<i64>();
+            (total.abs() % 10) as i32
+        })
+        .collect()
+}
+
+/// Apply `phases` phases to the tail of a signal starting at `offset`.
+pub fn fft(tail: &[i32], offset: usize, phases: usize) -> Vec<i32> {
+    (0..phases).fold(tail.to_vec(), |signal, _| phase(&signal, offset))
+}
+
+#[aoc_generator(day16)]
+fn load_signal(input: &str) -> Vec<i32> {
+    input
+        .trim()
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .map(|d| d as i32)
+        .collect()
+}
+
+#[aoc(day16, part1)]
+fn first_eight(signal: &[i32]) -> String {
+    digits(&fft(signal, 0, PHASES))
+}
+
+#[aoc(day16, part2)]
+fn embedded_message(signal: &[i32]) -> String {
+    let offset = signal
+        .iter()
+        .take(7)
+        .fold(0, |acc, &d| acc * 10 + d as usize);
+    let tail = signal
+        .iter()
+        .copied()
+        .cycle()
+        .take(signal.len() * REPEAT)
+        .skip(offset)
+        .collect_vec();
+    digits(&fft(&tail, offset, PHASES))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phases() {
+        let signal = load_signal("12345678");
+        let expected = ["48226158", "34040438", "03415518", "01029498"];
+        for (phases, expected) in (1..).zip(expected.iter()) {
+            assert_eq!(digits(&fft(&signal, 0, phases)), *expected);
+        }
+    }
+
+    #[test]
+    fn test_tail() {
+        // A tail transforms the same as the end of the whole signal
+        let signal = load_signal("80871224585914546619083218645595");
+        let whole = fft(&signal, 0, 4);
+        for offset in &[1, 7, 16, 31] {
+            assert_eq!(fft(&signal[*offset..], *offset, 4), &whole[*offset..]);
+        }
+    }
+
+    #[test]
+    fn test_part1() {
+        let signal = load_signal("80871224585914546619083218645595");
+        assert_eq!(first_eight(&signal), "24176176");
+
+        let signal = load_signal("19617804207202209144916044189917");
+        assert_eq!(first_eight(&signal), "73745418");
+
+        let signal = load_signal("69317163492948606335995924319873");
+        assert_eq!(first_eight(&signal), "52432133");
+    }
+
+    #[test]
+    fn test_part2() {
+        let signal = load_signal("03036732577212944063491565474664");
+        assert_eq!(embedded_message(&signal), "84462026");
+
+        let signal = load_signal("02935109699940807407585447034323");
+        assert_eq!(embedded_message(&signal), "78725270");
+
+        let signal = load_signal("03081770884921959731165446850517");
+        assert_eq!(embedded_message(&signal), "53553731");
+    }
+}
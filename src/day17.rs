@@ -0,0 +1,300 @@
+use crate::intcode::ascii::AsciiMachine;
+use crate::intcode::{parse_program, IntcodeError};
+use itertools::Itertools;
+use std::collections::HashSet;
+use std::error::Error;
+
+/// Longest a movement routine or function can be, not counting the newline.
+const MAX_LENGTH: usize = 20;
+
+type Position = (i32, i32);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    fn left(self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    fn right(self) -> Self {
+        self.left().left().left()
+    }
+
+    fn step(self, (x, y): Position) -> Position {
+        match self {
+            Direction::Up => (x, y - 1),
+            Direction::Down => (x, y + 1),
+            Direction::Left => (x - 1, y),
+            Direction::Right => (x + 1, y),
+        }
+    }
+}
+
+/// The scaffolding as seen by the cameras, and where the robot is on it.
+pub struct Scaffold {
+    cells: HashSet<Position>,
+    robot: Option<(Position, Direction)>,
+}
+
+impl Scaffold {
+    pub fn parse(view: &str) -> Self {
+        let mut cells = HashSet::new();
+        let mut robot = None;
+        for (y, line) in view.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let position = (x as i32, y as i32);
+                let direction = match c {
+                    '#' => None,
+                    '^' => Some(Direction::Up),
+                    'v' => Some(Direction::Down),
+                    '<' => Some(Direction::Left),
+                    '>' => Some(Direction::Right),
+                    _ => continue,
+                };
+                cells.insert(position);
+                if let Some(direction) = direction {
+                    robot = Some((position, direction));
+                }
+            }
+        }
+        Scaffold { cells, robot }
+    }
+
+    pub fn intersections(&self) -> Vec<Position> {
+        self.cells
+            .iter()
+            .copied()
+            .filter(|&(x, y)| {
+                [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+                    .iter()
+                    .all(|p| self.cells.contains(p))
+            })
+            .sorted()
+            .collect()
+    }
+
+    pub fn alignment(&self) -> i32 {
+        self.intersections().iter().map(|(x, y)| x * y).sum()
+    }
+
+    /// The moves to the end of the scaffold, going straight over every
+    /// intersection, written as `R,8`.
+    pub fn path(&self) -> Vec<String> {
+        let (mut position, mut direction) = match self.robot {
+            Some(robot) => robot,
+            None => return Vec::new(),
+        };
+        let mut moves = Vec::new();
+
+        let forward = |position: &mut Position, direction: Direction| {
+            let mut count = 0;
+            while self.cells.contains(&direction.step(*position)) {
+                *position = direction.step(*position);
+                count += 1;
+            }
+            count
+        };
+
+        // The robot may start facing along the scaffold
+        let count = forward(&mut position, direction);
+        if count > 0 {
+            moves.push(count.to_string());
+        }
+        loop {
+            let turn = if self.cells.contains(&direction.left().step(position)) {
+                direction = direction.left();
+                'L'
+            } else if self.cells.contains(&direction.right().step(position)) {
+                direction = direction.right();
+                'R'
+            } else {
+                break;
+            };
+            let count = forward(&mut position, direction);
+            moves.push(format!("{},{}", turn, count));
+        }
+        moves
+    }
+}
+
+/// A main routine calling up to three movement functions.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Routines {
+    pub main: Vec<usize>,
+    pub functions: Vec<Vec<String>>,
+}
+
+impl Routines {
+    /// The movement logic as the robot reads it, ending with the answer to
+    /// whether to show the video feed.
+    pub fn to_ascii(&self) -> String {
+        let mut lines = vec![self
+            .main
+            .iter()
+            .map(|&i| (b'A' + i as u8) as char)
+            .join(",")];
+        lines.extend(self.functions.iter().map(|f| f.join(",")));
+        lines.resize(4, String::new());
+        lines.push("n".to_owned());
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    pub fn expand(&self) -> Vec<String> {
+        self.main
+            .iter()
+            .flat_map(|&i| self.functions[i].iter().cloned())
+            .collect()
+    }
+}
+
+fn fits(moves: &[String]) -> bool {
+    moves.iter().map(|m| m.len() + 1).sum::<usize>() - 1 <= MAX_LENGTH
+}
+
+fn search<'a>(
+    moves: &'a [String],
+    functions: &mut Vec<&'a [String]>,
+    main: &mut Vec<usize>,
+) -> bool {
+    if moves.is_empty() {
+        return true;
+    }
+    // Every call takes a letter and a comma
+    if 2 * (main.len() + 1) - 1 > MAX_LENGTH {
+        return false;
+    }
+
+    for i in 0..functions.len() {
+        if moves.starts_with(functions[i]) {
+            main.push(i);
+            if search(&moves[functions[i].len()..], functions, main) {
+                return true;
+            }
+            main.pop();
+        }
+    }
+
+    // Try each prefix that fits as a new function, longest first
+    if functions.len() < 3 {
+        let longest = (1..=moves.len()).take_while(|&n| fits(&moves[..n])).last();
+        for n in (1..=longest.unwrap_or(0)).rev() {
+            functions.push(&moves[..n]);
+            main.push(functions.len() - 1);
+            if search(&moves[n..], functions, main) {
+                return true;
+            }
+            main.pop();
+            functions.pop();
+        }
+    }
+    false
+}
+
+/// Split a path into a main routine and up to three functions that all fit
+/// in the robot's memory.
+pub fn compress(moves: &[String]) -> Option<Routines> {
+    let (mut functions, mut main) = (Vec::new(), Vec::new());
+    if !search(moves, &mut functions, &mut main) {
+        return None;
+    }
+    Some(Routines {
+        main,
+        functions: functions.into_iter().map(|f| f.to_vec()).collect(),
+    })
+}
+
+fn camera_view(program: &[i64]) -> Result<String, IntcodeError> {
+    AsciiMachine::new(program).read_screen()
+}
+
+#[aoc_generator(day17)]
+fn load_program(input: &str) -> Vec<i64> {
+    parse_program(input).unwrap()
+}
+
+#[aoc(day17, part1)]
+fn alignment_parameters(program: &[i64]) -> Result<i32, IntcodeError> {
+    Ok(Scaffold::parse(&camera_view(program)?).alignment())
+}
+
+#[aoc(day17, part2)]
+fn dust_collected(program: &[i64]) -> Result<i64, Box<dyn Error>> {
+    let scaffold = Scaffold::parse(&camera_view(program)?);
+    let routines = compress(&scaffold.path()).ok_or("path doesn't compress")?;
+
+    let mut am = AsciiMachine::new(program);
+    am.machine_mut().store(0, 2);
+    am.send(&routines.to_ascii());
+    am.read_screen()?;
+    Ok(am.take_values().pop().ok_or("no dust reported")?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    const INTERSECTIONS: &str = "..#..........\n..#..........\n#######...###\n#.#...#...#.#\n#############\n..#...#...#..\n..#####...^..";
+    const WALK: &str = "#######...#####\n#.....#...#...#\n#.....#...#...#\n......#...#...#\n......#...###.#\n......#.....#.#\n^########...#.#\n......#.#...#.#\n......#########\n........#...#..\n....#########..\n....#...#......\n....#...#......\n....#...#......\n....#####......";
+
+    #[test]
+    fn test_camera_view() {
+        // Print a zero terminated string, one character per output
+        let program = assemble(
+            "
+            loop:  jz [rb+view], done
+                   out [rb+view]
+                   arb 1
+                   jz 0, loop
+            done:  hlt
+            view:  .data 46, 35, 46, 10, 35, 94, 35, 10, 46, 35, 46, 10, 0
+            ",
+        )
+        .unwrap();
+        assert_eq!(camera_view(&program), Ok(".#.\n#^#\n.#.\n".to_owned()));
+        assert_eq!(alignment_parameters(&program), Ok(1));
+    }
+
+    #[test]
+    fn test_part1() {
+        let scaffold = Scaffold::parse(INTERSECTIONS);
+        assert_eq!(
+            scaffold.intersections(),
+            vec![(2, 2), (2, 4), (6, 4), (10, 4)]
+        );
+        assert_eq!(scaffold.alignment(), 76);
+    }
+
+    #[test]
+    fn test_path() {
+        let path = Scaffold::parse(WALK).path();
+        assert_eq!(
+            path.join(","),
+            "R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2"
+        );
+    }
+
+    #[test]
+    fn test_compress() {
+        let path = Scaffold::parse(WALK).path();
+        let routines = compress(&path).unwrap();
+        assert_eq!(routines.expand(), path);
+        assert!(routines.functions.len() <= 3);
+        assert!(routines.to_ascii().lines().all(|l| l.len() <= MAX_LENGTH));
+
+        // Too many distinct moves to fit in three functions
+        let path = (1..=20).map(|n| format!("L,{}", n)).collect_vec();
+        assert_eq!(compress(&path), None);
+    }
+}
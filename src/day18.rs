@@ -0,0 +1,268 @@
+use crate::geometry::Point2;
+use crate::grid::{Grid, Position};
+use crate::search::dijkstra;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// A route from one node to a key, and what it passes on the way.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Edge {
+    pub key: usize,
+    pub distance: usize,
+    /// Doors on the way, as a mask of the keys that open them.
+    pub doors: u32,
+    /// Other keys on the way, picked up in passing.
+    pub keys: u32,
+}
+
+/// The vault reduced to the distances between its keys.
+///
+/// Keys `a` to `z` are nodes 0 to 25, and the robots' starting points follow.
+pub struct KeyMaze {
+    edges: Vec<Vec<Edge>>,
+    starts: Vec<usize>,
+    all_keys: u32,
+}
+
+impl KeyMaze {
+    pub fn parse(map: &str) -> Self {
+        let grid = parse_vault(map);
+
+        let mut nodes = HashMap::new();
+        let mut starts = Vec::new();
+        let mut all_keys = 0;
+        for (position, &c) in grid.iter() {
+            match c {
+                b'a'..=b'z' => {
+                    let key = usize::from(c - b'a');
+                    nodes.insert(key, position);
+                    all_keys |= 1 << key;
+                }
+                b'@' => {
+                    let node = 26 + starts.len();
+                    nodes.insert(node, position);
+                    starts.push(node);
+                }
+                _ => {}
+            }
+        }
+
+        let mut edges = vec![Vec::new(); 26 + starts.len()];
+        for (&node, &position) in &nodes {
+            edges[node] = Self::reachable(&grid, position, node);
+        }
+        KeyMaze {
+            edges,
+            starts,
+            all_keys,
+        }
+    }
+
+    /// Breadth first search from one node to every key it can reach.
+    fn reachable(grid: &Grid<u8>, start: Position, node: usize) -> Vec<Edge> {
+        let mut edges = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(start);
+        queue.push_back((start, 0, 0, 0));
+
+        while let Some((position, distance, doors, keys)) = queue.pop_front() {
+            for next in grid.neighbors(position) {
+                let c = match grid[next] {
+                    b'#' => continue,
+                    c => c,
+                };
+                if !seen.insert(next) {
+                    continue;
+                }
+
+                let (mut doors, mut keys) = (doors, keys);
+                match c {
+                    b'A'..=b'Z' => doors |= 1 << (c - b'A'),
+                    b'a'..=b'z' => {
+                        let key = usize::from(c - b'a');
+                        if key != node {
+                            edges.push(Edge {
+                                key,
+                                distance: distance + 1,
+                                doors,
+                                keys,
+                            });
+                        }
+                        keys |= 1 << key;
+                    }
+                    _ => {}
+                }
+                queue.push_back((next, distance + 1, doors, keys));
+            }
+        }
+        edges
+    }
+
+    pub fn edges(&self, node: usize) -> &[Edge] {
+        &self.edges[node]
+    }
+
+    pub fn robots(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Fewest steps for the robots to collect every key, searching over
+    /// where each robot is and which keys have been collected.
+    pub fn shortest_collection(&self) -> Option<usize> {
+        let moves = |(robots, collected): &(Vec<usize>, u32)| {
+            let mut moves = Vec::new();
+            for (i, &node) in robots.iter().enumerate() {
+                for edge in &self.edges[node] {
+                    let key = 1 << edge.key;
+                    if collected & key != 0 || edge.doors & !collected != 0 {
+                        continue;
+                    }
+                    let mut next = robots.clone();
+                    next[i] = edge.key;
+                    moves.push(((next, collected | key | edge.keys), edge.distance));
+                }
+            }
+            moves
+        };
+        let start = (self.starts.clone(), 0u32);
+        dijkstra(start, moves, |(_, collected)| *collected == self.all_keys).map(|path| path.cost)
+    }
+}
+
+/// The vault a byte per tile, with anything past the end of a short line or
+/// outside ASCII a wall.
+fn parse_vault(map: &str) -> Grid<u8> {
+    let tile = |c: char| match c {
+        c if c.is_ascii() && c != ' ' => c as u8,
+        _ => b'#',
+    };
+    // Every character is some tile, so this can't fail
+    Grid::parse(map, |c| Some(tile(c))).unwrap()
+}
+
+/// Wall off the middle of the vault and put a robot in each quarter.
+///
+/// Maps that already have more than one robot, or whose robot is on the
+/// edge with no room around it, are left as they are.
+pub fn split_vault(map: &str) -> String {
+    let mut grid = parse_vault(map);
+    let starts = grid
+        .iter()
+        .filter(|&(_, &c)| c == b'@')
+        .map(|(position, _)| position)
+        .collect::<Vec<_>>();
+
+    let (width, height) = (grid.width(), grid.height());
+    let room = |p: Position| (1..width - 1).contains(&p.x) && (1..height - 1).contains(&p.y);
+    if let [start] = starts[..] {
+        if room(start) {
+            let replacement = [b"@#@", b"###", b"@#@"];
+            for (dy, row) in replacement.iter().enumerate() {
+                for (dx, &c) in row.iter().enumerate() {
+                    grid[Point2::new(start.x + dx - 1, start.y + dy - 1)] = c;
+                }
+            }
+        }
+    }
+    (0..grid.height())
+        .map(|y| {
+            (0..grid.width())
+                .map(|x| char::from(grid[Point2::new(x, y)]))
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[aoc(day18, part1)]
+fn collect_keys(input: &str) -> Option<usize> {
+    KeyMaze::parse(input).shortest_collection()
+}
+
+#[aoc(day18, part2)]
+fn collect_keys_with_robots(input: &str) -> Option<usize> {
+    KeyMaze::parse(&split_vault(input)).shortest_collection()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_maze() {
+        let maze = KeyMaze::parse("#########\n#b.A.@.a#\n#########");
+        assert_eq!(maze.robots(), 1);
+        assert_eq!(
+            maze.edges(0),
+            &[Edge {
+                key: 1,
+                distance: 6,
+                doors: 1,
+                keys: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(collect_keys("#########\n#b.A.@.a#\n#########"), Some(8));
+        assert_eq!(
+            collect_keys(
+                "########################\n#f.D.E.e.C.b.A.@.a.B.c.#\n######################.#\n#d.....................#\n########################"
+            ),
+            Some(86)
+        );
+        assert_eq!(
+            collect_keys(
+                "########################\n#...............b.C.D.f#\n#.######################\n#.....@.a.B.c.d.A.e.F.g#\n########################"
+            ),
+            Some(132)
+        );
+        assert_eq!(
+            collect_keys(
+                "#################\n#i.G..c...e..H.p#\n########.########\n#j.A..b...f..D.o#\n########@########\n#k.E..a...g..B.n#\n########.########\n#l.F..d...h..C.m#\n#################"
+            ),
+            Some(136)
+        );
+        assert_eq!(
+            collect_keys(
+                "########################\n#@..............ac.GI.b#\n###d#e#f################\n###A#B#C################\n###g#h#i################\n########################"
+            ),
+            Some(81)
+        );
+    }
+
+    #[test]
+    fn test_part2() {
+        let vault = "#######\n#a.#Cd#\n##...##\n##.@.##\n##...##\n#cB#Ab#\n#######";
+        assert_eq!(
+            split_vault(vault),
+            "#######\n#a.#Cd#\n##@#@##\n#######\n##@#@##\n#cB#Ab#\n#######"
+        );
+        assert_eq!(collect_keys_with_robots(vault), Some(8));
+
+        // No room to split around a robot on the edge
+        for vault in &["@.a\n...", "..a\n..@", "...\n.@.", ".\n@"] {
+            assert_eq!(split_vault(vault), *vault);
+        }
+
+        assert_eq!(
+            collect_keys_with_robots(
+                "###############\n#d.ABC.#.....a#\n######@#@######\n###############\n######@#@######\n#b.....#.....c#\n###############"
+            ),
+            Some(24)
+        );
+        assert_eq!(
+            collect_keys_with_robots(
+                "#############\n#DcBa.#.GhKl#\n#.###@#@#I###\n#e#d#####j#k#\n###C#@#@###J#\n#fEbA.#.FgHi#\n#############"
+            ),
+            Some(32)
+        );
+        assert_eq!(
+            collect_keys_with_robots(
+                "#############\n#g#f.D#..h#l#\n#F###e#E###.#\n#dCba@#@BcIJ#\n#############\n#nK.L@#@G...#\n#M###N#H###.#\n#o#m..#i#jk.#\n#############"
+            ),
+            Some(72)
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use crate::intcode::arena::Arena;
+use crate::intcode::{parse_program, IntcodeError};
+use std::error::Error;
+
+const SCAN_SIZE: usize = 50;
+const SHIP_SIZE: usize = 100;
+
+/// How far right of the origin to look for the beam in a row, per row down.
+/// Rows near the emitter can miss the beam entirely.
+const MAX_SLOPE: usize = 10;
+
+/// The drone program, queried one point at a time by machines reused from
+/// an arena.
+pub struct Beam {
+    arena: Arena,
+}
+
+impl Beam {
+    pub fn new(program: &[i64]) -> Self {
+        Beam {
+            arena: Arena::new(program),
+        }
+    }
+
+    /// Whether the drone is pulled at `(x, y)`.
+    pub fn pulled(&self, x: usize, y: usize) -> Result<bool, IntcodeError> {
+        let output = self.arena.evaluate(&[x as i64, y as i64])?;
+        Ok(output == Some(1))
+    }
+
+    /// Number of points pulled in the `size` by `size` square at the origin.
+    pub fn affected(&self, size: usize) -> Result<usize, IntcodeError> {
+        let mut count = 0;
+        for y in 0..size {
+            for x in 0..size {
+                if self.pulled(x, y)? {
+                    count += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// The first point in row `y` at or after `x` that's in the beam.
+    fn left_edge(&self, mut x: usize, y: usize) -> Result<Option<usize>, IntcodeError> {
+        while x <= MAX_SLOPE * (y + 1) {
+            if self.pulled(x, y)? {
+                return Ok(Some(x));
+            }
+            x += 1;
+        }
+        Ok(None)
+    }
+
+    /// The top left corner of the square closest to the emitter that fits
+    /// entirely inside the beam.
+    ///
+    /// Follows the left edge of the beam down row by row, which only moves
+    /// right, and checks whether the opposite corner of a square sitting on
+    /// it is pulled too. Gives up after `limit` rows.
+    pub fn fit_square(
+        &self,
+        size: usize,
+        limit: usize,
+    ) -> Result<Option<(usize, usize)>, IntcodeError> {
+        let mut x = 0;
+        for bottom in size - 1..limit {
+            x = match self.left_edge(x, bottom)? {
+                Some(edge) => edge,
+                None => continue,
+            };
+            let top = bottom + 1 - size;
+            if self.pulled(x + size - 1, top)? {
+                return Ok(Some((x, top)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[aoc_generator(day19)]
+fn load_program(input: &str) -> Vec<i64> {
+    parse_program(input).unwrap()
+}
+
+#[aoc(day19, part1)]
+fn points_affected(program: &[i64]) -> Result<usize, IntcodeError> {
+    Beam::new(program).affected(SCAN_SIZE)
+}
+
+#[aoc(day19, part2)]
+fn closest_square(program: &[i64]) -> Result<usize, Box<dyn Error>> {
+    let (x, y) = Beam::new(program)
+        .fit_square(SHIP_SIZE, 100 * SHIP_SIZE)?
+        .ok_or("ship doesn't fit in the beam")?;
+    Ok(x * 10_000 + y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    // A beam between the lines y = x / 2 and y = 2x
+    fn beam() -> Vec<i64> {
+        assemble(
+            "
+            in [x]
+            in [y]
+            mul [y], 2, [a]
+            lt [a], [x], [below]
+            mul [x], 2, [a]
+            lt [a], [y], [above]
+            add [below], [above], [a]
+            eq [a], 0, [a]
+            out [a]
+            hlt
+            x:     .data 0
+            y:     .data 0
+            a:     .data 0
+            below: .data 0
+            above: .data 0
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_pulled() {
+        let program = beam();
+        let beam = Beam::new(&program);
+        assert_eq!(beam.pulled(0, 0), Ok(true));
+        assert_eq!(beam.pulled(4, 2), Ok(true));
+        assert_eq!(beam.pulled(5, 2), Ok(false));
+        assert_eq!(beam.pulled(1, 3), Ok(false));
+        assert_eq!(beam.affected(3), Ok(5));
+    }
+
+    #[test]
+    fn test_fit_square() {
+        let program = beam();
+        let beam = Beam::new(&program);
+        for size in 1..=10 {
+            let (x, y) = beam.fit_square(size, 1000).unwrap().unwrap();
+
+            // Compare with a scan of every top left corner
+            let fits = |x: usize, y: usize| {
+                [(x, y), (x + size - 1, y), (x, y + size - 1)]
+                    .iter()
+                    .all(|&(x, y)| beam.pulled(x, y).unwrap())
+            };
+            let expected = (0..)
+                .flat_map(|y| (0..=2 * y).map(move |x| (x, y)))
+                .find(|&(x, y)| fits(x, y))
+                .unwrap();
+            assert_eq!((x, y), expected, "size {}", size);
+        }
+        assert_eq!(beam.fit_square(10, 5), Ok(None));
+    }
+}
@@ -0,0 +1,208 @@
+use crate::geometry::Point2;
+use crate::grid::{Grid, Position};
+use crate::search::dijkstra;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+const START: &str = "AA";
+const END: &str = "ZZ";
+
+/// An open tile next to a portal label.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Node {
+    pub label: String,
+    pub position: Position,
+    /// Whether the label is on the outside edge of the donut.
+    pub outer: bool,
+}
+
+/// The donut maze reduced to walking distances between labelled tiles, and
+/// which tiles are the two ends of a portal.
+pub struct PortalMaze {
+    nodes: Vec<Node>,
+    edges: Vec<Vec<(usize, usize)>>,
+    partners: Vec<Option<usize>>,
+}
+
+impl PortalMaze {
+    pub fn parse(map: &str) -> Self {
+        // Every character is some tile, so this can't fail
+        let grid = Grid::parse(map, |c| Some(if c.is_ascii() { c as u8 } else { b' ' })).unwrap();
+        let at = |x: usize, y: usize| grid.get(Point2::new(x, y)).copied();
+        let (width, height) = (grid.width(), grid.height());
+
+        // Labels read left to right or top to bottom, next to an open tile
+        // on one side or the other
+        let mut nodes = Vec::new();
+        for (Point2 { x, y }, &first) in grid.iter() {
+            if !first.is_ascii_uppercase() {
+                continue;
+            }
+            let candidates = [
+                (x + 1, y, (x + 2, y), (x.wrapping_sub(1), y)),
+                (x, y + 1, (x, y + 2), (x, y.wrapping_sub(1))),
+            ];
+            for &(sx, sy, after, before) in &candidates {
+                let second = match at(sx, sy) {
+                    Some(c) if c.is_ascii_uppercase() => c,
+                    _ => continue,
+                };
+                let (px, py) = match (at(after.0, after.1), at(before.0, before.1)) {
+                    (Some(b'.'), _) => after,
+                    (_, Some(b'.')) => before,
+                    _ => continue,
+                };
+                nodes.push(Node {
+                    label: String::from_utf8_lossy(&[first, second]).into_owned(),
+                    position: Point2::new(px, py),
+                    outer: px == 2 || py == 2 || px + 3 == width || py + 3 == height,
+                });
+            }
+        }
+
+        let partners = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                nodes
+                    .iter()
+                    .enumerate()
+                    .position(|(j, other)| j != i && other.label == node.label)
+            })
+            .collect();
+        let edges = nodes
+            .iter()
+            .map(|node| Self::reachable(&grid, &nodes, node.position))
+            .collect();
+        PortalMaze {
+            nodes,
+            edges,
+            partners,
+        }
+    }
+
+    /// Breadth first search from one tile to every labelled tile it can reach.
+    fn reachable(grid: &Grid<u8>, nodes: &[Node], start: Position) -> Vec<(usize, usize)> {
+        let positions = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.position, i))
+            .collect::<HashMap<_, _>>();
+        let mut edges = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(start);
+        queue.push_back((start, 0));
+
+        while let Some((position, distance)) = queue.pop_front() {
+            for next in grid.neighbors(position) {
+                if grid[next] != b'.' || !seen.insert(next) {
+                    continue;
+                }
+                if let Some(&node) = positions.get(&next) {
+                    edges.push((node, distance + 1));
+                }
+                queue.push_back((next, distance + 1));
+            }
+        }
+        edges
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// The first labelled tile with `label`.
+    pub fn find(&self, label: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| node.label == label)
+    }
+
+    /// Labelled tiles reachable on foot, with the steps to each.
+    pub fn edges(&self, node: usize) -> &[(usize, usize)] {
+        &self.edges[node]
+    }
+
+    /// The other end of a portal.
+    pub fn partner(&self, node: usize) -> Option<usize> {
+        self.partners[node]
+    }
+
+    /// Fewest steps from `AA` to `ZZ`.
+    ///
+    /// In a recursive maze inner portals lead a level down and outer portals
+    /// a level up, outer portals are walls on the top level, and `ZZ` is
+    /// only there on the top level. The search doesn't go deeper than there
+    /// are portals.
+    pub fn shortest_path(&self, recursive: bool) -> Option<usize> {
+        let (start, end) = (self.find(START)?, self.find(END)?);
+        let moves = |&(node, level): &(usize, usize)| {
+            let mut moves = self.edges[node]
+                .iter()
+                .map(|&(to, steps)| ((to, level), steps))
+                .collect::<Vec<_>>();
+            if let Some(partner) = self.partners[node] {
+                let level = match (recursive, self.nodes[node].outer) {
+                    (false, _) => Some(0),
+                    (true, true) => level.checked_sub(1),
+                    (true, false) => Some(level + 1).filter(|&l| l <= self.nodes.len()),
+                };
+                if let Some(level) = level {
+                    moves.push(((partner, level), 1));
+                }
+            }
+            moves
+        };
+        dijkstra((start, 0), moves, |&state| state == (end, 0)).map(|path| path.cost)
+    }
+}
+
+#[aoc(day20, part1)]
+fn shortest_path(input: &str) -> Option<usize> {
+    PortalMaze::parse(input).shortest_path(false)
+}
+
+#[aoc(day20, part2)]
+fn shortest_recursive_path(input: &str) -> Option<usize> {
+    PortalMaze::parse(input).shortest_path(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMALL: &str = "         A           \n         A           \n  #######.#########  \n  #######.........#  \n  #######.#######.#  \n  #######.#######.#  \n  #######.#######.#  \n  #####  B    ###.#  \nBC...##  C    ###.#  \n  ##.##       ###.#  \n  ##...DE  F  ###.#  \n  #####    G  ###.#  \n  #########.#####.#  \nDE..#######...###.#  \n  #.#########.###.#  \nFG..#########.....#  \n  ###########.#####  \n             Z       \n             Z       ";
+    const LARGE: &str = "                   A               \n                   A               \n  #################.#############  \n  #.#...#...................#.#.#  \n  #.#.#.###.###.###.#########.#.#  \n  #.#.#.......#...#.....#.#.#...#  \n  #.#########.###.#####.#.#.###.#  \n  #.............#.#.....#.......#  \n  ###.###########.###.#.#.#.#.###  \n  #.....#        A   C    #.#.#.#  \n  #######        S   P    #####.#  \n  #.#...#                 #......VT\n  #.#.#.#                 #.#####  \n  #...#.#               YN....#.#  \n  #.###.#                 #####.#  \nDI....#.#                 #.....#  \n  #####.#                 #.###.#  \nZZ......#               QG....#..AS\n  ###.###                 #######  \nJO..#.#.#                 #.....#  \n  #.#.#.#                 ###.#.#  \n  #...#..DI             BU....#..LF\n  #####.#                 #.#####  \nYN......#               VT..#....QG\n  #.###.#                 #.###.#  \n  #.#...#                 #.....#  \n  ###.###    J L     J    #.#.###  \n  #.....#    O F     P    #.#...#  \n  #.###.#####.#.#####.#####.###.#  \n  #...#.#.#...#.....#.....#.#...#  \n  #.#####.###.###.#.#.#########.#  \n  #...#.#.....#...#.#.#.#.....#.#  \n  #.###.#####.###.###.#.#.#######  \n  #.#.........#...#.............#  \n  #########.###.###.#############  \n           B   J   C               \n           U   P   P               ";
+    const RECURSIVE: &str = "             Z L X W       C                 \n             Z P Q B       K                 \n  ###########.#.#.#.#######.###############  \n  #...#.......#.#.......#.#.......#.#.#...#  \n  ###.#.#.#.#.#.#.#.###.#.#.#######.#.#.###  \n  #.#...#.#.#...#.#.#...#...#...#.#.......#  \n  #.###.#######.###.###.#.###.###.#.#######  \n  #...#.......#.#...#...#.............#...#  \n  #.#########.#######.#.#######.#######.###  \n  #...#.#    F       R I       Z    #.#.#.#  \n  #.###.#    D       E C       H    #.#.#.#  \n  #.#...#                           #...#.#  \n  #.###.#                           #.###.#  \n  #.#....OA                       WB..#.#..ZH\n  #.###.#                           #.#.#.#  \nCJ......#                           #.....#  \n  #######                           #######  \n  #.#....CK                         #......IC\n  #.###.#                           #.###.#  \n  #.....#                           #...#.#  \n  ###.###                           #.#.#.#  \nXF....#.#                         RF..#.#.#  \n  #####.#                           #######  \n  #......CJ                       NM..#...#  \n  ###.#.#                           #.###.#  \nRE....#.#                           #......RF\n  ###.###        X   X       L      #.#.#.#  \n  #.....#        F   Q       P      #.#.#.#  \n  ###.###########.###.#######.#########.###  \n  #.....#...#.....#.......#...#.....#.#...#  \n  #####.#.###.#######.#######.###.###.#.#.#  \n  #.......#.......#.#.#.#.#...#...#...#.#.#  \n  #####.###.#####.#.#.#.#.###.###.#.###.###  \n  #.......#.....#.#...#...............#...#  \n  #############.#.#.###.###################  \n               A O F   N                     \n               A A D   M                     ";
+
+    #[test]
+    fn test_portal_maze() {
+        let maze = PortalMaze::parse(SMALL);
+        assert_eq!(maze.nodes().len(), 8);
+
+        let start = maze.find("AA").unwrap();
+        assert_eq!(maze.nodes()[start].position, Point2::new(9, 2));
+        assert!(maze.nodes()[start].outer);
+        assert_eq!(maze.partner(start), None);
+
+        // The inner end of BC is first in reading order
+        let inner = maze.find("BC").unwrap();
+        let outer = maze.partner(inner).unwrap();
+        assert_eq!(maze.nodes()[outer].label, "BC");
+        assert_eq!(maze.partner(outer), Some(inner));
+        assert!(!maze.nodes()[inner].outer);
+        assert!(maze.nodes()[outer].outer);
+        assert!(maze.edges(start).contains(&(inner, 4)));
+    }
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(shortest_path(SMALL), Some(23));
+        assert_eq!(shortest_path(LARGE), Some(58));
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(shortest_recursive_path(SMALL), Some(26));
+        assert_eq!(shortest_recursive_path(RECURSIVE), Some(396));
+        assert_eq!(shortest_recursive_path(LARGE), None);
+    }
+}
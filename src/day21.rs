@@ -0,0 +1,329 @@
+use crate::intcode::ascii::AsciiMachine;
+use crate::intcode::{parse_program, IntcodeError};
+use std::error::Error;
+use std::fmt;
+
+/// Most instructions the springdroid can hold.
+const MAX_INSTRUCTIONS: usize = 15;
+
+/// Registers that can be read, the ground sensors `A` to `I` and the
+/// temporary and jump registers.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Register {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    T,
+    J,
+}
+
+/// Registers that can be written.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Writable {
+    T,
+    J,
+}
+
+impl From<Writable> for Register {
+    fn from(register: Writable) -> Self {
+        match register {
+            Writable::T => Register::T,
+            Writable::J => Register::J,
+        }
+    }
+}
+
+impl Register {
+    /// How many tiles ahead a sensor looks.
+    fn distance(self) -> Option<usize> {
+        use Register::*;
+
+        [A, B, C, D, E, F, G, H, I]
+            .iter()
+            .position(|&r| r == self)
+            .map(|i| i + 1)
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Instruction {
+    And(Register, Writable),
+    Or(Register, Writable),
+    Not(Register, Writable),
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, x, y) = match *self {
+            Instruction::And(x, y) => ("AND", x, y),
+            Instruction::Or(x, y) => ("OR", x, y),
+            Instruction::Not(x, y) => ("NOT", x, y),
+        };
+        write!(f, "{} {} {}", name, x, Register::from(y))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Mode {
+    /// Sensors `A` to `D`.
+    Walk,
+    /// All nine sensors.
+    Run,
+}
+
+impl Mode {
+    fn sensors(self) -> usize {
+        match self {
+            Mode::Walk => 4,
+            Mode::Run => 9,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ScriptError {
+    TooLong(usize),
+    SensorUnavailable(Register),
+}
+
+impl Error for ScriptError {}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::TooLong(n) => {
+                write!(f, "{} instructions, at most {} fit", n, MAX_INSTRUCTIONS)
+            }
+            ScriptError::SensorUnavailable(r) => write!(f, "sensor {} needs RUN mode", r),
+        }
+    }
+}
+
+/// A springscript program that fits in the droid, with the sensors it reads
+/// available in its mode.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Script {
+    mode: Mode,
+    instructions: Vec<Instruction>,
+}
+
+impl Script {
+    pub fn new(mode: Mode, instructions: Vec<Instruction>) -> Result<Self, ScriptError> {
+        if instructions.len() > MAX_INSTRUCTIONS {
+            return Err(ScriptError::TooLong(instructions.len()));
+        }
+        for instruction in &instructions {
+            let (Instruction::And(x, _) | Instruction::Or(x, _) | Instruction::Not(x, _)) =
+                *instruction;
+            if x.distance().is_some_and(|d| d > mode.sensors()) {
+                return Err(ScriptError::SensorUnavailable(x));
+            }
+        }
+        Ok(Script { mode, instructions })
+    }
+
+    /// Whether the droid jumps, given which of the tiles ahead have ground.
+    pub fn jumps(&self, ground: &[bool]) -> bool {
+        let (mut t, mut j) = (false, false);
+        for instruction in &self.instructions {
+            let read = |r: Register| match r {
+                Register::T => t,
+                Register::J => j,
+                _ => r
+                    .distance()
+                    .is_none_or(|d| ground.get(d - 1) != Some(&false)),
+            };
+            let (value, target) = match *instruction {
+                Instruction::And(x, y) => (read(x) && read(y.into()), y),
+                Instruction::Or(x, y) => (read(x) || read(y.into()), y),
+                Instruction::Not(x, y) => (!read(x), y),
+            };
+            match target {
+                Writable::T => t = value,
+                Writable::J => j = value,
+            }
+        }
+        j
+    }
+
+    /// The script as the droid reads it, ending with the mode command.
+    pub fn to_ascii(&self) -> String {
+        let mut text = self
+            .instructions
+            .iter()
+            .map(|i| format!("{}\n", i))
+            .collect::<String>();
+        text.push_str(match self.mode {
+            Mode::Walk => "WALK\n",
+            Mode::Run => "RUN\n",
+        });
+        text
+    }
+}
+
+/// How a survey went.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    /// The droid made it across and reported the hull damage.
+    Damage(i64),
+    /// The droid fell into space, with the droid's drawing of where.
+    Fell(String),
+}
+
+pub fn survey(program: &[i64], script: &Script) -> Result<Outcome, IntcodeError> {
+    let mut am = AsciiMachine::new(program);
+    am.send(&script.to_ascii());
+    let drawing = am.read_screen()?;
+    Ok(match am.take_values().pop() {
+        Some(damage) => Outcome::Damage(damage),
+        None => Outcome::Fell(drawing),
+    })
+}
+
+/// Jump if there's a hole in the next three tiles and ground to land on.
+fn walk_script() -> Script {
+    use Instruction::*;
+
+    Script::new(
+        Mode::Walk,
+        vec![
+            Not(Register::A, Writable::J),
+            Not(Register::B, Writable::T),
+            Or(Register::T, Writable::J),
+            Not(Register::C, Writable::T),
+            Or(Register::T, Writable::J),
+            And(Register::D, Writable::J),
+        ],
+    )
+    .unwrap()
+}
+
+/// As when walking, but only if there's somewhere to go after landing,
+/// either a step or another jump.
+fn run_script() -> Script {
+    use Instruction::*;
+
+    let mut instructions = walk_script().instructions;
+    instructions.extend(&[
+        Not(Register::E, Writable::T),
+        Not(Register::T, Writable::T),
+        Or(Register::H, Writable::T),
+        And(Register::T, Writable::J),
+    ]);
+    Script::new(Mode::Run, instructions).unwrap()
+}
+
+fn hull_damage(program: &[i64], script: &Script) -> Result<i64, Box<dyn Error>> {
+    match survey(program, script)? {
+        Outcome::Damage(damage) => Ok(damage),
+        Outcome::Fell(view) => Err(format!("droid fell\n{}", view).into()),
+    }
+}
+
+#[aoc_generator(day21)]
+fn load_program(input: &str) -> Vec<i64> {
+    parse_program(input).unwrap()
+}
+
+#[aoc(day21, part1)]
+fn walking_damage(program: &[i64]) -> Result<i64, Box<dyn Error>> {
+    hull_damage(program, &walk_script())
+}
+
+#[aoc(day21, part2)]
+fn running_damage(program: &[i64]) -> Result<i64, Box<dyn Error>> {
+    hull_damage(program, &run_script())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    // Whether a droid following `script` makes it across a hull
+    fn crosses(script: &Script, hull: &str) -> bool {
+        let ground = hull.chars().map(|c| c == '#').collect::<Vec<_>>();
+        let mut position = 0;
+        while position < ground.len() {
+            if !ground[position] {
+                return false;
+            }
+            let ahead = &ground[(position + 1).min(ground.len())..];
+            position += if script.jumps(ahead) { 4 } else { 1 };
+        }
+        true
+    }
+
+    #[test]
+    fn test_script() {
+        use Instruction::*;
+
+        assert_eq!(
+            walk_script().to_ascii(),
+            "NOT A J\nNOT B T\nOR T J\nNOT C T\nOR T J\nAND D J\nWALK\n"
+        );
+        assert_eq!(
+            Script::new(Mode::Walk, vec![Or(Register::E, Writable::J)]),
+            Err(ScriptError::SensorUnavailable(Register::E))
+        );
+        assert_eq!(
+            Script::new(Mode::Run, vec![Or(Register::E, Writable::J); 16]),
+            Err(ScriptError::TooLong(16))
+        );
+    }
+
+    #[test]
+    fn test_jumps() {
+        let walk = walk_script();
+        for hull in &[
+            "#####.###########",
+            "#####..#.########",
+            "#####...#########",
+        ] {
+            assert!(crosses(&walk, hull), "{}", hull);
+        }
+        assert!(!crosses(&walk, "#####.#.##..#.###"));
+
+        let run = run_script();
+        for hull in &[
+            "#####.###########",
+            "#####.#.##..#.###",
+            "#####.##.##.#.###",
+            "#####...#.#######",
+        ] {
+            assert!(crosses(&run, hull), "{}", hull);
+        }
+    }
+
+    #[test]
+    fn test_survey() {
+        // Report damage once WALK is read, a RUN script gets no answer
+        let program = assemble(
+            "
+            loop: in [c]
+                  eq [c], 75, [k]
+                  jz [k], loop
+                  in [c]
+                  out 19355
+                  hlt
+            c:    .data 0
+            k:    .data 0
+            ",
+        )
+        .unwrap();
+        assert_eq!(walking_damage(&program).unwrap(), 19355);
+        assert!(running_damage(&program).is_err());
+    }
+}
@@ -0,0 +1,366 @@
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+const SMALL_DECK: i64 = 10_007;
+const HUGE_DECK: i64 = 119_315_717_514_047;
+const HUGE_SHUFFLES: u64 = 101_741_582_076_661;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Technique {
+    NewStack,
+    Cut(i64),
+    Increment(i64),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseTechniqueError(String);
+
+impl error::Error for ParseTechniqueError {}
+
+impl fmt::Display for ParseTechniqueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid technique {:?}", self.0)
+    }
+}
+
+impl FromStr for Technique {
+    type Err = ParseTechniqueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseTechniqueError(s.to_owned());
+        let s = s.trim();
+        if s == "deal into new stack" {
+            Ok(Technique::NewStack)
+        } else if let Some(n) = s.strip_prefix("cut ") {
+            Ok(Technique::Cut(n.parse().map_err(|_| error())?))
+        } else if let Some(n) = s.strip_prefix("deal with increment ") {
+            Ok(Technique::Increment(n.parse().map_err(|_| error())?))
+        } else {
+            Err(error())
+        }
+    }
+}
+
+impl fmt::Display for Technique {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Technique::NewStack => write!(f, "deal into new stack"),
+            Technique::Cut(n) => write!(f, "cut {}", n),
+            Technique::Increment(n) => write!(f, "deal with increment {}", n),
+        }
+    }
+}
+
+impl Technique {
+    /// Shuffle a deck, given top card first, by actually dealing it.
+    pub fn deal(self, deck: &[i64]) -> Vec<i64> {
+        let mut next = deck.to_vec();
+        let len = deck.len();
+        match self {
+            Technique::NewStack => next.reverse(),
+            Technique::Cut(n) if len > 0 => next.rotate_left(n.rem_euclid(len as i64) as usize),
+            Technique::Increment(n) if len > 0 => {
+                for (i, &card) in deck.iter().enumerate() {
+                    next[(i as i64 * n).rem_euclid(len as i64) as usize] = card;
+                }
+            }
+            _ => {}
+        }
+        next
+    }
+}
+
+/// The map from a card's position before a shuffle to after it,
+/// `x -> a * x + b` modulo the deck size `m`.
+///
+/// Every technique is a map like this, and so is any sequence of them.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LinearShuffle {
+    pub a: i64,
+    pub b: i64,
+    pub m: i64,
+}
+
+/// `x * y % m`, without overflowing.
+fn mul_mod(x: i64, y: i64, m: i64) -> i64 {
+    (i128::from(x) * i128::from(y)).rem_euclid(i128::from(m)) as i64
+}
+
+/// The inverse of `x` modulo `m`, by the extended Euclidean algorithm.
+fn inverse_mod(x: i64, m: i64) -> Option<i64> {
+    let (mut r0, mut r1) = (i128::from(m), i128::from(x).rem_euclid(i128::from(m)));
+    let (mut t0, mut t1) = (0i128, 1i128);
+    while r1 != 0 {
+        let q = r0 / r1;
+        let (r, t) = (r0 - q * r1, t0 - q * t1);
+        r0 = r1;
+        r1 = r;
+        t0 = t1;
+        t1 = t;
+    }
+    if r0 != 1 {
+        return None;
+    }
+    Some(t0.rem_euclid(i128::from(m)) as i64)
+}
+
+impl LinearShuffle {
+    /// Leaves every card where it is.
+    pub fn identity(m: i64) -> Self {
+        LinearShuffle { a: 1, b: 0, m }
+    }
+
+    pub fn from_technique(technique: Technique, m: i64) -> Self {
+        let (a, b) = match technique {
+            Technique::NewStack => (-1, -1),
+            Technique::Cut(n) => (1, -n),
+            Technique::Increment(n) => (n, 0),
+        };
+        LinearShuffle {
+            a: a.rem_euclid(m),
+            b: b.rem_euclid(m),
+            m,
+        }
+    }
+
+    pub fn from_techniques(techniques: &[Technique], m: i64) -> Self {
+        techniques
+            .iter()
+            .fold(Self::identity(m), |shuffle, &technique| {
+                shuffle.then(Self::from_technique(technique, m))
+            })
+    }
+
+    /// Where the card at `position` ends up.
+    pub fn apply(self, position: i64) -> i64 {
+        (mul_mod(self.a, position, self.m) + self.b).rem_euclid(self.m)
+    }
+
+    /// This shuffle followed by `next`.
+    pub fn then(self, next: Self) -> Self {
+        LinearShuffle {
+            a: mul_mod(next.a, self.a, self.m),
+            b: (mul_mod(next.a, self.b, self.m) + next.b).rem_euclid(self.m),
+            m: self.m,
+        }
+    }
+
+    /// The shuffle that undoes this one, if cards don't collide.
+    pub fn inverse(self) -> Option<Self> {
+        let a = inverse_mod(self.a, self.m)?;
+        Some(LinearShuffle {
+            a,
+            b: mul_mod(-a, self.b, self.m),
+            m: self.m,
+        })
+    }
+
+    /// The cards from the top after shuffling a deck in factory order, for
+    /// decks small enough to hold.
+    pub fn deck(self) -> Vec<i64> {
+        let mut deck = vec![0; self.m as usize];
+        for card in 0..self.m {
+            deck[self.apply(card) as usize] = card;
+        }
+        deck
+    }
+
+    /// This shuffle repeated `times` times, by squaring.
+    pub fn repeat(self, mut times: u64) -> Self {
+        let (mut result, mut square) = (Self::identity(self.m), self);
+        while times > 0 {
+            if times & 1 == 1 {
+                result = result.then(square);
+            }
+            square = square.then(square);
+            times >>= 1;
+        }
+        result
+    }
+}
+
+/// A small deck after each technique, dealt for real and by composing the
+/// techniques so far, which should always agree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Frame {
+    /// The technique just used, `None` for the deck in factory order.
+    pub technique: Option<Technique>,
+    pub dealt: Vec<i64>,
+    pub composed: Vec<i64>,
+    /// The composed transform, `x -> a * x + b`.
+    pub shuffle: LinearShuffle,
+}
+
+pub fn frames(techniques: &[Technique], size: i64) -> Vec<Frame> {
+    let identity = LinearShuffle::identity(size);
+    let first = Frame {
+        technique: None,
+        dealt: identity.deck(),
+        composed: identity.deck(),
+        shuffle: identity,
+    };
+    let mut frames = vec![first];
+    for &technique in techniques {
+        let last = &frames[frames.len() - 1];
+        let shuffle = last
+            .shuffle
+            .then(LinearShuffle::from_technique(technique, size));
+        let frame = Frame {
+            technique: Some(technique),
+            dealt: technique.deal(&last.dealt),
+            composed: shuffle.deck(),
+            shuffle,
+        };
+        frames.push(frame);
+    }
+    frames
+}
+
+/// One line per frame: the technique, the cards from the top, and the
+/// composed transform. Frames where dealing and composing disagree also show
+/// the composed deck.
+pub fn render_frames(frames: &[Frame]) -> String {
+    let names = frames
+        .iter()
+        .map(|frame| match frame.technique {
+            Some(technique) => technique.to_string(),
+            None => "factory order".to_owned(),
+        })
+        .collect::<Vec<_>>();
+    let width = names.iter().map(String::len).max().unwrap_or(0);
+    let cards = |deck: &[i64]| {
+        deck.iter()
+            .map(|card| card.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let mut text = String::new();
+    for (name, frame) in names.iter().zip(frames) {
+        let shuffle = frame.shuffle;
+        text.push_str(&format!(
+            "{:width$}  {}  x -> {}x + {}",
+            name,
+            cards(&frame.dealt),
+            shuffle.a,
+            shuffle.b,
+            width = width
+        ));
+        if frame.dealt != frame.composed {
+            text.push_str(&format!("  != {}", cards(&frame.composed)));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+#[aoc_generator(day22)]
+fn load_techniques(input: &str) -> Vec<Technique> {
+    input.lines().map(|s| s.parse().unwrap()).collect()
+}
+
+#[aoc(day22, part1)]
+fn position_of_2019(techniques: &[Technique]) -> i64 {
+    LinearShuffle::from_techniques(techniques, SMALL_DECK).apply(2019)
+}
+
+#[aoc(day22, part2)]
+fn card_at_2020(techniques: &[Technique]) -> Option<i64> {
+    let shuffle = LinearShuffle::from_techniques(techniques, HUGE_DECK).repeat(HUGE_SHUFFLES);
+    Some(shuffle.inverse()?.apply(2020))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The deck in order from the top, after shuffling factory order
+    fn deck(techniques: &str, size: i64) -> Vec<i64> {
+        LinearShuffle::from_techniques(&load_techniques(techniques), size).deck()
+    }
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(
+            deck(
+                "deal with increment 7\ndeal into new stack\ndeal into new stack",
+                10
+            ),
+            vec![0, 3, 6, 9, 2, 5, 8, 1, 4, 7]
+        );
+        assert_eq!(
+            deck("cut 6\ndeal with increment 7\ndeal into new stack", 10),
+            vec![3, 0, 7, 4, 1, 8, 5, 2, 9, 6]
+        );
+        assert_eq!(
+            deck("deal with increment 7\ndeal with increment 9\ncut -2", 10),
+            vec![6, 3, 0, 7, 4, 1, 8, 5, 2, 9]
+        );
+        assert_eq!(
+            deck("deal into new stack\ncut -2\ndeal with increment 7\ncut 8\ncut -4\ndeal with increment 7\ncut 3\ndeal with increment 9\ndeal with increment 3\ncut -1", 10),
+            vec![9, 2, 5, 8, 1, 4, 7, 0, 3, 6]
+        );
+        assert!("deal with increment x".parse::<Technique>().is_err());
+    }
+
+    #[test]
+    fn test_frames() {
+        let techniques = load_techniques("cut 6\ndeal with increment 7\ndeal into new stack");
+        assert_eq!(
+            render_frames(&frames(&techniques, 10)),
+            "\
+factory order          0 1 2 3 4 5 6 7 8 9  x -> 1x + 0
+cut 6                  6 7 8 9 0 1 2 3 4 5  x -> 1x + 4
+deal with increment 7  6 9 2 5 8 1 4 7 0 3  x -> 7x + 8
+deal into new stack    3 0 7 4 1 8 5 2 9 6  x -> 3x + 1
+"
+        );
+
+        let techniques =
+            load_techniques("deal with increment 7\ndeal into new stack\ndeal into new stack");
+        assert_eq!(
+            render_frames(&frames(&techniques, 10)),
+            "\
+factory order          0 1 2 3 4 5 6 7 8 9  x -> 1x + 0
+deal with increment 7  0 3 6 9 2 5 8 1 4 7  x -> 7x + 0
+deal into new stack    7 4 1 8 5 2 9 6 3 0  x -> 3x + 9
+deal into new stack    0 3 6 9 2 5 8 1 4 7  x -> 7x + 0
+"
+        );
+
+        // A composed deck that's wrong shows up next to the dealt one
+        let mut broken = frames(&techniques[..1], 10);
+        broken[1].composed.swap(0, 1);
+        assert!(render_frames(&broken).ends_with("  != 3 0 6 9 2 5 8 1 4 7\n"));
+    }
+
+    #[test]
+    fn test_linear_shuffle() {
+        let techniques = load_techniques("cut 6\ndeal with increment 7\ndeal into new stack");
+        let shuffle = LinearShuffle::from_techniques(&techniques, SMALL_DECK);
+        let inverse = shuffle.inverse().unwrap();
+        for card in &[0, 1, 2019, SMALL_DECK - 1] {
+            assert_eq!(inverse.apply(shuffle.apply(*card)), *card);
+        }
+
+        let repeated = (0..25).fold(LinearShuffle::identity(SMALL_DECK), |s, _| s.then(shuffle));
+        assert_eq!(shuffle.repeat(25), repeated);
+        assert_eq!(shuffle.repeat(0), LinearShuffle::identity(SMALL_DECK));
+
+        // Large enough that products overflow without 128 bits
+        let shuffle = LinearShuffle::from_techniques(&techniques, HUGE_DECK);
+        let inverse = shuffle.repeat(HUGE_SHUFFLES).inverse().unwrap();
+        assert_eq!(inverse.inverse(), Some(shuffle.repeat(HUGE_SHUFFLES)));
+        assert_eq!(
+            shuffle.repeat(HUGE_SHUFFLES).apply(inverse.apply(2020)),
+            2020
+        );
+
+        // Increments that share a factor with the deck size lose cards
+        assert_eq!(
+            LinearShuffle::from_technique(Technique::Increment(2), 10).inverse(),
+            None
+        );
+    }
+}
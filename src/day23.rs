@@ -0,0 +1,202 @@
+use crate::intcode::{parse_program, IntcodeError, IntcodeMachine, State};
+use std::error::Error;
+
+const COMPUTERS: usize = 50;
+const NAT: usize = 255;
+
+/// Input a computer reads when no packet is waiting.
+const NO_PACKET: i64 = -1;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Packet {
+    pub x: i64,
+    pub y: i64,
+}
+
+/// Networked computers run in turn on one thread.
+pub struct Network {
+    machines: Vec<IntcodeMachine>,
+    /// Output from each computer that doesn't make a whole packet yet.
+    partial: Vec<Vec<i64>>,
+    idle: bool,
+}
+
+impl Network {
+    /// Boot `size` computers, each told its address.
+    pub fn new(program: &[i64], size: usize) -> Self {
+        let machines = (0..size)
+            .map(|address| {
+                let mut im = IntcodeMachine::new(program, None, None);
+                im.set_idle_input(Some(NO_PACKET));
+                im.push_input(address as i64);
+                im
+            })
+            .collect();
+        Network {
+            machines,
+            partial: vec![Vec::new(); size],
+            idle: false,
+        }
+    }
+
+    pub fn deliver(&mut self, address: usize, packet: Packet) {
+        let im = &mut self.machines[address];
+        im.push_input(packet.x);
+        im.push_input(packet.y);
+    }
+
+    /// Run each computer in turn until it polls for a packet and finds none,
+    /// or halts. Packets between computers are delivered straight away, and
+    /// ones sent to other addresses are returned.
+    pub fn round(&mut self) -> Result<Vec<(usize, Packet)>, IntcodeError> {
+        let mut outside = Vec::new();
+        let mut sent = 0;
+        for i in 0..self.machines.len() {
+            let polls = self.machines[i].idle_reads();
+            while self.machines[i].idle_reads() == polls {
+                match self.machines[i].step()? {
+                    State::Output(v) => self.partial[i].push(v),
+                    State::Running => continue,
+                    _ => break,
+                }
+                if let [address, x, y] = self.partial[i][..] {
+                    self.partial[i].clear();
+                    sent += 1;
+                    let packet = Packet { x, y };
+                    match address as usize {
+                        address if address < self.machines.len() => self.deliver(address, packet),
+                        address => outside.push((address, packet)),
+                    }
+                }
+            }
+        }
+        self.idle = sent == 0;
+        Ok(outside)
+    }
+
+    /// Whether nothing was sent in the last round, so every computer is
+    /// waiting on an empty queue.
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+}
+
+/// The first packet sent to the NAT.
+fn first_nat_packet(program: &[i64], size: usize) -> Result<i64, IntcodeError> {
+    let mut network = Network::new(program, size);
+    loop {
+        if let Some((_, packet)) = network
+            .round()?
+            .into_iter()
+            .find(|&(address, _)| address == NAT)
+        {
+            return Ok(packet.y);
+        }
+    }
+}
+
+/// The first `y` the NAT sends to address 0 twice in a row when the network
+/// goes idle.
+fn repeated_wake_up(program: &[i64], size: usize) -> Result<i64, Box<dyn Error>> {
+    let mut network = Network::new(program, size);
+    let (mut nat, mut last_sent) = (None, None);
+    loop {
+        for (address, packet) in network.round()? {
+            if address == NAT {
+                nat = Some(packet);
+            }
+        }
+        if network.is_idle() {
+            let packet = nat.ok_or("network idle before anything reached the NAT")?;
+            if last_sent == Some(packet.y) {
+                return Ok(packet.y);
+            }
+            network.deliver(0, packet);
+            last_sent = Some(packet.y);
+        }
+    }
+}
+
+#[aoc_generator(day23)]
+fn load_program(input: &str) -> Vec<i64> {
+    parse_program(input).unwrap()
+}
+
+#[aoc(day23, part1)]
+fn part1(program: &[i64]) -> Result<i64, IntcodeError> {
+    first_nat_packet(program, COMPUTERS)
+}
+
+#[aoc(day23, part2)]
+fn part2(program: &[i64]) -> Result<i64, Box<dyn Error>> {
+    repeated_wake_up(program, COMPUTERS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    // Each computer sends its address and ten times that to the next one,
+    // the last of three sending to the NAT, then forwards any packet it gets
+    // to the NAT unchanged
+    fn relay() -> Vec<i64> {
+        assemble(
+            "
+                  in [addr]
+                  add [addr], 1, [dest]
+                  eq [dest], 3, [last]
+                  jz [last], send
+                  add 255, 0, [dest]
+            send: out [dest]
+                  out [addr]
+                  mul [addr], 10, [y]
+                  out [y]
+            poll: in [x]
+                  eq [x], -1, [idle]
+                  jnz [idle], poll
+                  in [y]
+                  out 255
+                  out [x]
+                  out [y]
+                  jz 0, poll
+            addr: .data 0
+            dest: .data 0
+            last: .data 0
+            x:    .data 0
+            y:    .data 0
+            idle: .data 0
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_network() {
+        let program = relay();
+        let mut network = Network::new(&program, 3);
+
+        // Packets to later computers are picked up in the same round
+        assert_eq!(
+            network.round(),
+            Ok(vec![
+                (NAT, Packet { x: 0, y: 0 }),
+                (NAT, Packet { x: 2, y: 20 }),
+                (NAT, Packet { x: 1, y: 10 })
+            ])
+        );
+        assert!(!network.is_idle());
+        assert_eq!(network.round(), Ok(vec![]));
+        assert!(network.is_idle());
+
+        network.deliver(1, Packet { x: 7, y: 8 });
+        assert_eq!(network.round(), Ok(vec![(NAT, Packet { x: 7, y: 8 })]));
+    }
+
+    #[test]
+    fn test_nat() {
+        let program = relay();
+        assert_eq!(first_nat_packet(&program, 3), Ok(0));
+        assert_eq!(repeated_wake_up(&program, 3).unwrap(), 10);
+    }
+}
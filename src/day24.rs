@@ -0,0 +1,544 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+const MINUTES: usize = 200;
+
+/// The puzzle's grids are 5x5, which fit a bitboard.
+const SIZE: usize = 5;
+const CENTER: usize = 12;
+
+/// Neighbour counts that give a tile a bug next minute.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rules {
+    /// Counts that infest an empty tile.
+    pub birth: Vec<usize>,
+    /// Counts that a bug survives with.
+    pub survival: Vec<usize>,
+}
+
+/// A bug dies unless there's exactly one bug next to it, and an empty tile
+/// becomes infested if one or two bugs are.
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            birth: vec![1, 2],
+            survival: vec![1],
+        }
+    }
+}
+
+impl Rules {
+    fn lives(&self, bug: bool, neighbours: usize) -> bool {
+        if bug {
+            self.survival.contains(&neighbours)
+        } else {
+            self.birth.contains(&neighbours)
+        }
+    }
+}
+
+/// A 5x5 grid of bugs, one bit per tile in reading order, so the grid is its
+/// own biodiversity rating.
+type Bits = u32;
+
+/// Tiles next to `i` on the same 5x5 grid.
+fn adjacent(i: usize) -> Bits {
+    let (x, y) = (i % SIZE, i / SIZE);
+    let mut mask = 0;
+    if x > 0 {
+        mask |= 1 << (i - 1);
+    }
+    if x + 1 < SIZE {
+        mask |= 1 << (i + 1);
+    }
+    if y > 0 {
+        mask |= 1 << (i - SIZE);
+    }
+    if y + 1 < SIZE {
+        mask |= 1 << (i + SIZE);
+    }
+    mask
+}
+
+fn step_bits(grid: Bits, rules: &Rules) -> Bits {
+    (0..SIZE * SIZE)
+        .filter(|&i| {
+            let neighbours = (grid & adjacent(i)).count_ones() as usize;
+            rules.lives(grid & 1 << i != 0, neighbours)
+        })
+        .fold(0, |next, i| next | 1 << i)
+}
+
+const TOP: Bits = 0b11111;
+const BOTTOM: Bits = TOP << 20;
+const LEFT: Bits = 0b00001_00001_00001_00001_00001;
+const RIGHT: Bits = LEFT << 4;
+
+/// Tiles next to `i` on the 5x5 grid around this one, and on the grid
+/// inside it.
+fn adjacent_levels(i: usize) -> (Bits, Bits) {
+    let (x, y) = (i % SIZE, i / SIZE);
+    let mut outer = 0;
+    if x == 0 {
+        outer |= 1 << (CENTER - 1);
+    }
+    if x + 1 == SIZE {
+        outer |= 1 << (CENTER + 1);
+    }
+    if y == 0 {
+        outer |= 1 << (CENTER - SIZE);
+    }
+    if y + 1 == SIZE {
+        outer |= 1 << (CENTER + SIZE);
+    }
+    let inner = match i {
+        _ if i == CENTER - SIZE => TOP,
+        _ if i == CENTER + SIZE => BOTTOM,
+        _ if i == CENTER - 1 => LEFT,
+        _ if i == CENTER + 1 => RIGHT,
+        _ => 0,
+    };
+    (outer, inner)
+}
+
+fn step_level_bits(outer: Bits, grid: Bits, inner: Bits, rules: &Rules) -> Bits {
+    (0..SIZE * SIZE)
+        .filter(|&i| i != CENTER)
+        .filter(|&i| {
+            let (outer_mask, inner_mask) = adjacent_levels(i);
+            let neighbours = (grid & adjacent(i) & !(1 << CENTER)).count_ones()
+                + (outer & outer_mask).count_ones()
+                + (inner & inner_mask).count_ones();
+            rules.lives(grid & 1 << i != 0, neighbours as usize)
+        })
+        .fold(0, |next, i| next | 1 << i)
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum Tiles {
+    /// The puzzle's 5x5 grids.
+    Bits(Bits),
+    /// Any other size, in reading order.
+    Cells(Vec<bool>),
+}
+
+/// A grid of bugs.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    tiles: Tiles,
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize) -> Self {
+        let tiles = if (width, height) == (SIZE, SIZE) {
+            Tiles::Bits(0)
+        } else {
+            Tiles::Cells(vec![false; width * height])
+        };
+        Grid {
+            width,
+            height,
+            tiles,
+        }
+    }
+
+    fn from_bits(bits: Bits) -> Self {
+        Grid {
+            width: SIZE,
+            height: SIZE,
+            tiles: Tiles::Bits(bits),
+        }
+    }
+
+    fn bits(&self) -> Option<Bits> {
+        match self.tiles {
+            Tiles::Bits(bits) => Some(bits),
+            Tiles::Cells(_) => None,
+        }
+    }
+
+    /// Read a grid of `#` and `.`, as wide as its first line.
+    pub fn parse(s: &str) -> Self {
+        let lines = s
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<_>>();
+        let width = lines.first().map_or(0, |l| l.len());
+        let mut grid = Grid::new(width, lines.len());
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().take(width).enumerate() {
+                grid.set(x, y, c == '#');
+            }
+        }
+        grid
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        let i = y * self.width + x;
+        match &self.tiles {
+            Tiles::Bits(bits) => bits & 1 << i != 0,
+            Tiles::Cells(cells) => cells[i],
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, bug: bool) {
+        assert!(x < self.width && y < self.height, "tile off the grid");
+        let i = y * self.width + x;
+        match &mut self.tiles {
+            Tiles::Bits(bits) if bug => *bits |= 1 << i,
+            Tiles::Bits(bits) => *bits &= !(1 << i),
+            Tiles::Cells(cells) => cells[i] = bug,
+        }
+    }
+
+    pub fn bugs(&self) -> usize {
+        match &self.tiles {
+            Tiles::Bits(bits) => bits.count_ones() as usize,
+            Tiles::Cells(cells) => cells.iter().filter(|&&bug| bug).count(),
+        }
+    }
+
+    /// Each bug scores two to the power of its tile's index, so for grids of
+    /// up to 64 tiles different grids score differently.
+    pub fn biodiversity(&self) -> u64 {
+        match &self.tiles {
+            Tiles::Bits(bits) => u64::from(*bits),
+            Tiles::Cells(cells) => cells
+                .iter()
+                .take(64)
+                .enumerate()
+                .filter(|&(_, &bug)| bug)
+                .map(|(i, _)| 1 << i)
+                .sum(),
+        }
+    }
+
+    fn tiles(&self) -> impl Iterator<Item = (usize, usize)> {
+        let width = self.width;
+        (0..self.width * self.height).map(move |i| (i % width, i / width))
+    }
+
+    /// Tiles next to `(x, y)`, at most four.
+    fn adjacent(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        let (width, height) = (self.width, self.height);
+        [(0, -1), (-1, 0), (1, 0), (0, 1)]
+            .iter()
+            .filter_map(move |&(dx, dy)| {
+                let (x, y) = (x as isize + dx, y as isize + dy);
+                if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
+                    return None;
+                }
+                Some((x as usize, y as usize))
+            })
+    }
+
+    pub fn step(&self, rules: &Rules) -> Self {
+        if let Some(bits) = self.bits() {
+            return Grid::from_bits(step_bits(bits, rules));
+        }
+        let mut next = Grid::new(self.width, self.height);
+        for (x, y) in self.tiles() {
+            let neighbours = self.adjacent(x, y).filter(|&(x, y)| self.get(x, y)).count();
+            next.set(x, y, rules.lives(self.get(x, y), neighbours));
+        }
+        next
+    }
+
+    /// The middle tile, where the next grid in is.
+    fn center(&self) -> (usize, usize) {
+        (self.width / 2, self.height / 2)
+    }
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..self.height {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            for x in 0..self.width {
+                write!(f, "{}", if self.get(x, y) { '#' } else { '.' })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The first layout seen twice.
+pub fn first_repeat(grid: &Grid, rules: &Rules) -> Grid {
+    let mut seen = HashSet::new();
+    let mut grid = grid.clone();
+    while seen.insert(grid.clone()) {
+        grid = grid.step(rules);
+    }
+    grid
+}
+
+/// Grids nested inside each other's middle tile, outermost first.
+///
+/// Every level is the same size, which should be odd both ways to have a
+/// middle tile.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecursiveGrid {
+    levels: VecDeque<Grid>,
+}
+
+impl RecursiveGrid {
+    pub fn new(grid: &Grid) -> Self {
+        let mut grid = grid.clone();
+        let (cx, cy) = grid.center();
+        if grid.get(cx, cy) {
+            grid.set(cx, cy, false);
+        }
+        RecursiveGrid {
+            levels: vec![grid].into_iter().collect(),
+        }
+    }
+
+    /// Bugs next to `(x, y)` on level `l`, counting tiles on the grids
+    /// around and inside it.
+    fn neighbours(&self, l: usize, x: usize, y: usize) -> usize {
+        let grid = &self.levels[l];
+        let (width, height) = (grid.width, grid.height);
+        let (cx, cy) = grid.center();
+        let mut count = grid
+            .adjacent(x, y)
+            .filter(|&(x, y)| (x, y) != (cx, cy) && grid.get(x, y))
+            .count();
+
+        // Tiles around the middle, missing on grids one tile wide or high
+        let left = cx.checked_sub(1).map(|x| (x, cy));
+        let right = Some((cx + 1, cy));
+        let up = cy.checked_sub(1).map(|y| (cx, y));
+        let down = Some((cx, cy + 1));
+
+        if let Some(outer) = l.checked_sub(1).map(|l| &self.levels[l]) {
+            let edges = [
+                (x == 0, left),
+                (x + 1 == width, right),
+                (y == 0, up),
+                (y + 1 == height, down),
+            ];
+            count += edges
+                .iter()
+                .filter(|&&(edge, tile)| edge && tile.is_some_and(|(x, y)| outer.get(x, y)))
+                .count();
+        }
+
+        if let Some(inner) = self.levels.get(l + 1) {
+            let tile = Some((x, y));
+            let side: Vec<(usize, usize)> = match tile {
+                _ if tile == up => (0..width).map(|x| (x, 0)).collect(),
+                _ if tile == down => (0..width).map(|x| (x, height - 1)).collect(),
+                _ if tile == left => (0..height).map(|y| (0, y)).collect(),
+                _ if tile == right => (0..height).map(|y| (width - 1, y)).collect(),
+                _ => Vec::new(),
+            };
+            count += side.into_iter().filter(|&(x, y)| inner.get(x, y)).count();
+        }
+        count
+    }
+
+    pub fn step(&mut self, rules: &Rules) {
+        // Bugs spread at most one level each way a minute
+        let (width, height) = match self.levels.front() {
+            Some(grid) => (grid.width, grid.height),
+            None => return,
+        };
+        self.levels.push_front(Grid::new(width, height));
+        self.levels.push_back(Grid::new(width, height));
+
+        let next = match self.step_bits(rules) {
+            Some(next) => next,
+            None => self.step_cells(rules),
+        };
+        self.levels = next;
+
+        while self.levels.len() > 1 && self.levels.front().is_some_and(|g| g.bugs() == 0) {
+            self.levels.pop_front();
+        }
+        while self.levels.len() > 1 && self.levels.back().is_some_and(|g| g.bugs() == 0) {
+            self.levels.pop_back();
+        }
+    }
+
+    /// The next minute for 5x5 levels, or `None` for other sizes.
+    fn step_bits(&self, rules: &Rules) -> Option<VecDeque<Grid>> {
+        let levels = self
+            .levels
+            .iter()
+            .map(Grid::bits)
+            .collect::<Option<Vec<_>>>()?;
+        let level = |l: usize| levels.get(l).copied().unwrap_or(0);
+        let next = (0..levels.len())
+            .map(|l| {
+                let (outer, grid, inner) = (level(l.wrapping_sub(1)), level(l), level(l + 1));
+                Grid::from_bits(step_level_bits(outer, grid, inner, rules))
+            })
+            .collect();
+        Some(next)
+    }
+
+    fn step_cells(&self, rules: &Rules) -> VecDeque<Grid> {
+        let (width, height) = (self.levels[0].width, self.levels[0].height);
+        (0..self.levels.len())
+            .map(|l| {
+                let grid = &self.levels[l];
+                let center = grid.center();
+                let mut next = Grid::new(width, height);
+                for (x, y) in grid.tiles().filter(|&tile| tile != center) {
+                    next.set(x, y, rules.lives(grid.get(x, y), self.neighbours(l, x, y)));
+                }
+                next
+            })
+            .collect()
+    }
+
+    pub fn levels(&self) -> &VecDeque<Grid> {
+        &self.levels
+    }
+
+    pub fn bugs(&self) -> usize {
+        self.levels.iter().map(Grid::bugs).sum()
+    }
+}
+
+#[aoc_generator(day24)]
+fn load_grid(input: &str) -> Grid {
+    Grid::parse(input)
+}
+
+#[aoc(day24, part1)]
+fn biodiversity(grid: &Grid) -> u64 {
+    first_repeat(grid, &Rules::default()).biodiversity()
+}
+
+#[aoc(day24, part2)]
+fn recursive_bugs(grid: &Grid) -> usize {
+    let rules = Rules::default();
+    let mut grid = RecursiveGrid::new(grid);
+    for _ in 0..MINUTES {
+        grid.step(&rules);
+    }
+    grid.bugs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "....#\n#..#.\n#..##\n..#..\n#....";
+
+    #[test]
+    fn test_step() {
+        let rules = Rules::default();
+        let grid = Grid::parse(EXAMPLE).step(&rules);
+        assert_eq!(grid.to_string(), "#..#.\n####.\n###.#\n##.##\n.##..");
+        let grid = grid.step(&rules);
+        assert_eq!(grid.to_string(), "#####\n....#\n....#\n...#.\n#.###");
+    }
+
+    #[test]
+    fn test_part1() {
+        let grid = first_repeat(&Grid::parse(EXAMPLE), &Rules::default());
+        assert_eq!(grid.to_string(), ".....\n.....\n.....\n#....\n.#...");
+        assert_eq!(grid.biodiversity(), 2_129_920);
+    }
+
+    #[test]
+    fn test_part2() {
+        let rules = Rules::default();
+        let mut grid = RecursiveGrid::new(&Grid::parse(EXAMPLE));
+        for _ in 0..10 {
+            grid.step(&rules);
+        }
+        assert_eq!(grid.bugs(), 99);
+        assert_eq!(grid.levels().len(), 11);
+        assert_eq!(
+            grid.levels()[5].to_string(),
+            ".#...\n.#.##\n.#...\n.....\n....."
+        );
+    }
+
+    #[test]
+    fn test_bitboard() {
+        // 5x5 grids run on bitboards, and agree with the same bugs on a grid
+        // a row taller whose extra row starts empty
+        let rules = Rules::default();
+        let grid = Grid::parse(EXAMPLE);
+        assert_eq!(grid.bits(), Some(grid.biodiversity() as Bits));
+        let mut taller = Grid::new(5, 6);
+        for (x, y) in grid.tiles().filter(|&(x, y)| grid.get(x, y)) {
+            taller.set(x, y, true);
+        }
+        assert_eq!(taller.bits(), None);
+        let (grid, taller) = (grid.step(&rules), taller.step(&rules));
+        assert_eq!(taller.to_string(), format!("{}\n#....", grid));
+    }
+
+    #[test]
+    fn test_thin_grids() {
+        let rules = Rules::default();
+        let mut grid = RecursiveGrid::new(&Grid::parse("#\n.\n#"));
+        grid.step(&rules);
+        let levels = grid
+            .levels()
+            .iter()
+            .map(Grid::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(levels, vec!["#\n.\n#", ".\n.\n.", "#\n.\n#"]);
+
+        let mut grid = RecursiveGrid::new(&Grid::parse("#.#."));
+        for _ in 0..3 {
+            grid.step(&rules);
+        }
+        let levels = grid
+            .levels()
+            .iter()
+            .map(Grid::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(levels, vec![".#.."; 4]);
+
+        let mut grid = RecursiveGrid::new(&Grid::parse(""));
+        grid.step(&rules);
+        assert_eq!(grid.bugs(), 0);
+    }
+
+    #[test]
+    fn test_variants() {
+        // On a 6x3 grid where bugs always die and tiles next to exactly one
+        // are infested, a bug spreads out as a ring
+        let ring = Rules {
+            birth: vec![1],
+            survival: vec![],
+        };
+        let grid = Grid::parse("......\n..#...\n......");
+        assert_eq!((grid.width(), grid.height()), (6, 3));
+        let grid = grid.step(&ring);
+        assert_eq!(grid.to_string(), "..#...\n.#.#..\n..#...");
+        let grid = grid.step(&ring);
+        assert_eq!(grid.to_string(), "......\n#...#.\n......");
+
+        // A lone bug in a 3x3 corner touches two tiles on its own level and
+        // two on the level around it, and doesn't survive
+        let mut grid = RecursiveGrid::new(&Grid::parse("...\n.#.\n#.."));
+        grid.step(&ring);
+        assert_eq!(grid.levels().len(), 2);
+        assert_eq!(grid.levels()[0].to_string(), "...\n#..\n.#.");
+        assert_eq!(grid.levels()[1].to_string(), "...\n#..\n.#.");
+    }
+}
@@ -0,0 +1,375 @@
+use crate::intcode::ascii::AsciiMachine;
+use crate::intcode::{parse_program, IntcodeError};
+use std::collections::HashSet;
+use std::error::Error;
+
+const CHECKPOINT: &str = "Security Checkpoint";
+
+/// Items that end the game or stop the droid moving when taken.
+const DANGEROUS: [&str; 5] = [
+    "escape pod",
+    "giant electromagnet",
+    "infinite loop",
+    "molten lava",
+    "photons",
+];
+
+/// Something that takes a command and replies with text.
+pub trait Console {
+    fn send(&mut self, command: &str) -> Result<String, IntcodeError>;
+}
+
+/// The droid's ASCII interface.
+pub struct Ship {
+    machine: AsciiMachine,
+}
+
+impl Ship {
+    pub fn new(program: &[i64]) -> Self {
+        Ship {
+            machine: AsciiMachine::new(program),
+        }
+    }
+
+    /// Run to the first prompt, returning the description of the first room.
+    pub fn start(&mut self) -> Result<String, IntcodeError> {
+        self.machine.read_screen()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.machine.is_halted()
+    }
+}
+
+impl Console for Ship {
+    fn send(&mut self, command: &str) -> Result<String, IntcodeError> {
+        self.machine.send_line(command);
+        self.machine.read_screen()
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Room {
+    pub name: String,
+    pub doors: Vec<String>,
+    pub items: Vec<String>,
+}
+
+/// The last room described in some output, which is where the droid is.
+pub fn parse_room(text: &str) -> Option<Room> {
+    let start = text.rfind("== ")?;
+    let mut lines = text[start..].lines();
+    let name = lines
+        .next()?
+        .trim_start_matches("== ")
+        .trim_end_matches(" ==")
+        .to_owned();
+
+    let (mut room, mut list) = (Room::default(), None);
+    for line in lines {
+        match line {
+            "Doors here lead:" => list = Some(&mut room.doors),
+            "Items here:" => list = Some(&mut room.items),
+            _ => match (line.strip_prefix("- "), list.as_mut()) {
+                (Some(entry), Some(list)) => list.push(entry.to_owned()),
+                _ => list = None,
+            },
+        }
+    }
+    room.name = name;
+    Some(room)
+}
+
+fn opposite(direction: &str) -> &'static str {
+    match direction {
+        "north" => "south",
+        "south" => "north",
+        "east" => "west",
+        _ => "east",
+    }
+}
+
+/// What exploring the ship found.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Expedition {
+    /// Safe items picked up on the way.
+    pub items: Vec<String>,
+    /// Directions from the first room to the security checkpoint.
+    pub checkpoint: Option<Vec<String>>,
+    /// The direction from the checkpoint to the pressure-sensitive floor.
+    pub floor: Option<String>,
+}
+
+fn visit<C: Console>(
+    console: &mut C,
+    room: &Room,
+    back: Option<&str>,
+    path: &mut Vec<String>,
+    seen: &mut HashSet<String>,
+    expedition: &mut Expedition,
+) -> Result<(), Box<dyn Error>> {
+    seen.insert(room.name.clone());
+    for item in &room.items {
+        if !DANGEROUS.contains(&item.as_str()) {
+            console.send(&format!("take {}", item))?;
+            expedition.items.push(item.clone());
+        }
+    }
+
+    // Going on from the checkpoint weighs the droid, so stop here
+    if room.name == CHECKPOINT {
+        expedition.checkpoint = Some(path.clone());
+        expedition.floor = room
+            .doors
+            .iter()
+            .find(|&d| Some(d.as_str()) != back)
+            .cloned();
+        return Ok(());
+    }
+
+    for door in &room.doors {
+        if Some(door.as_str()) == back {
+            continue;
+        }
+        let reply = console.send(door)?;
+        let next = parse_room(&reply).ok_or_else(|| format!("no room in {:?}", reply))?;
+        if !seen.contains(&next.name) {
+            path.push(door.clone());
+            visit(console, &next, Some(opposite(door)), path, seen, expedition)?;
+            path.pop();
+        }
+        console.send(opposite(door))?;
+    }
+    Ok(())
+}
+
+/// Map every room reachable from the one described by `intro` with a depth
+/// first search, picking up every safe item, and return to the start.
+pub fn explore<C: Console>(console: &mut C, intro: &str) -> Result<Expedition, Box<dyn Error>> {
+    let room = parse_room(intro).ok_or("no starting room")?;
+    let mut expedition = Expedition::default();
+    visit(
+        console,
+        &room,
+        None,
+        &mut Vec::new(),
+        &mut HashSet::new(),
+        &mut expedition,
+    )?;
+    Ok(expedition)
+}
+
+/// Carry every combination of items onto the pressure-sensitive floor until
+/// the droid is let through, and return the password it's given.
+///
+/// Combinations are tried in Gray code order, so each differs from the last
+/// by one item taken or dropped.
+pub fn find_password<C: Console>(
+    console: &mut C,
+    expedition: &Expedition,
+) -> Result<String, Box<dyn Error>> {
+    let path = expedition
+        .checkpoint
+        .as_ref()
+        .ok_or("no security checkpoint")?;
+    let floor = expedition
+        .floor
+        .as_ref()
+        .ok_or("no way past the checkpoint")?;
+    for direction in path {
+        console.send(direction)?;
+    }
+
+    let items = &expedition.items;
+    let mut held = (1u32 << items.len()) - 1;
+    for i in 0..1u32 << items.len() {
+        let wanted = !(i ^ (i >> 1)) & ((1 << items.len()) - 1);
+        for (bit, item) in items.iter().enumerate() {
+            let mask = 1 << bit;
+            if held & mask != wanted & mask {
+                let verb = if wanted & mask != 0 { "take" } else { "drop" };
+                console.send(&format!("{} {}", verb, item))?;
+            }
+        }
+        held = wanted;
+
+        let reply = console.send(floor)?;
+        if !reply.contains("Alert!") {
+            return reply
+                .split_whitespace()
+                .find(|word| word.chars().all(|c| c.is_ascii_digit()))
+                .map(str::to_owned)
+                .ok_or_else(|| format!("no password in {:?}", reply).into());
+        }
+    }
+    Err("no combination of items gets through".into())
+}
+
+#[aoc_generator(day25)]
+fn load_program(input: &str) -> Vec<i64> {
+    parse_program(input).unwrap()
+}
+
+#[aoc(day25, part1)]
+fn password(program: &[i64]) -> Result<String, Box<dyn Error>> {
+    let mut ship = Ship::new(program);
+    let intro = ship.start()?;
+    let expedition = explore(&mut ship, &intro)?;
+    find_password(&mut ship, &expedition)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const INTRO: &str = "\n\n\n== Hull Breach ==\nYou got in through a hole in the floor here.\n\nDoors here lead:\n- north\n- east\n\nItems here:\n- mug\n\nCommand?\n";
+
+    type Doors = Vec<(&'static str, &'static str)>;
+
+    // A small ship in the same text format, with a loop of four rooms, where
+    // the floor wants exactly the mug and the spool
+    struct Mock {
+        rooms: HashMap<&'static str, (Doors, Vec<String>)>,
+        position: &'static str,
+        inventory: Vec<String>,
+    }
+
+    impl Mock {
+        fn new() -> Self {
+            let mut rooms = HashMap::new();
+            rooms.insert(
+                "Hull Breach",
+                (
+                    vec![("north", "Kitchen"), ("east", "Hallway")],
+                    vec!["mug".to_owned()],
+                ),
+            );
+            rooms.insert(
+                "Kitchen",
+                (
+                    vec![("south", "Hull Breach"), ("east", "Pantry")],
+                    vec!["infinite loop".to_owned(), "spool".to_owned()],
+                ),
+            );
+            rooms.insert(
+                "Pantry",
+                (vec![("west", "Kitchen"), ("south", "Hallway")], vec![]),
+            );
+            rooms.insert(
+                "Hallway",
+                (
+                    vec![
+                        ("west", "Hull Breach"),
+                        ("north", "Pantry"),
+                        ("east", CHECKPOINT),
+                    ],
+                    vec!["weight".to_owned()],
+                ),
+            );
+            rooms.insert(
+                CHECKPOINT,
+                (
+                    vec![("west", "Hallway"), ("south", "Pressure-Sensitive Floor")],
+                    vec![],
+                ),
+            );
+            Mock {
+                rooms,
+                position: "Hull Breach",
+                inventory: Vec::new(),
+            }
+        }
+
+        fn describe(&self, name: &str) -> String {
+            let (doors, items) = &self.rooms[name];
+            let mut text = format!("\n\n\n== {} ==\nA room.\n\nDoors here lead:\n", name);
+            for (door, _) in doors {
+                text.push_str(&format!("- {}\n", door));
+            }
+            if !items.is_empty() {
+                text.push_str("\nItems here:\n");
+                for item in items {
+                    text.push_str(&format!("- {}\n", item));
+                }
+            }
+            text.push_str("\nCommand?\n");
+            text
+        }
+    }
+
+    impl Console for Mock {
+        fn send(&mut self, command: &str) -> Result<String, IntcodeError> {
+            let (verb, item) = command.split_at(command.find(' ').unwrap_or(command.len()));
+            let item = item.trim().to_owned();
+            let position = self.position;
+            let room = self.rooms.get_mut(position).unwrap();
+            match verb {
+                "take" => {
+                    assert!(item != "infinite loop");
+                    room.1.retain(|i| *i != item);
+                    self.inventory.push(item.clone());
+                    Ok(format!("\nYou take the {}.\n\nCommand?\n", item))
+                }
+                "drop" => {
+                    self.inventory.retain(|i| *i != item);
+                    room.1.push(item.clone());
+                    Ok(format!("\nYou drop the {}.\n\nCommand?\n", item))
+                }
+                direction => {
+                    let &(_, next) = room.0.iter().find(|(d, _)| *d == direction).unwrap();
+                    if next != "Pressure-Sensitive Floor" {
+                        self.position = next;
+                        return Ok(self.describe(next));
+                    }
+                    let mut held = self.inventory.clone();
+                    held.sort();
+                    if held == ["mug", "spool"] {
+                        Ok(format!("\n\n\n== {} ==\nYou should be able to get in by typing 2424308736 on the keypad at the main airlock.\n", next))
+                    } else {
+                        Ok(format!("\n\n\n== {} ==\nAnalyzing...\n\nDoors here lead:\n- north\n\nA loud, robotic voice says \"Alert! Droids on this ship are lighter than the detected value!\" and you are ejected back to the checkpoint.\n{}", next, self.describe(CHECKPOINT)))
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_room() {
+        assert_eq!(
+            parse_room(INTRO),
+            Some(Room {
+                name: "Hull Breach".to_owned(),
+                doors: vec!["north".to_owned(), "east".to_owned()],
+                items: vec!["mug".to_owned()],
+            })
+        );
+        let ejected = Mock::new().describe(CHECKPOINT);
+        let ejected = format!(
+            "== Pressure-Sensitive Floor ==\nDoors here lead:\n- north\n\nAlert!\n{}",
+            ejected
+        );
+        assert_eq!(parse_room(&ejected).unwrap().name, CHECKPOINT);
+        assert_eq!(parse_room("\nYou take the mug.\n"), None);
+    }
+
+    #[test]
+    fn test_explore() {
+        let mut mock = Mock::new();
+        let expedition = explore(&mut mock, INTRO).unwrap();
+        assert_eq!(expedition.items, vec!["mug", "spool", "weight"]);
+        assert_eq!(
+            expedition.checkpoint,
+            Some(vec![
+                "north".to_owned(),
+                "east".to_owned(),
+                "south".to_owned(),
+                "east".to_owned()
+            ])
+        );
+        assert_eq!(expedition.floor, Some("south".to_owned()));
+        assert_eq!(mock.position, "Hull Breach");
+
+        assert_eq!(find_password(&mut mock, &expedition).unwrap(), "2424308736");
+    }
+}
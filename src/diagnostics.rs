@@ -0,0 +1,115 @@
+//! Diagnostic programs, as run on days 5 and 9.
+//!
+//! A diagnostic program takes a system ID as its only input, outputs the
+//! result of each check it makes, zero for a pass, and finishes by outputting
+//! a diagnostic code.
+
+use crate::intcode::io::IterSource;
+use crate::intcode::{IntcodeError, IntcodeMachine};
+use std::error::Error;
+use std::fmt;
+use std::iter;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DiagnosticError {
+    /// The program finished without any output.
+    NoOutput,
+    /// Checks failed, with every output the program made.
+    Failed(Vec<i64>),
+}
+
+impl Error for DiagnosticError {}
+
+impl fmt::Display for DiagnosticError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticError::NoOutput => write!(f, "diagnostic program made no output"),
+            DiagnosticError::Failed(outputs) => {
+                write!(f, "diagnostic checks failed: {:?}", outputs)
+            }
+        }
+    }
+}
+
+/// Everything a diagnostic program output.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub outputs: Vec<i64>,
+}
+
+impl Diagnostic {
+    /// Checks that didn't pass, as their position in the output and result.
+    pub fn failures(&self) -> Vec<(usize, i64)> {
+        let checks = self.outputs.len().saturating_sub(1);
+        self.outputs[..checks]
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, v)| v != 0)
+            .collect()
+    }
+
+    /// The diagnostic code, if every check passed.
+    pub fn code(&self) -> Result<i64, DiagnosticError> {
+        if !self.failures().is_empty() {
+            return Err(DiagnosticError::Failed(self.outputs.clone()));
+        }
+        self.outputs
+            .last()
+            .copied()
+            .ok_or(DiagnosticError::NoOutput)
+    }
+}
+
+/// Run a diagnostic program for the system with `system_id`.
+pub fn run(program: &[i64], system_id: i64) -> Result<Diagnostic, IntcodeError> {
+    let mut im = IntcodeMachine::new(program, None, None);
+    im.set_input_source(IterSource(iter::once(system_id)));
+    let outputs = im.outputs().collect::<Result<_, _>>()?;
+    Ok(Diagnostic { outputs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::parse_program;
+
+    #[test]
+    fn test_run() {
+        // Day 5 example, output 999, 1000 or 1001 for input below, equal or above 8
+        let program = parse_program(
+            "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99",
+        )
+        .unwrap();
+        for (id, code) in &[(7, 999), (8, 1000), (9, 1001)] {
+            assert_eq!(run(&program, *id).unwrap().code(), Ok(*code));
+        }
+
+        let program = parse_program("3,0,3,0,99").unwrap();
+        assert_eq!(
+            run(&program, 1),
+            Err(IntcodeError::InputExhausted { pc: 2 })
+        );
+    }
+
+    #[test]
+    fn test_code() {
+        let diagnostic = Diagnostic {
+            outputs: vec![0, 0, 0, 42],
+        };
+        assert_eq!(diagnostic.failures(), vec![]);
+        assert_eq!(diagnostic.code(), Ok(42));
+
+        let diagnostic = Diagnostic {
+            outputs: vec![0, 3, 0, 42],
+        };
+        assert_eq!(diagnostic.failures(), vec![(1, 3)]);
+        assert_eq!(
+            diagnostic.code(),
+            Err(DiagnosticError::Failed(vec![0, 3, 0, 42]))
+        );
+
+        let diagnostic = Diagnostic { outputs: vec![] };
+        assert_eq!(diagnostic.code(), Err(DiagnosticError::NoOutput));
+    }
+}
@@ -0,0 +1,225 @@
+//! Points on integer grids and in space.
+
+use num::Signed;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Point2<T> {
+    pub x: T,
+    pub y: T,
+}
+
+impl<T> Point2<T> {
+    pub fn new(x: T, y: T) -> Self {
+        Point2 { x, y }
+    }
+}
+
+impl<T: Signed + Copy + Ord> Point2<T> {
+    pub fn manhattan(self, other: Self) -> T {
+        (self.x - other.x).abs() + (self.y - other.y).abs()
+    }
+
+    /// Moves between the two points when diagonal moves are allowed.
+    pub fn chebyshev(self, other: Self) -> T {
+        (self.x - other.x).abs().max((self.y - other.y).abs())
+    }
+
+    /// A quarter turn counterclockwise about the origin, with y pointing up.
+    pub fn rotate_left(self) -> Self {
+        Point2::new(-self.y, self.x)
+    }
+
+    /// A quarter turn clockwise about the origin, with y pointing up.
+    pub fn rotate_right(self) -> Self {
+        Point2::new(self.y, -self.x)
+    }
+
+    /// The four points one step away, in the order of [`Direction::ALL`].
+    pub fn neighbors(self) -> impl Iterator<Item = Self> {
+        Direction::ALL.iter().map(move |&d| self + d.offset())
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Point3<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+impl<T> Point3<T> {
+    pub fn new(x: T, y: T, z: T) -> Self {
+        Point3 { x, y, z }
+    }
+}
+
+impl<T: Signed + Copy> Point3<T> {
+    pub fn abs(self) -> Self {
+        Point3::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+
+    pub fn signum(self) -> Self {
+        Point3::new(self.x.signum(), self.y.signum(), self.z.signum())
+    }
+
+    /// The distance from the origin moving along axes.
+    pub fn manhattan_length(self) -> T {
+        let Point3 { x, y, z } = self.abs();
+        x + y + z
+    }
+
+    pub fn manhattan(self, other: Self) -> T {
+        (self - other).manhattan_length()
+    }
+}
+
+macro_rules! impl_ops {
+    ($point:ident { $($field:ident),+ }) => {
+        impl<T: Add<Output = T>> Add for $point<T> {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self {
+                $point { $($field: self.$field + rhs.$field),+ }
+            }
+        }
+
+        impl<T: Sub<Output = T>> Sub for $point<T> {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self {
+                $point { $($field: self.$field - rhs.$field),+ }
+            }
+        }
+
+        impl<T: Neg<Output = T>> Neg for $point<T> {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                $point { $($field: -self.$field),+ }
+            }
+        }
+
+        /// Scaling by a constant.
+        impl<T: Mul<Output = T> + Copy> Mul<T> for $point<T> {
+            type Output = Self;
+
+            fn mul(self, rhs: T) -> Self {
+                $point { $($field: self.$field * rhs),+ }
+            }
+        }
+
+        impl<T: AddAssign> AddAssign for $point<T> {
+            fn add_assign(&mut self, rhs: Self) {
+                $(self.$field += rhs.$field;)+
+            }
+        }
+
+        impl<T: SubAssign> SubAssign for $point<T> {
+            fn sub_assign(&mut self, rhs: Self) {
+                $(self.$field -= rhs.$field;)+
+            }
+        }
+    };
+}
+
+impl_ops!(Point2 { x, y });
+impl_ops!(Point3 { x, y, z });
+
+/// A direction on a grid, with up towards increasing y.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    pub fn turn_left(self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    pub fn turn_right(self) -> Self {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        self.turn_left().turn_left()
+    }
+
+    /// One step in this direction.
+    pub fn offset<T: Signed>(self) -> Point2<T> {
+        match self {
+            Direction::Up => Point2::new(T::zero(), T::one()),
+            Direction::Down => Point2::new(T::zero(), -T::one()),
+            Direction::Left => Point2::new(-T::one(), T::zero()),
+            Direction::Right => Point2::new(T::one(), T::zero()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point2() {
+        let (a, b) = (Point2::new(1, -2), Point2::new(4, 2));
+        assert_eq!(a + b, Point2::new(5, 0));
+        assert_eq!(b - a, Point2::new(3, 4));
+        assert_eq!(-a * 2, Point2::new(-2, 4));
+        assert_eq!(a.manhattan(b), 7);
+        assert_eq!(a.chebyshev(b), 4);
+        assert_eq!(b.rotate_left(), Point2::new(-2, 4));
+        assert_eq!(b.rotate_right(), Point2::new(2, -4));
+        assert_eq!(b.rotate_left().rotate_right(), b);
+        assert_eq!(
+            Point2::new(0, 0).neighbors().collect::<Vec<_>>(),
+            vec![
+                Point2::new(0, 1),
+                Point2::new(0, -1),
+                Point2::new(-1, 0),
+                Point2::new(1, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_point3() {
+        let mut a = Point3::new(1, -2, 3);
+        a += Point3::new(1, 1, -5);
+        assert_eq!(a, Point3::new(2, -1, -2));
+        assert_eq!(a.abs(), Point3::new(2, 1, 2));
+        assert_eq!(a.signum(), Point3::new(1, -1, -1));
+        assert_eq!(a.manhattan_length(), 5);
+        assert_eq!(a.manhattan(Point3::default()), 5);
+    }
+
+    #[test]
+    fn test_direction() {
+        for &d in &Direction::ALL {
+            assert_eq!(d.turn_left().turn_right(), d);
+            assert_eq!(d.offset::<i32>().rotate_left(), d.turn_left().offset());
+            assert_eq!(d.offset::<i32>().rotate_right(), d.turn_right().offset());
+            assert_eq!(-d.offset::<i32>(), d.opposite().offset());
+        }
+    }
+}
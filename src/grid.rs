@@ -0,0 +1,195 @@
+//! Rectangular grids stored row by row.
+
+use crate::geometry::Point2;
+use std::error::Error;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+pub type Position = Point2<usize>;
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ParseGridError {
+    pub position: Position,
+    pub c: char,
+}
+
+impl Error for ParseGridError {}
+
+impl fmt::Display for ParseGridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unexpected {:?} at {},{}",
+            self.c, self.position.x, self.position.y
+        )
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> Self {
+        Grid {
+            width,
+            height,
+            cells: vec![fill; width * height],
+        }
+    }
+
+    /// Read a grid a line per row, mapping each character to a cell.
+    ///
+    /// The grid is as wide as its longest line, and shorter lines are padded
+    /// as if they ended in spaces.
+    pub fn parse<F>(s: &str, mut cell: F) -> Result<Self, ParseGridError>
+    where
+        F: FnMut(char) -> Option<T>,
+    {
+        let lines = s.lines().collect::<Vec<_>>();
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        let mut cells = Vec::with_capacity(width * lines.len());
+        for (y, line) in lines.iter().enumerate() {
+            let padding = std::iter::repeat_n(' ', width - line.chars().count());
+            for (x, c) in line.chars().chain(padding).enumerate() {
+                let position = Point2::new(x, y);
+                cells.push(cell(c).ok_or(ParseGridError { position, c })?);
+            }
+        }
+        Ok(Grid {
+            width,
+            height: lines.len(),
+            cells,
+        })
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, position: Position) -> Option<&T> {
+        if position.x >= self.width || position.y >= self.height {
+            return None;
+        }
+        self.cells.get(position.y * self.width + position.x)
+    }
+
+    pub fn get_mut(&mut self, position: Position) -> Option<&mut T> {
+        if position.x >= self.width || position.y >= self.height {
+            return None;
+        }
+        self.cells.get_mut(position.y * self.width + position.x)
+    }
+
+    /// Every cell with its position, in reading order.
+    pub fn iter(&self) -> impl Iterator<Item = (Position, &T)> {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(i, cell)| (Point2::new(i % width, i / width), cell))
+    }
+
+    /// Cells a row at a time, top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    /// Positions up, down, left and right of `position` that are on the grid.
+    pub fn neighbors(&self, position: Position) -> impl Iterator<Item = Position> {
+        let (width, height) = (self.width, self.height);
+        let Point2 { x, y } = position;
+        let candidates = [
+            y.checked_sub(1).map(|y| Point2::new(x, y)),
+            Some(Point2::new(x, y + 1)).filter(|_| y + 1 < height),
+            x.checked_sub(1).map(|x| Point2::new(x, y)),
+            Some(Point2::new(x + 1, y)).filter(|_| x + 1 < width),
+        ];
+        IntoIterator::into_iter(candidates).flatten()
+    }
+}
+
+impl<T> Index<Position> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, position: Position) -> &T {
+        self.get(position).expect("position off the grid")
+    }
+}
+
+impl<T> IndexMut<Position> for Grid<T> {
+    fn index_mut(&mut self, position: Position) -> &mut T {
+        self.get_mut(position).expect("position off the grid")
+    }
+}
+
+/// A line per row, each cell written with its own `Display`.
+impl<T: fmt::Display> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.rows() {
+            for cell in row {
+                write!(f, "{}", cell)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let grid = Grid::parse("#.#\n.\n", |c| match c {
+            '#' => Some('#'),
+            '.' | ' ' => Some('.'),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid[Point2::new(2, 0)], '#');
+        assert_eq!(grid.get(Point2::new(3, 0)), None);
+        assert_eq!(grid.to_string(), "#.#\n...\n");
+        assert_eq!(
+            grid.rows().collect::<Vec<_>>(),
+            vec![&['#', '.', '#'][..], &['.', '.', '.'][..]]
+        );
+
+        assert_eq!(
+            Grid::parse("..\n.x", |c| Some(c).filter(|&c| c == '.')),
+            Err(ParseGridError {
+                position: Point2::new(1, 1),
+                c: 'x'
+            })
+        );
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let mut grid = Grid::new(3, 2, 0);
+        grid[Point2::new(1, 1)] = 5;
+        assert_eq!(
+            grid.iter()
+                .filter(|&(_, &v)| v == 5)
+                .map(|(p, _)| p)
+                .collect::<Vec<_>>(),
+            vec![Point2::new(1, 1)]
+        );
+        assert_eq!(
+            grid.neighbors(Point2::new(0, 0)).collect::<Vec<_>>(),
+            vec![Point2::new(0, 1), Point2::new(1, 0)]
+        );
+        assert_eq!(grid.neighbors(Point2::new(1, 1)).count(), 3);
+    }
+}
@@ -1,82 +1,116 @@
+//! An Intcode virtual machine, as built up over days 2, 5 and 9.
+//!
+//! Programs are loaded into an [`IntcodeMachine`], which reads input from and
+//! writes output to optional `std::sync::mpsc` channels. A machine can be run
+//! to completion with [`IntcodeMachine::run`], or driven one instruction at a
+//! time with [`IntcodeMachine::step`]:
+//!
+//! ```
+//! use advent_of_code_2019::intcode::{parse_program, IntcodeMachine};
+//! use std::sync::mpsc::channel;
+//!
+//! let program = parse_program("3,0,1002,0,2,0,4,0,99").unwrap();
+//! let (tx_input, rx_input) = channel();
+//! let (tx_output, rx_output) = channel();
+//!
+//! let mut im = IntcodeMachine::new(&program, Some(rx_input), Some(tx_output));
+//! tx_input.send(21).unwrap();
+//! im.run().unwrap();
+//!
+//! assert_eq!(rx_output.recv(), Ok(42));
+//! ```
+
+use ansi_term::Color;
+use itertools::Itertools;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
 use std::error;
 use std::fmt;
 use std::fmt::Debug;
-use std::sync::mpsc::{Receiver, RecvError, SendError, Sender};
-
-// Total size 4096 * 8 = 32,768
-const MEMORY: usize = 4096;
-
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub mod analysis;
+pub mod arena;
+pub mod ascii;
+pub mod asm;
+pub mod checkpoint;
+pub mod conformance;
+pub mod debugger;
+pub mod diff;
+pub mod io;
+pub mod pipeline;
+pub mod sandbox;
+pub mod snapshot;
+pub mod trace;
+
+use checkpoint::Checkpoints;
+use io::{FnSink, Input, InputSource, IterSource, OutputSink};
+use snapshot::Snapshot;
+use trace::Trace;
+
+/// Parse the comma separated program on the first line of `s`.
 pub fn parse_program(s: &str) -> Option<Vec<i64>> {
     s.lines()
         .map(|s| s.split(',').filter_map(|s| s.parse().ok()).collect())
         .next()
 }
 
-pub fn print_instruction(im: &IntcodeMachine, pc: usize, instruction: &Instruction) -> String {
-    let offset = match instruction {
-        Instruction::Add(_, _, _) => 4,
-        Instruction::Multiply(_, _, _) => 4,
-        Instruction::Input(_) => 2,
-        Instruction::Output(_) => 2,
-        Instruction::JumpIfTrue(_, _) => 3,
-        Instruction::JumpIfFalse(_, _) => 3,
-        Instruction::LessThan(_, _, _) => 4,
-        Instruction::Equals(_, _, _) => 4,
-        Instruction::RelativeBase(_) => 2,
-        Instruction::Exit => 1,
-    };
-
-    let instruction = format!("{:?}", instruction);
-    let bytes = format!("{:>5?}", &im.mem[pc..pc + offset]);
-
-    format!("{:>5}: {:26} {}", pc, instruction, bytes)
-}
-
-type DebugHook = fn(&mut IntcodeMachine, usize, Instruction) -> Instruction;
-
-#[derive(Debug)]
-enum Error<T> {
-    Recv(RecvError),
-    Send(SendError<T>),
+/// Called before each instruction executes with the machine, the address of
+/// the instruction and its decoded form, returning the instruction to execute.
+pub type DebugHook = fn(&mut IntcodeMachine, usize, Instruction) -> Instruction;
+
+/// A fault that stops a machine, each recording the address of the
+/// instruction that caused it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum IntcodeError {
+    /// The instruction has an unknown opcode.
+    InvalidOpcode { pc: usize, value: i64 },
+    /// The instruction has an unknown parameter mode.
+    InvalidMode { pc: usize, value: i64 },
+    /// The instruction reads, writes or jumps to a negative address.
+    OutOfBounds { pc: usize, address: i64 },
+    /// The instruction reads input, but the input channel is disconnected.
+    InputExhausted { pc: usize },
+    /// The instruction waited longer than the machine's input timeout.
+    InputTimeout { pc: usize },
+    /// The instruction outputs `value`, but the output channel is disconnected.
+    OutputClosed { pc: usize, value: i64 },
+    /// The instruction writes past the machine's memory limit.
+    MemoryLimit { pc: usize, address: i64 },
+    /// The instruction's arithmetic overflows an `i64`.
+    Overflow { pc: usize },
 }
 
-impl<T: fmt::Debug + Send> error::Error for Error<T> {
-    fn description(&self) -> &str {
-        match *self {
-            Error::Recv(ref inner) => inner.description(),
-            Error::Send(ref inner) => inner.description(),
-        }
-    }
-
-    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        match *self {
-            Error::Recv(ref inner) => inner.source(),
-            Error::Send(ref inner) => inner.source(),
-        }
-    }
-}
+impl error::Error for IntcodeError {}
 
-impl<T> fmt::Display for Error<T> {
+impl fmt::Display for IntcodeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
-            Error::Recv(ref inner) => fmt::Display::fmt(inner, f),
-            Error::Send(ref inner) => fmt::Display::fmt(inner, f),
+            IntcodeError::InvalidOpcode { pc, value } => {
+                write!(f, "invalid opcode in {} at {}", value, pc)
+            }
+            IntcodeError::InvalidMode { pc, value } => {
+                write!(f, "invalid parameter mode in {} at {}", value, pc)
+            }
+            IntcodeError::OutOfBounds { pc, address } => {
+                write!(f, "address {} out of bounds at {}", address, pc)
+            }
+            IntcodeError::InputExhausted { pc } => write!(f, "input exhausted at {}", pc),
+            IntcodeError::InputTimeout { pc } => write!(f, "input timed out at {}", pc),
+            IntcodeError::OutputClosed { pc, value } => {
+                write!(f, "output closed at {}, dropping {}", pc, value)
+            }
+            IntcodeError::MemoryLimit { pc, address } => {
+                write!(f, "address {} past memory limit at {}", address, pc)
+            }
+            IntcodeError::Overflow { pc } => write!(f, "arithmetic overflow at {}", pc),
         }
     }
 }
 
-impl<T> From<RecvError> for Error<T> {
-    fn from(recv_error: RecvError) -> Self {
-        Error::Recv(recv_error)
-    }
-}
-
-impl<T> From<SendError<T>> for Error<T> {
-    fn from(send_error: SendError<T>) -> Self {
-        Error::Send(send_error)
-    }
-}
-
+#[derive(Copy, Clone)]
 enum Mode {
     Position,
     Immediate,
@@ -88,18 +122,20 @@ enum Perm {
     Write,
 }
 
-impl From<i64> for Mode {
-    fn from(mode: i64) -> Self {
+impl TryFrom<i64> for Mode {
+    type Error = i64;
+
+    fn try_from(mode: i64) -> Result<Self, Self::Error> {
         match mode {
-            0 => Mode::Position,
-            1 => Mode::Immediate,
-            2 => Mode::Relative,
-            _ => unreachable!(),
+            0 => Ok(Mode::Position),
+            1 => Ok(Mode::Immediate),
+            2 => Ok(Mode::Relative),
+            _ => Err(mode),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Instruction {
     Add(i64, i64, i64),
     Multiply(i64, i64, i64),
@@ -113,11 +149,185 @@ pub enum Instruction {
     Exit,
 }
 
-impl From<&mut IntcodeMachine> for Instruction {
-    fn from(machine: &mut IntcodeMachine) -> Self {
+impl Instruction {
+    /// Number of memory words the instruction occupies, opcode included.
+    pub fn size(&self) -> usize {
+        match self {
+            Instruction::Add(_, _, _) => 4,
+            Instruction::Multiply(_, _, _) => 4,
+            Instruction::Input(_) => 2,
+            Instruction::Output(_) => 2,
+            Instruction::JumpIfTrue(_, _) => 3,
+            Instruction::JumpIfFalse(_, _) => 3,
+            Instruction::LessThan(_, _, _) => 4,
+            Instruction::Equals(_, _, _) => 4,
+            Instruction::RelativeBase(_) => 2,
+            Instruction::Exit => 1,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Add(_, _, _) => "add",
+            Instruction::Multiply(_, _, _) => "mul",
+            Instruction::Input(_) => "in",
+            Instruction::Output(_) => "out",
+            Instruction::JumpIfTrue(_, _) => "jnz",
+            Instruction::JumpIfFalse(_, _) => "jz",
+            Instruction::LessThan(_, _, _) => "lt",
+            Instruction::Equals(_, _, _) => "eq",
+            Instruction::RelativeBase(_) => "arb",
+            Instruction::Exit => "hlt",
+        }
+    }
+
+    /// Decoded operands, with each one flagged if it is a write address.
+    fn operands(&self) -> Vec<(i64, Perm)> {
+        use Perm::{Read, Write};
+
+        match *self {
+            Instruction::Add(r1, r2, r3)
+            | Instruction::Multiply(r1, r2, r3)
+            | Instruction::LessThan(r1, r2, r3)
+            | Instruction::Equals(r1, r2, r3) => vec![(r1, Read), (r2, Read), (r3, Write)],
+            Instruction::Input(r1) => vec![(r1, Write)],
+            Instruction::Output(r1) | Instruction::RelativeBase(r1) => vec![(r1, Read)],
+            Instruction::JumpIfTrue(r1, r2) | Instruction::JumpIfFalse(r1, r2) => {
+                vec![(r1, Read), (r2, Read)]
+            }
+            Instruction::Exit => vec![],
+        }
+    }
+}
+
+/// Shows the mnemonic with the decoded operands, read operands as the values
+/// they resolved to and write operands as addresses. Width and alignment
+/// flags are honoured.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operands = self
+            .operands()
+            .iter()
+            .map(|(v, _)| v.to_string())
+            .join(", ");
+        if operands.is_empty() {
+            f.pad(self.mnemonic())
+        } else {
+            f.pad(&format!("{} {}", self.mnemonic(), operands))
+        }
+    }
+}
+
+/// Formats one decoded instruction as a listing line for traces and debugging.
+///
+/// Lines show the address, the instruction and its raw memory words. Operands
+/// can instead be shown as written with their parameter modes (`[12]` for
+/// position, `12` for immediate, `[rb+12]` for relative), optionally with the
+/// value each one resolved to, and the line can be highlighted with ANSI colors.
+pub struct InstructionFormat<'a> {
+    machine: &'a IntcodeMachine,
+    pc: usize,
+    instruction: &'a Instruction,
+    width: usize,
+    modes: bool,
+    values: bool,
+    color: bool,
+}
+
+impl<'a> InstructionFormat<'a> {
+    /// Pad the instruction column to `width` characters.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Show operands as written in memory, annotated with their parameter modes.
+    pub fn modes(mut self, modes: bool) -> Self {
+        self.modes = modes;
+        self
+    }
+
+    /// With `modes`, also show what each operand resolved to.
+    pub fn values(mut self, values: bool) -> Self {
+        self.values = values;
+        self
+    }
+
+    /// Highlight the mnemonic and operands with ANSI colors.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    fn operand(&self, i: usize, raw: i64, (value, perm): (i64, Perm)) -> (String, Option<Color>) {
+        // The instruction has been decoded, so its modes are valid
+        let mode = Mode::try_from(self.machine.load(self.pc) / 10i64.pow(i as u32 + 2) % 10)
+            .unwrap_or(Mode::Position);
+        let (text, color) = if self.modes {
+            match mode {
+                Mode::Position => (format!("[{}]", raw), Color::Yellow),
+                Mode::Immediate => (raw.to_string(), Color::Green),
+                Mode::Relative if raw < 0 => (format!("[rb{}]", raw), Color::Purple),
+                Mode::Relative => (format!("[rb+{}]", raw), Color::Purple),
+            }
+        } else {
+            (value.to_string(), Color::Green)
+        };
+
+        let text = match (self.modes && self.values, perm, mode) {
+            (true, Perm::Read, Mode::Position) | (true, Perm::Read, Mode::Relative) => {
+                format!("{}={}", text, value)
+            }
+            (true, Perm::Write, Mode::Relative) => format!("{}@{}", text, value),
+            _ => text,
+        };
+        (text, Some(color))
+    }
+}
+
+impl<'a> fmt::Display for InstructionFormat<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = self.instruction.size();
+        let raw = (self.pc..self.pc + size)
+            .map(|address| self.machine.load(address))
+            .collect_vec();
+
+        let mnemonic = (self.instruction.mnemonic().to_owned(), Some(Color::Cyan));
+        let operands = self
+            .instruction
+            .operands()
+            .into_iter()
+            .enumerate()
+            .map(|(i, operand)| self.operand(i, raw[i + 1], operand))
+            .collect_vec();
+
+        let paint = |(text, color): &(String, Option<Color>)| match color {
+            Some(color) if self.color => color.paint(text.as_str()).to_string(),
+            _ => text.to_owned(),
+        };
+        let (mut text, mut plain_width) = (paint(&mnemonic), mnemonic.0.chars().count());
+        for (i, operand) in operands.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            text.push_str(separator);
+            text.push_str(&paint(operand));
+            plain_width += separator.len() + operand.0.chars().count();
+        }
+
+        // Pad on the plain text, escape codes take no space on screen
+        let padding = " ".repeat(self.width.saturating_sub(plain_width));
+
+        write!(f, "{:>5}: {}{} {:>5?}", self.pc, text, padding, raw)
+    }
+}
+
+impl TryFrom<&mut IntcodeMachine> for Instruction {
+    type Error = IntcodeError;
+
+    fn try_from(machine: &mut IntcodeMachine) -> Result<Self, Self::Error> {
         use Mode::{Immediate, Position, Relative};
         use Perm::{Read, Write};
 
+        let pc = machine.pc;
         let instruction = machine.next();
 
         let opcode = instruction % 100;
@@ -125,89 +335,432 @@ impl From<&mut IntcodeMachine> for Instruction {
 
         let mut next = |perm| {
             let v = machine.next();
-            let m = mode % 10;
+            let m = Mode::try_from(mode % 10).map_err(|_| IntcodeError::InvalidMode {
+                pc,
+                value: instruction,
+            })?;
             mode /= 10;
 
-            match (m.into(), perm) {
-                (Position, Read) => machine.load(v as usize),
-                (Relative, Read) => machine.load((machine.relative_base + v) as usize),
-                (Immediate, _) | (Position, Write) => v,
-                (Relative, Write) => machine.relative_base + v,
+            let address = match (m, &perm) {
+                (Immediate, Read) => return Ok(v),
+                // Writes need somewhere to go
+                (Immediate, Write) => {
+                    return Err(IntcodeError::InvalidMode {
+                        pc,
+                        value: instruction,
+                    })
+                }
+                (Position, _) => v,
+                (Relative, _) => machine
+                    .relative_base
+                    .checked_add(v)
+                    .ok_or(IntcodeError::Overflow { pc })?,
+            };
+            if address < 0 {
+                return Err(IntcodeError::OutOfBounds { pc, address });
+            }
+            match perm {
+                Read => Ok(machine.load(address as usize)),
+                Write => Ok(address),
             }
         };
 
-        match opcode {
-            1 => Instruction::Add(next(Read), next(Read), next(Write)),
-            2 => Instruction::Multiply(next(Read), next(Read), next(Write)),
-            3 => Instruction::Input(next(Write)),
-            4 => Instruction::Output(next(Read)),
-            5 => Instruction::JumpIfTrue(next(Read), next(Read)),
-            6 => Instruction::JumpIfFalse(next(Read), next(Read)),
-            7 => Instruction::LessThan(next(Read), next(Read), next(Write)),
-            8 => Instruction::Equals(next(Read), next(Read), next(Write)),
-            9 => Instruction::RelativeBase(next(Read)),
+        Ok(match opcode {
+            1 => Instruction::Add(next(Read)?, next(Read)?, next(Write)?),
+            2 => Instruction::Multiply(next(Read)?, next(Read)?, next(Write)?),
+            3 => Instruction::Input(next(Write)?),
+            4 => Instruction::Output(next(Read)?),
+            5 => Instruction::JumpIfTrue(next(Read)?, next(Read)?),
+            6 => Instruction::JumpIfFalse(next(Read)?, next(Read)?),
+            7 => Instruction::LessThan(next(Read)?, next(Read)?, next(Write)?),
+            8 => Instruction::Equals(next(Read)?, next(Read)?, next(Write)?),
+            9 => Instruction::RelativeBase(next(Read)?),
             99 => Instruction::Exit,
-            _ => unreachable!(),
-        }
+            _ => {
+                return Err(IntcodeError::InvalidOpcode {
+                    pc,
+                    value: instruction,
+                })
+            }
+        })
+    }
+}
+
+/// What happened when the machine executed an instruction.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum State {
+    /// An instruction without I/O was executed.
+    Running,
+    /// The next instruction reads input, but none is available. The
+    /// instruction is retried on the next step.
+    NeedsInput,
+    /// A value was output.
+    Output(i64),
+    /// The program has exited.
+    Halted,
+}
+
+/// Where [`IntcodeMachine::run_for`] stopped.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RunResult {
+    Halted,
+    /// The next instruction reads input, but none is available.
+    NeedsInput,
+    /// The budget ran out with the program still running.
+    BudgetExhausted,
+}
+
+/// Where [`IntcodeMachine::run_until`] should stop.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// Stop after the next value is output.
+    Output,
+    /// Keep going until the machine needs input, queueing any output.
+    Input,
+}
+
+/// Resources a machine has used, see [`IntcodeMachine::stats`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Stats {
+    /// Instructions executed.
+    pub steps: u64,
+    /// Words of memory, the program and anything written past its end.
+    pub memory: usize,
+    /// Values read by input instructions.
+    pub inputs: u64,
+    /// Values written by output instructions.
+    pub outputs: u64,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} steps, {} words of memory, {} inputs, {} outputs",
+            self.steps, self.memory, self.inputs, self.outputs
+        )
     }
 }
 
+/// Words of memory a machine may grow to without a limit of its own, so a
+/// single write to a far address faults instead of exhausting memory.
+pub const MEMORY_CEILING: usize = 1 << 24;
+
 pub struct IntcodeMachine {
     pc: usize,
-    pub mem: [i64; MEMORY],
+    pub mem: Vec<i64>,
+    /// The program as loaded, which snapshots record changes against.
+    image: Arc<Vec<i64>>,
     relative_base: i64,
-    input: Option<Receiver<i64>>,
-    output: Option<Sender<i64>>,
+    input: Option<Box<dyn InputSource + Send>>,
+    output: Option<Box<dyn OutputSink + Send>>,
+    input_queue: VecDeque<i64>,
+    output_queue: VecDeque<i64>,
     debug: Option<DebugHook>,
+    trace: Option<Trace>,
+    checkpoints: Option<Checkpoints>,
+    steps: u64,
+    idle_input: Option<i64>,
+    idle_reads: u64,
+    inputs: u64,
+    outputs: u64,
+    memory_limit: Option<usize>,
+    input_timeout: Option<Duration>,
     halted: bool,
 }
 
 impl IntcodeMachine {
+    /// Load `program` into a fresh machine.
+    ///
+    /// Memory starts out as a copy of the program and grows as the program
+    /// writes past its end.
+    ///
+    /// Input instructions read values queued with [`push_input`] first, and
+    /// then block on `input`. Output instructions send to `output`. Without
+    /// channels the machine never blocks, and [`step`] and [`run_until`]
+    /// report when input is needed or output is produced instead.
+    ///
+    /// [`push_input`]: IntcodeMachine::push_input
+    /// [`step`]: IntcodeMachine::step
+    /// [`run_until`]: IntcodeMachine::run_until
     pub fn new(program: &[i64], input: Option<Receiver<i64>>, output: Option<Sender<i64>>) -> Self {
-        // Initialize system memory
-        let mut mem = [0; MEMORY];
-
-        // Load the program into memory
-        mem[..program.len()].copy_from_slice(program);
-
-        IntcodeMachine {
+        let mut im = IntcodeMachine {
             pc: 0,
-            mem,
+            mem: program.to_owned(),
+            image: Arc::new(program.to_owned()),
             relative_base: 0,
-            input,
-            output,
+            input: None,
+            output: None,
+            input_queue: VecDeque::new(),
+            output_queue: VecDeque::new(),
             debug: None,
+            trace: None,
+            checkpoints: None,
+            steps: 0,
+            idle_input: None,
+            idle_reads: 0,
+            inputs: 0,
+            outputs: 0,
+            memory_limit: None,
+            input_timeout: None,
             halted: false,
+        };
+        if let Some(input) = input {
+            im.set_input_source(input);
+        }
+        if let Some(output) = output {
+            im.set_output_sink(output);
+        }
+        im
+    }
+
+    /// Load `program` into a machine that reads input from `input` until it
+    /// runs out, and passes each output to `output`.
+    ///
+    /// ```
+    /// use advent_of_code_2019::intcode::{parse_program, IntcodeMachine};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let program = parse_program("3,0,1002,0,2,0,4,0,99").unwrap();
+    /// let outputs = Arc::new(Mutex::new(Vec::new()));
+    /// let sink = Arc::clone(&outputs);
+    ///
+    /// let mut im = IntcodeMachine::with_io(&program, vec![21], move |v| {
+    ///     sink.lock().unwrap().push(v)
+    /// });
+    /// im.run().unwrap();
+    ///
+    /// assert_eq!(*outputs.lock().unwrap(), vec![42]);
+    /// ```
+    pub fn with_io<I, F>(program: &[i64], input: I, output: F) -> Self
+    where
+        I: IntoIterator<Item = i64>,
+        I::IntoIter: Send + 'static,
+        F: FnMut(i64) + Send + 'static,
+    {
+        let mut im = IntcodeMachine::new(program, None, None);
+        im.set_input_source(IterSource(input.into_iter()));
+        im.set_output_sink(FnSink(output));
+        im
+    }
+
+    /// Run a fresh copy of `program` on `inputs` and return its first output,
+    /// or `None` if it halts without one.
+    ///
+    /// For programs that answer a single query per run, without setting up
+    /// channels or keeping the machine around.
+    pub fn evaluate(program: &[i64], inputs: &[i64]) -> Result<Option<i64>, IntcodeError> {
+        let mut im = IntcodeMachine::new(program, None, None);
+        im.input_queue.extend(inputs);
+        match im.run_until(Event::Output)? {
+            State::Output(v) => Ok(Some(v)),
+            State::NeedsInput => Err(IntcodeError::InputExhausted { pc: im.pc }),
+            _ => Ok(None),
         }
     }
 
+    /// Attach the channel input instructions read from.
+    pub fn set_input(&mut self, input: Receiver<i64>) {
+        self.set_input_source(input);
+    }
+
+    /// Attach the channel output instructions send to.
+    pub fn set_output(&mut self, output: Sender<i64>) {
+        self.set_output_sink(output);
+    }
+
+    /// Read input from `source` once queued input runs out.
+    pub fn set_input_source<S: InputSource + Send + 'static>(&mut self, source: S) {
+        self.input = Some(Box::new(source));
+    }
+
+    /// Write output to `sink` as well as reporting it from [`step`].
+    ///
+    /// [`step`]: IntcodeMachine::step
+    pub fn set_output_sink<S: OutputSink + Send + 'static>(&mut self, sink: S) {
+        self.output = Some(Box::new(sink));
+    }
+
+    /// Iterate over the machine's output, running it as far as the next value
+    /// each time. Ends when it halts, needs input, or faults, yielding the
+    /// fault last.
+    pub fn outputs(&mut self) -> Outputs<'_> {
+        Outputs {
+            machine: self,
+            done: false,
+        }
+    }
+
+    /// Read `v` whenever input is needed and none is waiting, instead of
+    /// blocking or reporting [`State::NeedsInput`]. For programs that poll.
+    pub fn set_idle_input(&mut self, v: Option<i64>) {
+        self.idle_input = v;
+    }
+
+    /// Number of times the idle input has been read.
+    pub fn idle_reads(&self) -> u64 {
+        self.idle_reads
+    }
+
+    /// Fault instead of writing at or past `limit`, so memory never grows
+    /// beyond that many words. Without a limit, writes fault at
+    /// [`MEMORY_CEILING`].
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.memory_limit = limit;
+    }
+
+    /// Fault with [`IntcodeError::InputTimeout`] instead of waiting longer
+    /// than `timeout` for input from a channel, such as when machines wired
+    /// together are all waiting on each other.
+    pub fn set_input_timeout(&mut self, timeout: Option<Duration>) {
+        self.input_timeout = timeout;
+    }
+
+    /// Queue a value for the next input instruction.
+    pub fn push_input(&mut self, v: i64) {
+        self.input_queue.push_back(v);
+    }
+
+    /// Take the oldest output queued by [`run_until`](IntcodeMachine::run_until).
+    pub fn pop_output(&mut self) -> Option<i64> {
+        self.output_queue.pop_front()
+    }
+
+    /// Take all output queued by [`run_until`](IntcodeMachine::run_until).
+    pub fn drain_output(&mut self) -> Vec<i64> {
+        self.output_queue.drain(..).collect()
+    }
+
+    /// Read the value at a memory address, memory that has never been
+    /// written reads as zero.
     pub fn load(&self, address: usize) -> i64 {
-        self.mem[address]
+        self.mem.get(address).copied().unwrap_or(0)
     }
 
+    /// Write a value to a memory address, growing memory to fit it.
     pub fn store(&mut self, address: usize, v: i64) {
+        if address >= self.mem.len() {
+            self.mem.resize(address + 1, 0);
+        }
         self.mem[address] = v;
     }
 
-    /// Run the intcode machine until it becomes halted.
-    pub fn run(&mut self) {
-        while !self.halted {
-            let result = self.tick();
+    /// Address of the next instruction to execute.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Current base for relative mode parameters.
+    pub fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    /// Number of instructions executed so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// What the run so far has used.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            steps: self.steps,
+            memory: self.mem.len(),
+            inputs: self.inputs,
+            outputs: self.outputs,
+        }
+    }
 
-            if result.is_err() {
-                break;
+    /// Whether the program has executed its exit instruction.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Execute a single instruction and report what happened.
+    ///
+    /// On a fault the program counter is left on the faulting instruction.
+    pub fn step(&mut self) -> Result<State, IntcodeError> {
+        if self.halted {
+            return Ok(State::Halted);
+        }
+        self.tick()
+    }
+
+    /// Step the machine until `event` happens, it needs input, or it halts.
+    ///
+    /// Output passed over on the way is queued, see [`pop_output`] and
+    /// [`drain_output`].
+    ///
+    /// [`pop_output`]: IntcodeMachine::pop_output
+    /// [`drain_output`]: IntcodeMachine::drain_output
+    pub fn run_until(&mut self, event: Event) -> Result<State, IntcodeError> {
+        loop {
+            match self.step()? {
+                State::Running => {}
+                State::Output(v) if event != Event::Output => self.output_queue.push_back(v),
+                state => return Ok(state),
             }
         }
+    }
+
+    /// Execute at most `max_instructions` instructions, queueing any output
+    /// like [`run_until`](IntcodeMachine::run_until), and stopping early if
+    /// the machine halts or needs input.
+    pub fn run_for(&mut self, max_instructions: u64) -> Result<RunResult, IntcodeError> {
+        for _ in 0..max_instructions {
+            match self.step()? {
+                State::Running => {}
+                State::Output(v) => self.output_queue.push_back(v),
+                State::NeedsInput => return Ok(RunResult::NeedsInput),
+                State::Halted => return Ok(RunResult::Halted),
+            }
+        }
+        Ok(if self.halted {
+            RunResult::Halted
+        } else {
+            RunResult::BudgetExhausted
+        })
+    }
+
+    /// Run the intcode machine until it becomes halted.
+    ///
+    /// Execution stops early on a fault, or when input runs out. Both channels
+    /// are dropped afterwards, so anything waiting on the machine is released.
+    pub fn run(&mut self) -> Result<(), IntcodeError> {
+        let result = self.run_to_halt();
 
         // Drop input and output channels
-        if let Some(rx_input) = self.input.take() {
-            drop(rx_input);
+        self.input = None;
+        self.output = None;
+        result
+    }
+
+    fn run_to_halt(&mut self) -> Result<(), IntcodeError> {
+        while !self.halted {
+            if self.tick()? == State::NeedsInput {
+                return Err(IntcodeError::InputExhausted { pc: self.pc });
+            }
         }
-        if let Some(tx_output) = self.output.take() {
-            drop(tx_output);
+        Ok(())
+    }
+
+    /// Format a decoded instruction found at `pc`, typically from a debug hook.
+    pub fn format_instruction<'a>(
+        &'a self,
+        pc: usize,
+        instruction: &'a Instruction,
+    ) -> InstructionFormat<'a> {
+        InstructionFormat {
+            machine: self,
+            pc,
+            instruction,
+            width: 26,
+            modes: false,
+            values: false,
+            color: false,
         }
     }
 
+    /// Install a hook that sees, and may replace, every instruction before it executes.
     pub fn set_debug(&mut self, hook: DebugHook) {
         self.debug = Some(hook)
     }
@@ -216,44 +769,232 @@ impl IntcodeMachine {
         self.debug = None
     }
 
+    /// Start recording every instruction executed from now on.
+    pub fn start_trace(&mut self) {
+        self.trace = Some(Trace::default());
+    }
+
+    /// Dump `len` words from `start` into the trace whenever an instruction
+    /// writes inside them, starting a trace if there isn't one.
+    pub fn watch_region(&mut self, start: usize, len: usize) {
+        self.trace
+            .get_or_insert_with(Trace::default)
+            .watch_region(start, len);
+    }
+
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    /// Stop recording and take the trace so far.
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
+    /// Put the machine back as it was when the program was loaded, reusing
+    /// its memory and queues. Channels, hooks and limits are kept, while any
+    /// trace or checkpoints are dropped.
+    pub fn reset(&mut self) {
+        self.mem.clear();
+        self.mem.extend_from_slice(&self.image);
+        self.pc = 0;
+        self.relative_base = 0;
+        self.input_queue.clear();
+        self.output_queue.clear();
+        self.trace = None;
+        self.checkpoints = None;
+        self.steps = 0;
+        self.idle_reads = 0;
+        self.inputs = 0;
+        self.outputs = 0;
+        self.halted = false;
+    }
+
+    /// Like [`reset`](IntcodeMachine::reset), also dropping channels, hooks
+    /// and limits, leaving the machine as [`new`](IntcodeMachine::new) made it
+    /// without channels.
+    pub fn reset_all(&mut self) {
+        self.reset();
+        self.input = None;
+        self.output = None;
+        self.debug = None;
+        self.idle_input = None;
+        self.memory_limit = None;
+        self.input_timeout = None;
+    }
+
+    /// Save the machine state, to go back to with [`restore`].
+    ///
+    /// [`restore`]: IntcodeMachine::restore
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::take(self)
+    }
+
+    /// Put the machine back in a saved state, which may have been taken from
+    /// another machine. Channels, hooks and limits are kept.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        snapshot.restore(self);
+    }
+
+    /// Save the machine state now and every `interval` instructions from
+    /// here on, keeping the latest `capacity` checkpoints.
+    pub fn start_checkpoints(&mut self, interval: u64, capacity: usize) {
+        let mut checkpoints = Checkpoints::new(interval, capacity);
+        checkpoints.save(self);
+        self.checkpoints = Some(checkpoints);
+    }
+
+    pub fn checkpoints(&self) -> Option<&Checkpoints> {
+        self.checkpoints.as_ref()
+    }
+
+    /// Go back to the latest checkpoint at or before `steps` instructions,
+    /// returning the steps it was taken at. Later checkpoints are dropped.
+    pub fn restore_checkpoint(&mut self, steps: u64) -> Option<u64> {
+        let mut checkpoints = self.checkpoints.take()?;
+        let restored = checkpoints.restore(self, steps);
+        self.checkpoints = Some(checkpoints);
+        restored
+    }
+
     fn next(&mut self) -> i64 {
         let v = self.load(self.pc);
         self.pc += 1;
         v
     }
 
-    fn tick(&mut self) -> Result<(), Error<i64>> {
+    fn jump(&mut self, pc: usize, address: i64) -> Result<(), IntcodeError> {
+        if address < 0 {
+            self.pc = pc;
+            return Err(IntcodeError::OutOfBounds { pc, address });
+        }
+        self.pc = address as usize;
+        Ok(())
+    }
+
+    fn overflow(&mut self, pc: usize) -> IntcodeError {
+        self.pc = pc;
+        IntcodeError::Overflow { pc }
+    }
+
+    fn tick(&mut self) -> Result<State, IntcodeError> {
         let pc = self.pc;
-        let mut instruction: Instruction = self.into();
+        let mut instruction = match Instruction::try_from(&mut *self) {
+            Ok(instruction) => instruction,
+            Err(e) => {
+                self.pc = pc;
+                return Err(e);
+            }
+        };
 
         if let Some(debug) = self.debug {
             instruction = debug(self, pc, instruction);
         }
 
+        let state = self.execute(pc, instruction)?;
+        if state != State::NeedsInput {
+            self.steps += 1;
+            if let Some(mut trace) = self.trace.take() {
+                trace.record(self, pc, instruction);
+                self.trace = Some(trace);
+            }
+            if let Some(mut checkpoints) = self.checkpoints.take() {
+                checkpoints.record(self);
+                self.checkpoints = Some(checkpoints);
+            }
+        }
+        Ok(state)
+    }
+
+    fn execute(&mut self, pc: usize, instruction: Instruction) -> Result<State, IntcodeError> {
+        let target = match instruction {
+            Instruction::Add(_, _, r)
+            | Instruction::Multiply(_, _, r)
+            | Instruction::LessThan(_, _, r)
+            | Instruction::Equals(_, _, r)
+            | Instruction::Input(r) => Some(r),
+            _ => None,
+        };
+        // A debug hook may have rewritten the target
+        if let Some(address) = target.filter(|&address| address < 0) {
+            self.pc = pc;
+            return Err(IntcodeError::OutOfBounds { pc, address });
+        }
+        if let Some(address) = target {
+            if address as usize >= self.memory_limit.unwrap_or(MEMORY_CEILING) {
+                self.pc = pc;
+                return Err(IntcodeError::MemoryLimit { pc, address });
+            }
+        }
+
         match instruction {
             Instruction::Add(r1, r2, r3) => {
-                self.store(r3 as usize, r1 + r2);
+                let v = r1.checked_add(r2).ok_or_else(|| self.overflow(pc))?;
+                self.store(r3 as usize, v);
             }
             Instruction::Multiply(r1, r2, r3) => {
-                self.store(r3 as usize, r1 * r2);
+                let v = r1.checked_mul(r2).ok_or_else(|| self.overflow(pc))?;
+                self.store(r3 as usize, v);
             }
             Instruction::Input(r1) => {
-                let rx_input = self.input.as_ref().expect("Input channel expected");
-                let v = rx_input.recv()?;
+                let v = match (
+                    self.input_queue.pop_front(),
+                    self.input.as_mut(),
+                    self.idle_input,
+                ) {
+                    (Some(v), _, _) => v,
+                    (None, Some(source), idle) => {
+                        let input = match (idle, self.input_timeout) {
+                            (None, Some(timeout)) => source.read_timeout(timeout),
+                            _ => source.read(idle.is_none()),
+                        };
+                        match (input, idle) {
+                            (Input::Value(v), _) => v,
+                            (Input::Empty, Some(idle)) => {
+                                self.idle_reads += 1;
+                                idle
+                            }
+                            (Input::TimedOut, _) => {
+                                self.pc = pc;
+                                return Err(IntcodeError::InputTimeout { pc });
+                            }
+                            _ => {
+                                self.pc = pc;
+                                return Err(IntcodeError::InputExhausted { pc });
+                            }
+                        }
+                    }
+                    (None, None, Some(idle)) => {
+                        self.idle_reads += 1;
+                        idle
+                    }
+                    (None, None, None) => {
+                        // Retry the instruction once input is available
+                        self.pc = pc;
+                        return Ok(State::NeedsInput);
+                    }
+                };
                 self.store(r1 as usize, v);
+                self.inputs += 1;
             }
             Instruction::Output(r1) => {
-                let tx_output = self.output.as_ref().expect("Output channel expected");
-                tx_output.send(r1)?;
+                if let Some(sink) = self.output.as_mut() {
+                    if !sink.write(r1) {
+                        self.pc = pc;
+                        return Err(IntcodeError::OutputClosed { pc, value: r1 });
+                    }
+                }
+                self.outputs += 1;
+                return Ok(State::Output(r1));
             }
             Instruction::JumpIfTrue(r1, r2) => {
                 if r1 != 0 {
-                    self.pc = r2 as usize;
+                    self.jump(pc, r2)?;
                 }
             }
             Instruction::JumpIfFalse(r1, r2) => {
                 if r1 == 0 {
-                    self.pc = r2 as usize;
+                    self.jump(pc, r2)?;
                 }
             }
             Instruction::LessThan(r1, r2, r3) => {
@@ -263,13 +1004,47 @@ impl IntcodeMachine {
                 self.store(r3 as usize, if r1 == r2 { 1 } else { 0 });
             }
             Instruction::RelativeBase(r1) => {
-                self.relative_base += r1;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(r1)
+                    .ok_or_else(|| self.overflow(pc))?;
             }
             Instruction::Exit => {
                 self.halted = true;
+                return Ok(State::Halted);
+            }
+        }
+        Ok(State::Running)
+    }
+}
+
+/// Iterator over a machine's output, see [`IntcodeMachine::outputs`].
+pub struct Outputs<'a> {
+    machine: &'a mut IntcodeMachine,
+    done: bool,
+}
+
+impl Iterator for Outputs<'_> {
+    type Item = Result<i64, IntcodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if let Some(v) = self.machine.pop_output() {
+            return Some(Ok(v));
+        }
+        match self.machine.run_until(Event::Output) {
+            Ok(State::Output(v)) => Some(Ok(v)),
+            Ok(_) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
             }
         }
-        Ok(())
     }
 }
 
@@ -296,27 +1071,27 @@ mod tests {
     fn test_intcode_machine() {
         let program = vec![1, 0, 0, 0, 99];
         let mut im = IntcodeMachine::new(&program, None, None);
-        im.run();
+        im.run().unwrap();
         assert_eq!(&im.mem[..program.len()], &[2, 0, 0, 0, 99]);
 
         let program = vec![2, 3, 0, 3, 99];
         let mut im = IntcodeMachine::new(&program, None, None);
-        im.run();
+        im.run().unwrap();
         assert_eq!(&im.mem[..program.len()], &[2, 3, 0, 6, 99]);
 
         let program = vec![2, 4, 4, 5, 99, 0];
         let mut im = IntcodeMachine::new(&program, None, None);
-        im.run();
+        im.run().unwrap();
         assert_eq!(&im.mem[..program.len()], &[2, 4, 4, 5, 99, 9801]);
 
         let program = vec![1, 1, 1, 4, 99, 5, 6, 0, 99];
         let mut im = IntcodeMachine::new(&program, None, None);
-        im.run();
+        im.run().unwrap();
         assert_eq!(&im.mem[..program.len()], &[30, 1, 1, 4, 2, 5, 6, 0, 99]);
 
         let program = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
         let mut im = IntcodeMachine::new(&program, None, None);
-        im.run();
+        im.run().unwrap();
         assert_eq!(
             &im.mem[..program.len()],
             &[3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50]
@@ -332,7 +1107,7 @@ mod tests {
         let (tx_output, rx_output) = channel();
         let mut im = IntcodeMachine::new(&program, Some(rx_input), Some(tx_output));
         tx_input.send(1).unwrap();
-        im.run();
+        im.run().unwrap();
 
         assert_eq!(rx_output.iter().collect_vec(), vec![1]);
     }
@@ -341,12 +1116,12 @@ mod tests {
     fn test_immediate_mode() {
         let program = vec![1002, 4, 3, 4, 33];
         let mut im = IntcodeMachine::new(&program, None, None);
-        im.run();
+        im.run().unwrap();
         assert_eq!(&im.mem[..program.len()], &[1002, 4, 3, 4, 99]);
 
         let program = vec![1101, 100, -1, 4, 0];
         let mut im = IntcodeMachine::new(&program, None, None);
-        im.run();
+        im.run().unwrap();
         assert_eq!(&im.mem[..program.len()], &[1101, 100, -1, 4, 99]);
     }
 
@@ -358,14 +1133,14 @@ mod tests {
         let (tx_output, rx_output) = channel();
         let mut im = IntcodeMachine::new(&program, Some(rx_input), Some(tx_output));
         tx_input.send(8).unwrap();
-        im.run();
+        im.run().unwrap();
         assert_eq!(rx_output.iter().collect_vec(), vec![1]);
 
         let (tx_input, rx_input) = channel();
         let (tx_output, rx_output) = channel();
         let mut im = IntcodeMachine::new(&program, Some(rx_input), Some(tx_output));
         tx_input.send(1).unwrap();
-        im.run();
+        im.run().unwrap();
         assert_eq!(rx_output.iter().collect_vec(), vec![0]);
 
         let program = vec![3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8];
@@ -374,14 +1149,14 @@ mod tests {
         let (tx_output, rx_output) = channel();
         let mut im = IntcodeMachine::new(&program, Some(rx_input), Some(tx_output));
         tx_input.send(8).unwrap();
-        im.run();
+        im.run().unwrap();
         assert_eq!(rx_output.iter().collect_vec(), vec![0]);
 
         let (tx_input, rx_input) = channel();
         let (tx_output, rx_output) = channel();
         let mut im = IntcodeMachine::new(&program, Some(rx_input), Some(tx_output));
         tx_input.send(1).unwrap();
-        im.run();
+        im.run().unwrap();
         assert_eq!(rx_output.iter().collect_vec(), vec![1]);
 
         let program = vec![3, 3, 1108, -1, 8, 3, 4, 3, 99];
@@ -390,14 +1165,14 @@ mod tests {
         let (tx_output, rx_output) = channel();
         let mut im = IntcodeMachine::new(&program, Some(rx_input), Some(tx_output));
         tx_input.send(8).unwrap();
-        im.run();
+        im.run().unwrap();
         assert_eq!(rx_output.iter().collect_vec(), vec![1]);
 
         let (tx_input, rx_input) = channel();
         let (tx_output, rx_output) = channel();
         let mut im = IntcodeMachine::new(&program, Some(rx_input), Some(tx_output));
         tx_input.send(1).unwrap();
-        im.run();
+        im.run().unwrap();
         assert_eq!(rx_output.iter().collect_vec(), vec![0]);
 
         let program = vec![3, 3, 1107, -1, 8, 3, 4, 3, 99];
@@ -406,14 +1181,14 @@ mod tests {
         let (tx_output, rx_output) = channel();
         let mut im = IntcodeMachine::new(&program, Some(rx_input), Some(tx_output));
         tx_input.send(8).unwrap();
-        im.run();
+        im.run().unwrap();
         assert_eq!(rx_output.iter().collect_vec(), vec![0]);
 
         let (tx_input, rx_input) = channel();
         let (tx_output, rx_output) = channel();
         let mut im = IntcodeMachine::new(&program, Some(rx_input), Some(tx_output));
         tx_input.send(1).unwrap();
-        im.run();
+        im.run().unwrap();
         assert_eq!(rx_output.iter().collect_vec(), vec![1]);
     }
 
@@ -425,7 +1200,7 @@ mod tests {
         let (tx_output, rx_output) = channel();
         let mut im = IntcodeMachine::new(&program, Some(rx_input), Some(tx_output));
         tx_input.send(1).unwrap();
-        im.run();
+        im.run().unwrap();
         assert_eq!(rx_output.iter().collect_vec(), vec![1]);
 
         let program = vec![3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1];
@@ -434,7 +1209,7 @@ mod tests {
         let (tx_output, rx_output) = channel();
         let mut im = IntcodeMachine::new(&program, Some(rx_input), Some(tx_output));
         tx_input.send(1).unwrap();
-        im.run();
+        im.run().unwrap();
         assert_eq!(rx_output.iter().collect_vec(), vec![1]);
 
         let program = vec![
@@ -447,24 +1222,247 @@ mod tests {
         let (tx_output, rx_output) = channel();
         let mut im = IntcodeMachine::new(&program, Some(rx_input), Some(tx_output));
         tx_input.send(1).unwrap();
-        im.run();
+        im.run().unwrap();
         assert_eq!(rx_output.iter().collect_vec(), vec![999]);
 
         let (tx_input, rx_input) = channel();
         let (tx_output, rx_output) = channel();
         let mut im = IntcodeMachine::new(&program, Some(rx_input), Some(tx_output));
         tx_input.send(8).unwrap();
-        im.run();
+        im.run().unwrap();
         assert_eq!(rx_output.iter().collect_vec(), vec![1000]);
 
         let (tx_input, rx_input) = channel();
         let (tx_output, rx_output) = channel();
         let mut im = IntcodeMachine::new(&program, Some(rx_input), Some(tx_output));
         tx_input.send(50).unwrap();
-        im.run();
+        im.run().unwrap();
         assert_eq!(rx_output.iter().collect_vec(), vec![1001]);
     }
 
+    #[test]
+    fn test_instruction_display() {
+        assert_eq!(Instruction::Add(1, 2, 3).to_string(), "add 1, 2, 3");
+        assert_eq!(Instruction::Exit.to_string(), "hlt");
+        assert_eq!(format!("{:>8}|", Instruction::Output(7)), "   out 7|");
+        assert_eq!(format!("{:<8}|", Instruction::Input(7)), "in 7    |");
+    }
+
+    #[test]
+    fn test_format_instruction() {
+        let program = vec![1002, 4, 3, 4, 33];
+        let mut im = IntcodeMachine::new(&program, None, None);
+        let instruction = Instruction::try_from(&mut im).unwrap();
+
+        assert_eq!(
+            im.format_instruction(0, &instruction).to_string(),
+            format!("{:>5}: {:26} {:>5?}", 0, "mul 33, 3, 4", &program[..4])
+        );
+        assert_eq!(
+            im.format_instruction(0, &instruction)
+                .width(0)
+                .modes(true)
+                .to_string(),
+            "    0: mul [4], 3, [4] [ 1002,     4,     3,     4]"
+        );
+        assert_eq!(
+            im.format_instruction(0, &instruction)
+                .width(0)
+                .modes(true)
+                .values(true)
+                .to_string(),
+            "    0: mul [4]=33, 3, [4] [ 1002,     4,     3,     4]"
+        );
+
+        let program = vec![109, 10, 22201, -1, 1, 3, 99];
+        let mut im = IntcodeMachine::new(&program, None, None);
+        im.tick().unwrap();
+        let instruction = Instruction::try_from(&mut im).unwrap();
+        assert_eq!(
+            im.format_instruction(2, &instruction)
+                .width(0)
+                .modes(true)
+                .values(true)
+                .to_string(),
+            "    2: add [rb-1]=0, [rb+1]=0, [rb+3]@13 [22201,    -1,     1,     3]"
+        );
+
+        let colored = im
+            .format_instruction(2, &instruction)
+            .modes(true)
+            .color(true)
+            .to_string();
+        assert!(colored.contains(&Color::Cyan.paint("add").to_string()));
+        assert!(colored.contains(&Color::Purple.paint("[rb-1]").to_string()));
+        // Padding ignores the escape codes
+        let plain_width = "add [rb-1], [rb+1], [rb+3]".len();
+        assert!(colored.contains(&format!("m{} [", " ".repeat(26 - plain_width))));
+    }
+
+    #[test]
+    fn test_growable_memory() {
+        let program = vec![1101, 6, 7, 100_000, 4, 100_000, 99];
+        let (tx_output, rx_output) = channel();
+        let mut im = IntcodeMachine::new(&program, None, Some(tx_output));
+        assert_eq!(im.mem.len(), program.len());
+        assert_eq!(im.load(50_000), 0);
+
+        im.run().unwrap();
+        assert_eq!(rx_output.recv(), Ok(13));
+        assert_eq!(im.load(100_000), 13);
+        assert_eq!(im.load(99_999), 0);
+        assert_eq!(im.mem.len(), 100_001);
+    }
+
+    #[test]
+    fn test_step_io() {
+        let program = vec![3, 0, 4, 0, 99];
+        let mut im = IntcodeMachine::new(&program, None, None);
+
+        assert_eq!(im.step(), Ok(State::NeedsInput));
+        assert_eq!(im.pc(), 0);
+        assert_eq!(im.step(), Ok(State::NeedsInput));
+
+        im.push_input(7);
+        assert_eq!(im.step(), Ok(State::Running));
+        assert_eq!(im.step(), Ok(State::Output(7)));
+        assert_eq!(im.step(), Ok(State::Halted));
+        assert_eq!(im.step(), Ok(State::Halted));
+    }
+
+    #[test]
+    fn test_run_until() {
+        // Output the sum of two inputs twice
+        let program = vec![3, 13, 3, 14, 1, 13, 14, 15, 4, 15, 4, 15, 99, 0, 0, 0];
+        let mut im = IntcodeMachine::new(&program, None, None);
+
+        im.push_input(2);
+        assert_eq!(im.run_until(Event::Output), Ok(State::NeedsInput));
+        im.push_input(3);
+        assert_eq!(im.run_until(Event::Output), Ok(State::Output(5)));
+        assert_eq!(im.pop_output(), None);
+
+        assert_eq!(im.run_until(Event::Input), Ok(State::Halted));
+        assert_eq!(im.drain_output(), vec![5]);
+
+        let mut im = IntcodeMachine::new(&program, None, None);
+        im.push_input(2);
+        im.push_input(3);
+        assert_eq!(im.run_until(Event::Input), Ok(State::Halted));
+        assert_eq!(im.drain_output(), vec![5, 5]);
+    }
+
+    #[test]
+    fn test_run_for() {
+        // Output a countdown from the input, then halt
+        let program = vec![3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0];
+        let mut im = IntcodeMachine::new(&program, None, None);
+        assert_eq!(im.run_for(10), Ok(RunResult::NeedsInput));
+        assert_eq!(im.steps(), 0);
+
+        im.push_input(3);
+        assert_eq!(im.run_for(5), Ok(RunResult::BudgetExhausted));
+        assert_eq!(im.steps(), 5);
+        assert_eq!(im.drain_output(), vec![3, 2]);
+        assert_eq!(im.run_for(100), Ok(RunResult::Halted));
+        assert_eq!(im.drain_output(), vec![1]);
+        assert_eq!(im.steps(), 11);
+        assert_eq!(im.run_for(0), Ok(RunResult::Halted));
+    }
+
+    #[test]
+    fn test_stats() {
+        // Store the input past the end of the program and echo it twice
+        let program = vec![3, 20, 4, 20, 4, 20, 99];
+        let mut im = IntcodeMachine::new(&program, None, None);
+        im.push_input(7);
+        let snapshot = im.snapshot();
+        im.run().unwrap();
+        let stats = im.stats();
+        assert_eq!(
+            stats,
+            Stats {
+                steps: 4,
+                memory: 21,
+                inputs: 1,
+                outputs: 2
+            }
+        );
+        assert_eq!(
+            stats.to_string(),
+            "4 steps, 21 words of memory, 1 inputs, 2 outputs"
+        );
+
+        im.restore(&snapshot);
+        assert_eq!(
+            im.stats(),
+            Stats {
+                memory: 7,
+                ..Stats::default()
+            }
+        );
+        im.reset();
+        assert_eq!(im.stats().outputs, 0);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let program = vec![3, 13, 3, 14, 1, 13, 14, 15, 4, 15, 4, 15, 99, 0, 0, 0];
+        assert_eq!(IntcodeMachine::evaluate(&program, &[2, 3]), Ok(Some(5)));
+        assert_eq!(
+            IntcodeMachine::evaluate(&program, &[2]),
+            Err(IntcodeError::InputExhausted { pc: 2 })
+        );
+        assert_eq!(IntcodeMachine::evaluate(&[99], &[]), Ok(None));
+    }
+
+    #[test]
+    fn test_idle_input() {
+        let program = vec![3, 9, 3, 10, 4, 9, 4, 10, 99, 0, 0];
+        let mut im = IntcodeMachine::new(&program, None, None);
+        im.set_idle_input(Some(-1));
+        im.push_input(4);
+        assert_eq!(im.run_until(Event::Input), Ok(State::Halted));
+        assert_eq!(im.drain_output(), vec![4, -1]);
+        assert_eq!(im.idle_reads(), 1);
+
+        let (tx_input, rx_input) = channel();
+        let mut im = IntcodeMachine::new(&program, Some(rx_input), None);
+        im.set_idle_input(Some(-1));
+        tx_input.send(5).unwrap();
+        assert_eq!(im.run_until(Event::Input), Ok(State::Halted));
+        assert_eq!(im.drain_output(), vec![5, -1]);
+
+        let mut im = IntcodeMachine::new(&program, Some(channel().1), None);
+        im.set_idle_input(Some(-1));
+        assert_eq!(im.step(), Err(IntcodeError::InputExhausted { pc: 0 }));
+    }
+
+    #[test]
+    fn test_iterator_io() {
+        // Output the sum of two inputs, twice over
+        let program = vec![3, 13, 3, 14, 1, 13, 14, 15, 4, 15, 1105, 1, 0, 0, 0, 0];
+        let (tx_output, rx_output) = channel();
+        let mut im = IntcodeMachine::with_io(&program, vec![1, 2, 3, 4], move |v| {
+            tx_output.send(v).unwrap()
+        });
+        assert_eq!(im.run(), Err(IntcodeError::InputExhausted { pc: 0 }));
+        assert_eq!(rx_output.iter().collect_vec(), vec![3, 7]);
+
+        let mut im = IntcodeMachine::new(&program, None, None);
+        im.set_input_source(IterSource(vec![5, 6].into_iter()));
+        assert_eq!(
+            im.outputs().collect_vec(),
+            vec![Ok(11), Err(IntcodeError::InputExhausted { pc: 0 })]
+        );
+
+        // Without a source, outputs stop when the machine needs input
+        let mut im = IntcodeMachine::new(&program, None, None);
+        im.push_input(1);
+        im.push_input(1);
+        assert_eq!(im.outputs().collect_vec(), vec![Ok(2)]);
+    }
+
     // Day 9 examples
     #[test]
     fn test_relative_mode() {
@@ -474,7 +1472,7 @@ mod tests {
         let (tx_output, rx_output) = channel();
 
         let mut im = IntcodeMachine::new(&program, None, Some(tx_output));
-        im.run();
+        im.run().unwrap();
 
         let output = rx_output.iter().collect_vec();
         assert_eq!(output, program);
@@ -483,7 +1481,7 @@ mod tests {
         let (tx_output, rx_output) = channel();
 
         let mut im = IntcodeMachine::new(&program, None, Some(tx_output));
-        im.run();
+        im.run().unwrap();
 
         assert_eq!(rx_output.recv(), Ok(1219070632396864));
 
@@ -491,7 +1489,7 @@ mod tests {
         let (tx_output, rx_output) = channel();
 
         let mut im = IntcodeMachine::new(&program, None, Some(tx_output));
-        im.run();
+        im.run().unwrap();
 
         assert_eq!(rx_output.recv(), Ok(1125899906842624));
     }
@@ -0,0 +1,246 @@
+//! Static analysis of Intcode programs, without running them.
+//!
+//! Code is found by following control flow from address 0. Jumps with
+//! immediate targets are followed, while jumps to computed addresses are
+//! only counted, so anything they alone reach shows up as data.
+
+use super::Perm;
+use itertools::Itertools;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::Range;
+
+/// An instruction that writes over reachable code.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SelfModification {
+    pub pc: usize,
+    pub address: usize,
+}
+
+/// What [`analyze`] found out about a program.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Report {
+    /// Words in the program.
+    pub length: usize,
+    /// Reachable instructions.
+    pub instructions: usize,
+    /// Reachable instructions by mnemonic.
+    pub opcodes: BTreeMap<&'static str, usize>,
+    pub inputs: usize,
+    pub outputs: usize,
+    /// Jumps whose target isn't known until run time.
+    pub indirect_jumps: usize,
+    pub self_modifications: Vec<SelfModification>,
+    /// Runs of words that aren't reachable code.
+    pub data: Vec<Range<usize>>,
+}
+
+/// Mnemonic and operand kinds of an opcode.
+fn shape(opcode: i64) -> Option<(&'static str, &'static [Perm])> {
+    use Perm::{Read, Write};
+
+    Some(match opcode {
+        1 => ("add", &[Read, Read, Write]),
+        2 => ("mul", &[Read, Read, Write]),
+        3 => ("in", &[Write]),
+        4 => ("out", &[Read]),
+        5 => ("jnz", &[Read, Read]),
+        6 => ("jz", &[Read, Read]),
+        7 => ("lt", &[Read, Read, Write]),
+        8 => ("eq", &[Read, Read, Write]),
+        9 => ("arb", &[Read]),
+        99 => ("hlt", &[]),
+        _ => return None,
+    })
+}
+
+/// An instruction decoded from memory, with each operand's mode and raw word.
+struct Decoded {
+    mnemonic: &'static str,
+    operands: Vec<(i64, i64, &'static Perm)>,
+}
+
+fn decode(program: &[i64], pc: usize) -> Option<Decoded> {
+    let word = *program.get(pc)?;
+    let (mnemonic, perms) = shape(word % 100)?;
+    let mut operands = Vec::new();
+    for (i, perm) in perms.iter().enumerate() {
+        let mode = word / 10i64.pow(i as u32 + 2) % 10;
+        if mode > 2 || (mode == 1 && matches!(perm, Perm::Write)) {
+            return None;
+        }
+        operands.push((mode, *program.get(pc + i + 1)?, perm));
+    }
+    // Unused mode digits should be zero
+    if word / 10i64.pow(perms.len() as u32 + 2) != 0 {
+        return None;
+    }
+    Some(Decoded { mnemonic, operands })
+}
+
+pub fn analyze(program: &[i64]) -> Report {
+    let mut report = Report {
+        length: program.len(),
+        ..Report::default()
+    };
+    let mut code = BTreeSet::new();
+    let mut writes = Vec::new();
+    let mut seen = BTreeSet::new();
+    let mut pending = vec![0];
+
+    while let Some(pc) = pending.pop() {
+        if !seen.insert(pc) {
+            continue;
+        }
+        let decoded = match decode(program, pc) {
+            Some(decoded) => decoded,
+            None => continue,
+        };
+        let size = decoded.operands.len() + 1;
+        code.extend(pc..pc + size);
+        report.instructions += 1;
+        *report.opcodes.entry(decoded.mnemonic).or_insert(0) += 1;
+
+        for &(mode, raw, perm) in &decoded.operands {
+            if matches!(perm, Perm::Write) && mode == 0 && raw >= 0 {
+                writes.push(SelfModification {
+                    pc,
+                    address: raw as usize,
+                });
+            }
+        }
+
+        let next = pc + size;
+        match (decoded.mnemonic, &decoded.operands[..]) {
+            ("hlt", _) => {}
+            ("in", _) => {
+                report.inputs += 1;
+                pending.push(next);
+            }
+            ("out", _) => {
+                report.outputs += 1;
+                pending.push(next);
+            }
+            (jump, &[(condition_mode, condition, _), (target_mode, target, _)])
+                if jump == "jnz" || jump == "jz" =>
+            {
+                // An immediate condition makes the jump always or never taken
+                let taken = (condition != 0) == (jump == "jnz");
+                if condition_mode != 1 || !taken {
+                    pending.push(next);
+                }
+                if condition_mode != 1 || taken {
+                    if target_mode == 1 && target >= 0 {
+                        pending.push(target as usize);
+                    } else {
+                        report.indirect_jumps += 1;
+                    }
+                }
+            }
+            _ => pending.push(next),
+        }
+    }
+
+    report.self_modifications = writes
+        .into_iter()
+        .filter(|w| code.contains(&w.address))
+        .collect();
+
+    let mut start = None;
+    for address in 0..=program.len() {
+        match (start, address < program.len() && !code.contains(&address)) {
+            (None, true) => start = Some(address),
+            (Some(s), false) => {
+                report.data.push(s..address);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    report
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "length          {}", self.length)?;
+        writeln!(f, "instructions    {}", self.instructions)?;
+        writeln!(f, "inputs          {}", self.inputs)?;
+        writeln!(f, "outputs         {}", self.outputs)?;
+        writeln!(f, "indirect jumps  {}", self.indirect_jumps)?;
+        writeln!(
+            f,
+            "opcodes         {}",
+            self.opcodes
+                .iter()
+                .map(|(mnemonic, count)| format!("{} {}", mnemonic, count))
+                .join(", ")
+        )?;
+        writeln!(f, "self-modifying  {}", self.self_modifications.len())?;
+        for SelfModification { pc, address } in &self.self_modifications {
+            writeln!(f, "  {:>5} writes {}", pc, address)?;
+        }
+        let words = self.data.iter().map(|r| r.len()).sum::<usize>();
+        writeln!(f, "data            {} words", words)?;
+        for range in &self.data {
+            writeln!(f, "  {:>5}..{}", range.start, range.end)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    #[test]
+    fn test_analyze() {
+        let program = assemble(
+            "
+                  in [n]
+            loop: out [n]
+                  add [n], -1, [n]
+                  add [next], 0, [patch]
+                  jnz [n], loop
+                  jz 0, done
+            patch: .data 7, 8
+            done: hlt
+            next: .data 0
+            n:    .data 0
+            ",
+        )
+        .unwrap();
+        let report = analyze(&program);
+
+        assert_eq!(report.length, 23);
+        assert_eq!(report.instructions, 7);
+        assert_eq!(report.inputs, 1);
+        assert_eq!(report.outputs, 1);
+        assert_eq!(report.indirect_jumps, 0);
+        assert_eq!(report.opcodes["add"], 2);
+        assert_eq!(report.self_modifications, vec![]);
+        assert_eq!(report.data, vec![18..20, 21..23]);
+    }
+
+    #[test]
+    fn test_self_modification() {
+        // Overwrite the exit with an output, then jump through memory
+        let program = assemble(
+            "
+                  add 0, 104, [end]
+                  jnz [target], [target]
+            end:  hlt
+                  .data 5
+            target: .data 8
+            ",
+        )
+        .unwrap();
+        let report = analyze(&program);
+        assert_eq!(
+            report.self_modifications,
+            vec![SelfModification { pc: 0, address: 7 }]
+        );
+        assert_eq!(report.indirect_jumps, 1);
+        assert_eq!(report.data, vec![8..10]);
+    }
+}
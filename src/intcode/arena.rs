@@ -0,0 +1,155 @@
+//! Reusing machines across many short runs of one program.
+
+use super::{Event, IntcodeError, IntcodeMachine, State};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// A pool of machines loaded with the same program.
+///
+/// Machines are reset rather than dropped when they're returned, keeping the
+/// memory they grew, so once the pool is as big as the most machines used at
+/// once, running the program again allocates nothing. The pool can be shared
+/// between threads.
+pub struct Arena {
+    program: Vec<i64>,
+    pool: Mutex<Vec<IntcodeMachine>>,
+    created: AtomicUsize,
+}
+
+impl Arena {
+    pub fn new(program: &[i64]) -> Self {
+        Self::with_capacity(program, 0)
+    }
+
+    /// An arena with `capacity` machines loaded up front.
+    pub fn with_capacity(program: &[i64], capacity: usize) -> Self {
+        let pool = (0..capacity)
+            .map(|_| IntcodeMachine::new(program, None, None))
+            .collect();
+        Arena {
+            program: program.to_owned(),
+            pool: Mutex::new(pool),
+            created: AtomicUsize::new(capacity),
+        }
+    }
+
+    /// A fresh machine, from the pool if there's one free.
+    pub fn acquire(&self) -> Lease<'_> {
+        let machine = self.pool.lock().unwrap().pop().unwrap_or_else(|| {
+            self.created.fetch_add(1, Ordering::Relaxed);
+            IntcodeMachine::new(&self.program, None, None)
+        });
+        Lease {
+            arena: self,
+            machine: Some(machine),
+        }
+    }
+
+    /// Run the program on `inputs` and return its first output, like
+    /// [`IntcodeMachine::evaluate`].
+    pub fn evaluate(&self, inputs: &[i64]) -> Result<Option<i64>, IntcodeError> {
+        let mut im = self.acquire();
+        for &v in inputs {
+            im.push_input(v);
+        }
+        match im.run_until(Event::Output)? {
+            State::Output(v) => Ok(Some(v)),
+            State::NeedsInput => Err(IntcodeError::InputExhausted { pc: im.pc() }),
+            _ => Ok(None),
+        }
+    }
+
+    /// Machines made so far, in the pool or leased out.
+    pub fn created(&self) -> usize {
+        self.created.load(Ordering::Relaxed)
+    }
+}
+
+/// A machine borrowed from an [`Arena`], returned to it when dropped.
+///
+/// Anything set on the machine, such as channels or limits, is cleared
+/// before the next lease.
+pub struct Lease<'a> {
+    arena: &'a Arena,
+    machine: Option<IntcodeMachine>,
+}
+
+impl Deref for Lease<'_> {
+    type Target = IntcodeMachine;
+
+    fn deref(&self) -> &IntcodeMachine {
+        self.machine.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for Lease<'_> {
+    fn deref_mut(&mut self) -> &mut IntcodeMachine {
+        self.machine.as_mut().unwrap()
+    }
+}
+
+impl Drop for Lease<'_> {
+    fn drop(&mut self) {
+        if let Some(mut machine) = self.machine.take() {
+            machine.reset_all();
+            if let Ok(mut pool) = self.arena.pool.lock() {
+                pool.push(machine);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reuse() {
+        // Output the product of two inputs, writing past the program
+        let program = vec![3, 100, 3, 101, 2, 100, 101, 102, 4, 102, 99];
+        let arena = Arena::new(&program);
+        assert_eq!(arena.evaluate(&[6, 7]), Ok(Some(42)));
+        assert_eq!(arena.evaluate(&[3, 5]), Ok(Some(15)));
+        assert_eq!(arena.created(), 1);
+
+        {
+            let (mut a, b) = (arena.acquire(), arena.acquire());
+            assert_eq!(arena.created(), 2);
+            assert_eq!(a.load(100), 0);
+            assert_eq!(b.mem, program);
+            a.push_input(1);
+            assert_eq!(a.run_until(Event::Input), Ok(State::NeedsInput));
+        }
+        assert_eq!(arena.acquire().pc(), 0);
+        assert_eq!(
+            arena.evaluate(&[1]),
+            Err(IntcodeError::InputExhausted { pc: 2 })
+        );
+        assert_eq!(arena.created(), 2);
+    }
+
+    #[test]
+    fn test_lease_settings() {
+        let program = vec![3, 100, 3, 101, 2, 100, 101, 102, 4, 102, 99];
+        let arena = Arena::new(&program);
+        {
+            let mut lease = arena.acquire();
+            lease.set_memory_limit(Some(5));
+            lease.set_idle_input(Some(3));
+            assert_eq!(
+                lease.run(),
+                Err(IntcodeError::MemoryLimit {
+                    pc: 0,
+                    address: 100
+                })
+            );
+        }
+        assert_eq!(arena.created(), 1);
+        assert_eq!(arena.evaluate(&[6, 7]), Ok(Some(42)));
+        assert_eq!(
+            arena.evaluate(&[7]),
+            Err(IntcodeError::InputExhausted { pc: 2 })
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Text I/O for programs that talk ASCII, as on days 17, 21 and 25.
+
+use super::{IntcodeError, IntcodeMachine, State};
+use std::convert::TryFrom;
+
+/// A machine whose input and output are lines of text.
+///
+/// Output above 127 isn't text, puzzles use it to report an answer once the
+/// conversation is over, so it's set aside to be read with
+/// [`take_values`](AsciiMachine::take_values).
+pub struct AsciiMachine {
+    machine: IntcodeMachine,
+    values: Vec<i64>,
+}
+
+impl AsciiMachine {
+    pub fn new(program: &[i64]) -> Self {
+        Self::from(IntcodeMachine::new(program, None, None))
+    }
+
+    pub fn machine(&self) -> &IntcodeMachine {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut IntcodeMachine {
+        &mut self.machine
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.machine.is_halted()
+    }
+
+    /// Queue a line of input, UTF-8 encoded and followed by a newline.
+    pub fn send_line(&mut self, line: &str) {
+        for b in line.bytes().chain(Some(b'\n')) {
+            self.machine.push_input(i64::from(b));
+        }
+    }
+
+    /// Queue text as is, for input that's already split into lines.
+    pub fn send(&mut self, text: &str) {
+        for b in text.bytes() {
+            self.machine.push_input(i64::from(b));
+        }
+    }
+
+    /// Run until a whole line is output, returning it without the newline.
+    ///
+    /// If the machine needs input or halts first, whatever it printed of the
+    /// line is returned, or `None` if that's nothing.
+    pub fn read_line(&mut self) -> Result<Option<String>, IntcodeError> {
+        let mut line = Vec::new();
+        while let Some(v) = self.next_output()? {
+            match self.text(v) {
+                Some(b'\n') => return Ok(Some(decode(line))),
+                Some(b) => line.push(b),
+                None => {}
+            }
+        }
+        Ok(if line.is_empty() {
+            None
+        } else {
+            Some(decode(line))
+        })
+    }
+
+    /// Run until the machine needs input or halts, returning all the text
+    /// printed on the way.
+    pub fn read_screen(&mut self) -> Result<String, IntcodeError> {
+        let mut screen = Vec::new();
+        while let Some(v) = self.next_output()? {
+            if let Some(b) = self.text(v) {
+                screen.push(b);
+            }
+        }
+        Ok(decode(screen))
+    }
+
+    /// Take the non-text values output so far.
+    pub fn take_values(&mut self) -> Vec<i64> {
+        self.values.drain(..).collect()
+    }
+
+    /// The byte for a value, or `None` if it isn't text and was set aside.
+    fn text(&mut self, v: i64) -> Option<u8> {
+        match u8::try_from(v) {
+            Ok(b) if b.is_ascii() => Some(b),
+            _ => {
+                self.values.push(v);
+                None
+            }
+        }
+    }
+
+    fn next_output(&mut self) -> Result<Option<i64>, IntcodeError> {
+        if let Some(v) = self.machine.pop_output() {
+            return Ok(Some(v));
+        }
+        loop {
+            match self.machine.step()? {
+                State::Running => {}
+                State::Output(v) => return Ok(Some(v)),
+                State::NeedsInput | State::Halted => return Ok(None),
+            }
+        }
+    }
+}
+
+impl From<IntcodeMachine> for AsciiMachine {
+    fn from(machine: IntcodeMachine) -> Self {
+        AsciiMachine {
+            machine,
+            values: Vec::new(),
+        }
+    }
+}
+
+/// Text output is only ever ASCII, so is always valid UTF-8.
+fn decode(bytes: Vec<u8>) -> String {
+    bytes.into_iter().map(char::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    // Print a prompt, echo a line back in upper case, then a value and "ok"
+    fn echo() -> Vec<i64> {
+        assemble(
+            "
+                  out 62
+                  out 10
+            read: in [c]
+                  eq [c], 10, [end]
+                  jnz [end], done
+                  lt [c], 97, [upper]
+                  jnz [upper], print
+                  add [c], -32, [c]
+           print: out [c]
+                  jz 0, read
+            done: out 10
+                  out 1000
+                  out 111
+                  out 107
+                  hlt
+            c:     .data 0
+            end:   .data 0
+            upper: .data 0
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_read_line() {
+        let mut am = AsciiMachine::new(&echo());
+        assert_eq!(am.read_line(), Ok(Some(">".to_owned())));
+        assert_eq!(am.read_line(), Ok(None));
+
+        am.send_line("hi There");
+        assert_eq!(am.read_line(), Ok(Some("HI THERE".to_owned())));
+        assert_eq!(am.read_line(), Ok(Some("ok".to_owned())));
+        assert_eq!(am.read_line(), Ok(None));
+        assert!(am.is_halted());
+        assert_eq!(am.take_values(), vec![1000]);
+    }
+
+    #[test]
+    fn test_read_screen() {
+        let mut am = AsciiMachine::new(&echo());
+        am.send("abc\n");
+        assert_eq!(am.read_screen(), Ok(">\nABC\nok".to_owned()));
+        assert_eq!(am.take_values(), vec![1000]);
+        assert!(am.take_values().is_empty());
+    }
+}
@@ -0,0 +1,348 @@
+//! A small assembly language for writing Intcode programs by hand.
+//!
+//! Each line holds an optional `label:`, then an instruction or a `.data`
+//! directive, and anything after a `;` is a comment. Instructions use the
+//! same mnemonics as [`Instruction::mnemonic`](super::Instruction::mnemonic)
+//! and write their operands with the parameter modes shown by
+//! [`InstructionFormat`](super::InstructionFormat): `[12]` for position,
+//! `12` for immediate and `[rb+12]` for relative. A label can be used
+//! anywhere a number can, and stands for its address.
+//!
+//! ```
+//! use advent_of_code_2019::intcode::asm::assemble;
+//!
+//! let program = assemble(
+//!     "
+//!     in [x]          ; read a value
+//!     mul [x], 2, [x]
+//!     out [x]
+//!     hlt
+//! x:  .data 0
+//!     ",
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(program, vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0]);
+//! ```
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+/// Why a line could not be assembled, with the line number counting from 1.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AsmError {
+    UnknownMnemonic {
+        line: usize,
+        mnemonic: String,
+    },
+    Operands {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    InvalidOperand {
+        line: usize,
+        operand: String,
+    },
+    UnknownLabel {
+        line: usize,
+        label: String,
+    },
+    DuplicateLabel {
+        line: usize,
+        label: String,
+    },
+}
+
+impl error::Error for AsmError {}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsmError::UnknownMnemonic { line, mnemonic } => {
+                write!(f, "line {}: unknown mnemonic {:?}", line, mnemonic)
+            }
+            AsmError::Operands {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: expected {} operands, found {}",
+                line, expected, found
+            ),
+            AsmError::InvalidOperand { line, operand } => {
+                write!(f, "line {}: invalid operand {:?}", line, operand)
+            }
+            AsmError::UnknownLabel { line, label } => {
+                write!(f, "line {}: unknown label {:?}", line, label)
+            }
+            AsmError::DuplicateLabel { line, label } => {
+                write!(f, "line {}: duplicate label {:?}", line, label)
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Perm {
+    Read,
+    Write,
+}
+
+/// Opcode and operand kinds for each mnemonic.
+fn opcode(mnemonic: &str) -> Option<(i64, &'static [Perm])> {
+    use Perm::{Read, Write};
+
+    Some(match mnemonic {
+        "add" => (1, &[Read, Read, Write]),
+        "mul" => (2, &[Read, Read, Write]),
+        "in" => (3, &[Write]),
+        "out" => (4, &[Read]),
+        "jnz" => (5, &[Read, Read]),
+        "jz" => (6, &[Read, Read]),
+        "lt" => (7, &[Read, Read, Write]),
+        "eq" => (8, &[Read, Read, Write]),
+        "arb" => (9, &[Read]),
+        "hlt" => (99, &[]),
+        _ => return None,
+    })
+}
+
+enum Value<'a> {
+    Number(i64),
+    Label(&'a str),
+}
+
+impl<'a> Value<'a> {
+    fn parse(s: &'a str) -> Option<Self> {
+        if let Ok(v) = s.parse() {
+            return Some(Value::Number(v));
+        }
+        let mut chars = s.chars();
+        let first = chars.next()?;
+        if (first.is_alphabetic() || first == '_') && chars.all(|c| c.is_alphanumeric() || c == '_')
+        {
+            Some(Value::Label(s))
+        } else {
+            None
+        }
+    }
+}
+
+/// A parameter mode and the value written with it.
+fn operand(s: &str) -> Option<(i64, Value<'_>)> {
+    match s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        Some(inner) => {
+            let inner = inner.trim();
+            let relative = inner
+                .strip_prefix("rb")
+                .map(str::trim_start)
+                .filter(|offset| offset.starts_with(['+', '-']));
+            match relative {
+                Some(offset) => match offset.strip_prefix('+') {
+                    Some(offset) => Some((2, Value::parse(offset.trim())?)),
+                    None => Some((2, Value::Number(offset.replace(' ', "").parse().ok()?))),
+                },
+                None => Some((0, Value::parse(inner)?)),
+            }
+        }
+        None => Some((1, Value::parse(s)?)),
+    }
+}
+
+/// Assemble `source` into an Intcode program.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    // Lay out every line first, so labels can be used before they're defined
+    let mut labels = HashMap::new();
+    let mut items = Vec::new();
+    let mut address = 0;
+
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let mut line = line.split(';').next().unwrap_or("").trim();
+
+        if let Some((label, rest)) = line.split_once(':') {
+            let label = label.trim();
+            if labels.insert(label, address as i64).is_some() {
+                return Err(AsmError::DuplicateLabel {
+                    line: line_number,
+                    label: label.to_owned(),
+                });
+            }
+            line = rest.trim();
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let operands = rest
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        address += if mnemonic == ".data" {
+            operands.len()
+        } else {
+            operands.len() + 1
+        };
+        items.push((line_number, mnemonic, operands));
+    }
+
+    let mut program = Vec::with_capacity(address);
+    for (line, mnemonic, operands) in items {
+        let resolve = |value| match value {
+            Value::Number(v) => Ok(v),
+            Value::Label(label) => labels.get(label).copied().ok_or(AsmError::UnknownLabel {
+                line,
+                label: label.to_owned(),
+            }),
+        };
+        let invalid = |operand: &str| AsmError::InvalidOperand {
+            line,
+            operand: operand.to_owned(),
+        };
+
+        if mnemonic == ".data" {
+            for s in operands {
+                program.push(resolve(Value::parse(s).ok_or_else(|| invalid(s))?)?);
+            }
+            continue;
+        }
+
+        let (opcode, perms) = opcode(mnemonic).ok_or_else(|| AsmError::UnknownMnemonic {
+            line,
+            mnemonic: mnemonic.to_owned(),
+        })?;
+        if operands.len() != perms.len() {
+            return Err(AsmError::Operands {
+                line,
+                expected: perms.len(),
+                found: operands.len(),
+            });
+        }
+
+        let start = program.len();
+        program.push(opcode);
+        for (i, (s, &perm)) in operands.iter().zip(perms).enumerate() {
+            let (mode, value) = operand(s).ok_or_else(|| invalid(s))?;
+            // Immediate mode can't be written to
+            if perm == Perm::Write && mode == 1 {
+                return Err(invalid(s));
+            }
+            program[start] += mode * 10i64.pow(i as u32 + 2);
+            program.push(resolve(value)?);
+        }
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{parse_program, Event, Instruction, IntcodeMachine, State};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_assemble() {
+        assert_eq!(
+            assemble("add [9], [10], [0]\nmul [0], [11], [0]\nhlt"),
+            Ok(vec![1, 9, 10, 0, 2, 0, 11, 0, 99])
+        );
+        assert_eq!(
+            assemble("arb 10\nadd [rb-1], [rb+1], [rb+3]\nhlt"),
+            Ok(vec![109, 10, 22201, -1, 1, 3, 99])
+        );
+        assert_eq!(
+            assemble("  ; nothing\n\n.data 1, -2, end\nend:"),
+            Ok(vec![1, -2, 3])
+        );
+    }
+
+    #[test]
+    fn test_labels() {
+        // Count down from 3, outputting each value
+        let source = "
+            loop: out [n]
+                  add [n], -1, [n]
+                  jnz [n], loop
+                  hlt
+            n:    .data 3
+        ";
+        let program = assemble(source).unwrap();
+        assert_eq!(program[program.len() - 1], 3);
+
+        let mut im = IntcodeMachine::new(&program, None, None);
+        let mut outputs = Vec::new();
+        while let Ok(State::Output(v)) = im.run_until(Event::Output) {
+            outputs.push(v);
+        }
+        assert_eq!(outputs, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Listings with modes shown assemble back to the original program
+        let program =
+            parse_program("3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,99,0,0,0,0,0")
+                .unwrap();
+        let mut im = IntcodeMachine::new(&program, None, None);
+        let mut source = String::new();
+        while im.pc() < 17 {
+            let pc = im.pc();
+            let instruction = Instruction::try_from(&mut im).unwrap();
+            let listing = im
+                .format_instruction(pc, &instruction)
+                .modes(true)
+                .to_string();
+            let text = listing.split_once(": ").unwrap().1;
+            source.push_str(text.rsplit_once('[').unwrap().0);
+            source.push('\n');
+        }
+        source.push_str(".data 0, 0, 0, 0, 0");
+
+        assert_eq!(assemble(&source), Ok(program));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            assemble("hlt\nnop"),
+            Err(AsmError::UnknownMnemonic {
+                line: 2,
+                mnemonic: "nop".to_owned()
+            })
+        );
+        assert_eq!(
+            assemble("add 1, 2"),
+            Err(AsmError::Operands {
+                line: 1,
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            assemble("in 5"),
+            Err(AsmError::InvalidOperand {
+                line: 1,
+                operand: "5".to_owned()
+            })
+        );
+        assert_eq!(
+            assemble("jz 0, missing"),
+            Err(AsmError::UnknownLabel {
+                line: 1,
+                label: "missing".to_owned()
+            })
+        );
+        assert_eq!(
+            assemble("a: hlt\na: hlt"),
+            Err(AsmError::DuplicateLabel {
+                line: 2,
+                label: "a".to_owned()
+            })
+        );
+    }
+}
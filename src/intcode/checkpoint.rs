@@ -0,0 +1,127 @@
+use super::snapshot::Snapshot;
+use super::IntcodeMachine;
+use std::collections::VecDeque;
+
+/// Machine state saved after some number of instructions.
+pub type Checkpoint = Snapshot;
+
+/// Checkpoints taken every `interval` instructions, keeping only the most
+/// recent `capacity`, see [`IntcodeMachine::start_checkpoints`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Checkpoints {
+    interval: u64,
+    capacity: usize,
+    saved: VecDeque<Checkpoint>,
+}
+
+impl Checkpoints {
+    pub(super) fn new(interval: u64, capacity: usize) -> Self {
+        Checkpoints {
+            interval: interval.max(1),
+            capacity: capacity.max(1),
+            saved: VecDeque::new(),
+        }
+    }
+
+    pub(super) fn save(&mut self, machine: &IntcodeMachine) {
+        if self.saved.len() == self.capacity {
+            self.saved.pop_front();
+        }
+        self.saved.push_back(Snapshot::take(machine));
+    }
+
+    /// Save a checkpoint if the machine is on an interval.
+    pub(super) fn record(&mut self, machine: &IntcodeMachine) {
+        if machine.steps.is_multiple_of(self.interval) {
+            self.save(machine);
+        }
+    }
+
+    /// Restore the latest checkpoint at or before `steps`, forgetting any
+    /// after it. Returns the steps the machine was restored to.
+    pub(super) fn restore(&mut self, machine: &mut IntcodeMachine, steps: u64) -> Option<u64> {
+        let index = self.saved.iter().rposition(|c| c.steps() <= steps)?;
+        self.saved.truncate(index + 1);
+        self.saved[index].restore(machine);
+        Some(self.saved[index].steps())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Checkpoint> {
+        self.saved.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.saved.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.saved.is_empty()
+    }
+
+    /// The latest checkpoint at or before `steps`.
+    pub fn nearest(&self, steps: u64) -> Option<&Checkpoint> {
+        self.saved.iter().rev().find(|c| c.steps() <= steps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::asm::assemble;
+    use crate::intcode::{Event, IntcodeMachine, State};
+
+    // Output a countdown from the first input
+    fn countdown() -> Vec<i64> {
+        assemble(
+            "
+                  in [n]
+            loop: out [n]
+                  add [n], -1, [n]
+                  jnz [n], loop
+                  hlt
+            n:    .data 0
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_checkpoints() {
+        let program = countdown();
+        let mut im = IntcodeMachine::new(&program, None, None);
+        im.push_input(10);
+        im.start_checkpoints(5, 3);
+        assert_eq!(im.run_until(Event::Input), Ok(State::Halted));
+        assert_eq!(im.steps(), 32);
+
+        // Taken at 0, 5, ..., 30, and only the last three kept
+        let checkpoints = im.checkpoints().unwrap();
+        let steps = checkpoints.iter().map(|c| c.steps()).collect::<Vec<_>>();
+        assert_eq!(steps, vec![20, 25, 30]);
+        assert_eq!(checkpoints.nearest(29).map(|c| c.steps()), Some(25));
+        assert_eq!(checkpoints.nearest(19), None);
+    }
+
+    #[test]
+    fn test_restore() {
+        let program = countdown();
+        let mut im = IntcodeMachine::new(&program, None, None);
+        im.push_input(10);
+        im.start_checkpoints(4, 10);
+        im.run_until(Event::Input).unwrap();
+        let output = im.drain_output();
+
+        assert_eq!(im.restore_checkpoint(18), Some(16));
+        assert_eq!(im.steps(), 16);
+        assert!(!im.is_halted());
+        assert_eq!(im.checkpoints().unwrap().len(), 5);
+
+        // The queued output from before the checkpoint comes back too
+        im.run_until(Event::Input).unwrap();
+        assert_eq!(im.drain_output(), output);
+        assert_eq!(im.steps(), 32);
+        assert_eq!(im.checkpoints().unwrap().len(), 9);
+
+        let mut im = IntcodeMachine::new(&program, None, None);
+        assert_eq!(im.restore_checkpoint(0), None);
+    }
+}
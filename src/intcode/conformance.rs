@@ -0,0 +1,303 @@
+//! Cross-checking this machine against another Intcode interpreter.
+//!
+//! The other interpreter is run as a command with the program's file path as
+//! its last argument. It's given the inputs on stdin, one per line, and should
+//! print each output on a line of its own. Final memory can be reported too,
+//! on a line of its own as `memory: 1,2,3`, and is compared if it is.
+
+use super::diff::MemoryDiff;
+use super::{IntcodeError, IntcodeMachine, RunResult};
+use std::error;
+use std::fmt;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Instructions a local run may take before it's given up on.
+const MAX_STEPS: u64 = 100_000_000;
+
+/// What a run of a program produced.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Outcome {
+    pub outputs: Vec<i64>,
+    /// Final memory, if the interpreter reported it.
+    pub memory: Option<Vec<i64>>,
+}
+
+impl Outcome {
+    /// Read an interpreter's stdout.
+    pub fn parse(stdout: &str) -> Result<Self, ConformanceError> {
+        let mut outcome = Outcome::default();
+        for line in stdout.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let parsed = match line.strip_prefix("memory:") {
+                Some(memory) => memory
+                    .split(',')
+                    .map(|v| v.trim().parse())
+                    .collect::<Result<_, _>>()
+                    .map(|memory| outcome.memory = Some(memory)),
+                None => line.parse().map(|v| outcome.outputs.push(v)),
+            };
+            if parsed.is_err() {
+                return Err(ConformanceError::Unreadable(line.to_owned()));
+            }
+        }
+        Ok(outcome)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConformanceError {
+    /// This machine faulted.
+    Local(IntcodeError),
+    /// This machine didn't halt within its step budget.
+    Budget(u64),
+    /// The interpreter couldn't be started or talked to.
+    Io(io::Error),
+    /// The interpreter exited unsuccessfully, with what it wrote to stderr.
+    Failed(String),
+    /// A line of the interpreter's output that isn't a value or memory.
+    Unreadable(String),
+}
+
+impl error::Error for ConformanceError {}
+
+impl fmt::Display for ConformanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConformanceError::Local(e) => write!(f, "this machine failed: {}", e),
+            ConformanceError::Budget(steps) => {
+                write!(f, "this machine didn't halt within {} steps", steps)
+            }
+            ConformanceError::Io(e) => write!(f, "couldn't run the interpreter: {}", e),
+            ConformanceError::Failed(stderr) => {
+                write!(f, "the interpreter failed: {}", stderr.trim_end())
+            }
+            ConformanceError::Unreadable(line) => {
+                write!(f, "unreadable interpreter output {:?}", line)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for ConformanceError {
+    fn from(e: io::Error) -> Self {
+        ConformanceError::Io(e)
+    }
+}
+
+/// Run `program` on this crate's machine to completion, with all of its
+/// input given up front.
+pub fn run_local(program: &[i64], inputs: &[i64]) -> Result<Outcome, ConformanceError> {
+    let mut machine = IntcodeMachine::new(program, None, None);
+    for &v in inputs {
+        machine.push_input(v);
+    }
+    match machine.run_for(MAX_STEPS) {
+        Ok(RunResult::Halted) => {}
+        Ok(RunResult::NeedsInput) => {
+            let pc = machine.pc();
+            return Err(ConformanceError::Local(IntcodeError::InputExhausted { pc }));
+        }
+        Ok(RunResult::BudgetExhausted) => return Err(ConformanceError::Budget(MAX_STEPS)),
+        Err(e) => return Err(ConformanceError::Local(e)),
+    }
+    Ok(Outcome {
+        outputs: machine.drain_output(),
+        memory: Some(machine.mem),
+    })
+}
+
+/// Another interpreter, run as `command args... <program file>`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Interpreter {
+    command: String,
+    args: Vec<String>,
+}
+
+impl Interpreter {
+    pub fn new<I, S>(command: &str, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Interpreter {
+            command: command.to_owned(),
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn run(&self, program: &Path, inputs: &[i64]) -> Result<Outcome, ConformanceError> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .arg(program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            let lines = inputs
+                .iter()
+                .map(|v| format!("{}\n", v))
+                .collect::<String>();
+            // An interpreter that stops reading early isn't an error here
+            let _ = stdin.write_all(lines.as_bytes());
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(ConformanceError::Failed(
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            ));
+        }
+        Outcome::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+/// A way two runs of the same program disagree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Mismatch {
+    /// The first output that differs, `None` where one run stopped short.
+    Output {
+        index: usize,
+        local: Option<i64>,
+        external: Option<i64>,
+    },
+    /// Final memory differs, from this machine's to the interpreter's.
+    Memory(MemoryDiff),
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn value(v: &Option<i64>) -> String {
+            v.map_or_else(|| "nothing".to_owned(), |v| v.to_string())
+        }
+        match self {
+            Mismatch::Output {
+                index,
+                local,
+                external,
+            } => write!(
+                f,
+                "output {}: {} here, {} from the interpreter",
+                index,
+                value(local),
+                value(external)
+            ),
+            Mismatch::Memory(diff) => write!(
+                f,
+                "final memory, here -> interpreter:\n{}",
+                diff.to_string().trim_end()
+            ),
+        }
+    }
+}
+
+/// Everything that differs between a local run and an interpreter's, with
+/// memory only compared if both reported it.
+pub fn compare(local: &Outcome, external: &Outcome) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    let len = local.outputs.len().max(external.outputs.len());
+    if let Some(index) = (0..len).find(|&i| local.outputs.get(i) != external.outputs.get(i)) {
+        mismatches.push(Mismatch::Output {
+            index,
+            local: local.outputs.get(index).copied(),
+            external: external.outputs.get(index).copied(),
+        });
+    }
+    if let (Some(ours), Some(theirs)) = (&local.memory, &external.memory) {
+        let diff = MemoryDiff::new(ours, theirs);
+        if !diff.is_empty() {
+            mismatches.push(Mismatch::Memory(diff));
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_parse() {
+        let outcome = Outcome::parse("42\n\n-7\nmemory: 3, 0,4,0,99\n").unwrap();
+        assert_eq!(outcome.outputs, vec![42, -7]);
+        assert_eq!(outcome.memory, Some(vec![3, 0, 4, 0, 99]));
+        assert_eq!(Outcome::parse("").unwrap(), Outcome::default());
+        match Outcome::parse("1\nfault at 4\n") {
+            Err(ConformanceError::Unreadable(line)) => assert_eq!(line, "fault at 4"),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let program = assemble("in [0]\nmul [0], 2, [0]\nout [0]\nhlt").unwrap();
+        let local = run_local(&program, &[21]).unwrap();
+        assert_eq!(local.outputs, vec![42]);
+
+        let mut external = Outcome {
+            outputs: vec![42],
+            memory: None,
+        };
+        assert_eq!(compare(&local, &external), vec![]);
+
+        external.outputs.push(1);
+        let mut memory = local.memory.clone().unwrap();
+        memory[0] = 43;
+        memory.push(0);
+        external.memory = Some(memory);
+        let mismatches = compare(&local, &external);
+        assert_eq!(
+            mismatches[0],
+            Mismatch::Output {
+                index: 1,
+                local: None,
+                external: Some(1)
+            }
+        );
+        assert_eq!(
+            mismatches[0].to_string(),
+            "output 1: nothing here, 1 from the interpreter"
+        );
+        assert_eq!(
+            mismatches[1].to_string(),
+            "final memory, here -> interpreter:\n    0: 42 -> 43"
+        );
+
+        match run_local(&program, &[]) {
+            Err(ConformanceError::Local(IntcodeError::InputExhausted { pc: 0 })) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_interpreter() {
+        let path = env::temp_dir().join(format!("conformance-{}.int", std::process::id()));
+        fs::write(&path, "3,0,1002,0,2,0,4,0,99\n").unwrap();
+
+        // Doubles its input, but reports memory as if it had added one
+        let script = "read v; echo $((v * 2)); echo \"memory: $((v + 1)),0,1002,0,2,0,4,0,99\"";
+        let outcome = Interpreter::new("sh", vec!["-c", script, "sh"])
+            .run(&path, &[21])
+            .unwrap();
+        assert_eq!(outcome.outputs, vec![42]);
+
+        let local = run_local(&[3, 0, 1002, 0, 2, 0, 4, 0, 99], &[21]).unwrap();
+        let mismatches = compare(&local, &outcome);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(
+            mismatches[0],
+            Mismatch::Memory(MemoryDiff::new(&[42], &[22]))
+        );
+
+        match Interpreter::new("sh", vec!["-c", "echo broken >&2; exit 1", "sh"]).run(&path, &[]) {
+            Err(ConformanceError::Failed(stderr)) => assert_eq!(stderr, "broken\n"),
+            other => panic!("{:?}", other),
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
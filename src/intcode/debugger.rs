@@ -0,0 +1,246 @@
+use super::{Instruction, IntcodeError, IntcodeMachine, Perm, State};
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+
+/// Why the debugger handed control back.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Stop {
+    /// A single step finished without anything else to report.
+    Step(State),
+    /// Execution reached a breakpoint, which has not run yet.
+    Breakpoint(usize),
+    /// The instruction at `pc` wrote to a watched address.
+    Watchpoint {
+        pc: usize,
+        address: usize,
+        old: i64,
+        new: i64,
+    },
+    /// The machine is waiting for input.
+    NeedsInput,
+    /// The program has exited.
+    Halted,
+}
+
+/// Wraps a machine with breakpoints on instruction addresses and watchpoints
+/// on memory addresses.
+///
+/// Output passed over while resuming is queued on the machine, as with
+/// [`IntcodeMachine::run_until`].
+pub struct Debugger {
+    machine: IntcodeMachine,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeSet<usize>,
+}
+
+impl Debugger {
+    pub fn new(machine: IntcodeMachine) -> Self {
+        Debugger {
+            machine,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+        }
+    }
+
+    pub fn machine(&self) -> &IntcodeMachine {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut IntcodeMachine {
+        &mut self.machine
+    }
+
+    pub fn into_inner(self) -> IntcodeMachine {
+        self.machine
+    }
+
+    /// Break before the instruction at `pc` executes. Returns `false` if the
+    /// breakpoint was already set.
+    pub fn add_breakpoint(&mut self, pc: usize) -> bool {
+        self.breakpoints.insert(pc)
+    }
+
+    pub fn remove_breakpoint(&mut self, pc: usize) -> bool {
+        self.breakpoints.remove(&pc)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Break after any instruction writes to `address`, even if the value
+    /// doesn't change. Returns `false` if the watchpoint was already set.
+    pub fn add_watchpoint(&mut self, address: usize) -> bool {
+        self.watchpoints.insert(address)
+    }
+
+    pub fn remove_watchpoint(&mut self, address: usize) -> bool {
+        self.watchpoints.remove(&address)
+    }
+
+    pub fn watchpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.watchpoints.iter().copied()
+    }
+
+    /// Move execution to another instruction.
+    pub fn set_pc(&mut self, pc: usize) {
+        self.machine.pc = pc;
+    }
+
+    pub fn set_relative_base(&mut self, relative_base: i64) {
+        self.machine.relative_base = relative_base;
+    }
+
+    /// Decode the next instruction without executing it.
+    pub fn peek(&mut self) -> Result<Instruction, IntcodeError> {
+        let pc = self.machine.pc;
+        let instruction = Instruction::try_from(&mut self.machine);
+        self.machine.pc = pc;
+        instruction
+    }
+
+    /// Execute a single instruction, ignoring breakpoints.
+    pub fn step(&mut self) -> Result<Stop, IntcodeError> {
+        let pc = self.machine.pc;
+        let watched = if self.machine.halted {
+            None
+        } else {
+            self.peek()?
+                .operands()
+                .into_iter()
+                .filter(|(_, perm)| matches!(perm, Perm::Write))
+                .map(|(address, _)| address as usize)
+                .find(|address| self.watchpoints.contains(address))
+                .map(|address| (address, self.machine.load(address)))
+        };
+
+        let state = self.machine.step()?;
+        match (watched, state) {
+            // Input waiting to be retried hasn't written anything yet
+            (_, State::NeedsInput) => Ok(Stop::NeedsInput),
+            (Some((address, old)), _) => Ok(Stop::Watchpoint {
+                pc,
+                address,
+                old,
+                new: self.machine.load(address),
+            }),
+            (None, State::Halted) => Ok(Stop::Halted),
+            (None, state) => Ok(Stop::Step(state)),
+        }
+    }
+
+    /// Run until a breakpoint or watchpoint is hit, the machine needs input,
+    /// or it halts. At least one instruction is executed, so resuming from a
+    /// breakpoint moves past it.
+    pub fn resume(&mut self) -> Result<Stop, IntcodeError> {
+        loop {
+            match self.step()? {
+                Stop::Step(state) => {
+                    if let State::Output(v) = state {
+                        self.machine.output_queue.push_back(v);
+                    }
+                    if self.breakpoints.contains(&self.machine.pc) {
+                        return Ok(Stop::Breakpoint(self.machine.pc));
+                    }
+                }
+                stop => return Ok(stop),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    fn countdown() -> IntcodeMachine {
+        let program = assemble(
+            "
+            loop: out [n]
+                  add [n], -1, [n]
+                  jnz [n], loop
+                  hlt
+            n:    .data 3
+            ",
+        )
+        .unwrap();
+        IntcodeMachine::new(&program, None, None)
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut debugger = Debugger::new(countdown());
+        assert!(debugger.add_breakpoint(6));
+        assert!(!debugger.add_breakpoint(6));
+
+        assert_eq!(debugger.resume(), Ok(Stop::Breakpoint(6)));
+        assert_eq!(debugger.machine().load(10), 2);
+        assert_eq!(debugger.resume(), Ok(Stop::Breakpoint(6)));
+        assert_eq!(debugger.machine_mut().drain_output(), vec![3, 2]);
+
+        assert!(debugger.remove_breakpoint(6));
+        assert_eq!(debugger.resume(), Ok(Stop::Halted));
+        assert_eq!(debugger.machine_mut().drain_output(), vec![1]);
+        assert_eq!(debugger.resume(), Ok(Stop::Halted));
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let mut debugger = Debugger::new(countdown());
+        debugger.add_watchpoint(10);
+
+        let stop = Stop::Watchpoint {
+            pc: 2,
+            address: 10,
+            old: 3,
+            new: 2,
+        };
+        assert_eq!(debugger.resume(), Ok(stop));
+        assert_eq!(debugger.machine().pc(), 6);
+
+        // Patch memory so the loop ends early
+        debugger.machine_mut().store(10, 1);
+        let stop = Stop::Watchpoint {
+            pc: 2,
+            address: 10,
+            old: 1,
+            new: 0,
+        };
+        assert_eq!(debugger.resume(), Ok(stop));
+        assert_eq!(debugger.resume(), Ok(Stop::Halted));
+        assert_eq!(debugger.machine_mut().drain_output(), vec![3, 1]);
+    }
+
+    #[test]
+    fn test_step() {
+        let mut debugger = Debugger::new(countdown());
+        assert_eq!(debugger.peek().unwrap().mnemonic(), "out");
+        assert_eq!(debugger.step(), Ok(Stop::Step(State::Output(3))));
+        assert_eq!(debugger.step(), Ok(Stop::Step(State::Running)));
+
+        debugger.set_pc(9);
+        debugger.set_relative_base(5);
+        assert_eq!(debugger.machine().relative_base(), 5);
+        assert_eq!(debugger.step(), Ok(Stop::Halted));
+    }
+
+    #[test]
+    fn test_needs_input() {
+        let program = assemble("in [5]\nhlt").unwrap();
+        let mut debugger = Debugger::new(IntcodeMachine::new(&program, None, None));
+        debugger.add_watchpoint(5);
+
+        assert_eq!(debugger.resume(), Ok(Stop::NeedsInput));
+        debugger.machine_mut().push_input(4);
+        assert_eq!(
+            debugger.resume(),
+            Ok(Stop::Watchpoint {
+                pc: 0,
+                address: 5,
+                old: 0,
+                new: 4
+            })
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+
+/// A memory word that differs between two images.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Change {
+    pub address: usize,
+    pub before: i64,
+    pub after: i64,
+}
+
+/// The words that differ between two memory images, in address order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MemoryDiff {
+    pub changes: Vec<Change>,
+}
+
+impl MemoryDiff {
+    /// Compare two images, reading words past the end of either as zero like
+    /// the machine does.
+    pub fn new(before: &[i64], after: &[i64]) -> Self {
+        let changes = (0..before.len().max(after.len()))
+            .filter_map(|address| {
+                let before = before.get(address).copied().unwrap_or(0);
+                let after = after.get(address).copied().unwrap_or(0);
+                if before == after {
+                    return None;
+                }
+                Some(Change {
+                    address,
+                    before,
+                    after,
+                })
+            })
+            .collect();
+        MemoryDiff { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// One change per line, as `address: before -> after`.
+impl fmt::Display for MemoryDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(
+                f,
+                "{:>5}: {} -> {}",
+                change.address, change.before, change.after
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let diff = MemoryDiff::new(&[1, 2, 3], &[1, 5, 3, 0, 7]);
+        assert_eq!(
+            diff.changes,
+            vec![
+                Change {
+                    address: 1,
+                    before: 2,
+                    after: 5
+                },
+                Change {
+                    address: 4,
+                    before: 0,
+                    after: 7
+                }
+            ]
+        );
+        assert_eq!(diff.to_string(), "    1: 2 -> 5\n    4: 0 -> 7\n");
+        assert!(MemoryDiff::new(&[1, 0], &[1]).is_empty());
+    }
+}
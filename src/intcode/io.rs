@@ -0,0 +1,111 @@
+//! Where a machine's input comes from and its output goes.
+
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::time::Duration;
+
+/// What reading from an [`InputSource`] gave.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Input {
+    Value(i64),
+    /// Nothing is available yet, only returned when not waiting.
+    Empty,
+    /// Nothing more will ever be available.
+    Closed,
+    /// Nothing arrived in the time given to [`InputSource::read_timeout`].
+    TimedOut,
+}
+
+pub trait InputSource {
+    /// Read the next value, blocking until one is available if `wait`.
+    fn read(&mut self, wait: bool) -> Input;
+
+    /// Read the next value, waiting at most `timeout` for one. Sources that
+    /// never block can ignore the timeout.
+    fn read_timeout(&mut self, _timeout: Duration) -> Input {
+        self.read(true)
+    }
+}
+
+pub trait OutputSink {
+    /// Write a value, returning `false` if the sink no longer accepts any.
+    fn write(&mut self, value: i64) -> bool;
+}
+
+impl InputSource for Receiver<i64> {
+    fn read(&mut self, wait: bool) -> Input {
+        if wait {
+            return self.recv().map_or(Input::Closed, Input::Value);
+        }
+        match self.try_recv() {
+            Ok(v) => Input::Value(v),
+            Err(TryRecvError::Empty) => Input::Empty,
+            Err(TryRecvError::Disconnected) => Input::Closed,
+        }
+    }
+
+    fn read_timeout(&mut self, timeout: Duration) -> Input {
+        match self.recv_timeout(timeout) {
+            Ok(v) => Input::Value(v),
+            Err(RecvTimeoutError::Timeout) => Input::TimedOut,
+            Err(RecvTimeoutError::Disconnected) => Input::Closed,
+        }
+    }
+}
+
+impl OutputSink for Sender<i64> {
+    fn write(&mut self, value: i64) -> bool {
+        self.send(value).is_ok()
+    }
+}
+
+/// Input from an iterator, closed when it runs out.
+pub struct IterSource<I>(pub I);
+
+impl<I: Iterator<Item = i64>> InputSource for IterSource<I> {
+    fn read(&mut self, _wait: bool) -> Input {
+        self.0.next().map_or(Input::Closed, Input::Value)
+    }
+}
+
+/// Output passed to a closure.
+pub struct FnSink<F>(pub F);
+
+impl<F: FnMut(i64)> OutputSink for FnSink<F> {
+    fn write(&mut self, value: i64) -> bool {
+        (self.0)(value);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_channels() {
+        let (tx, mut rx) = channel();
+        assert_eq!(rx.read(false), Input::Empty);
+        let mut tx_sink = tx.clone();
+        assert!(tx_sink.write(5));
+        assert_eq!(rx.read(true), Input::Value(5));
+        let timeout = Duration::from_millis(1);
+        assert_eq!(rx.read_timeout(timeout), Input::TimedOut);
+        drop((tx, tx_sink));
+        assert_eq!(rx.read_timeout(timeout), Input::Closed);
+        assert_eq!(rx.read(false), Input::Closed);
+        assert_eq!(rx.read(true), Input::Closed);
+    }
+
+    #[test]
+    fn test_adapters() {
+        let mut source = IterSource(vec![1, 2].into_iter());
+        assert_eq!(source.read(true), Input::Value(1));
+        assert_eq!(source.read(false), Input::Value(2));
+        assert_eq!(source.read(true), Input::Closed);
+
+        let mut written = Vec::new();
+        assert!(FnSink(|v| written.push(v)).write(3));
+        assert_eq!(written, vec![3]);
+    }
+}
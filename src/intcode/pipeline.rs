@@ -0,0 +1,343 @@
+use super::{IntcodeError, IntcodeMachine};
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, RecvError, SendError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+struct Node {
+    name: String,
+    program: Vec<i64>,
+}
+
+/// Describes a set of named machines and how their I/O is wired together.
+///
+/// Every output value of a machine is copied to each of its destinations, so
+/// linking one machine to several others fans out, and linking several
+/// machines into one fans in.
+#[derive(Default)]
+pub struct PipelineBuilder {
+    nodes: Vec<Node>,
+    seeds: Vec<(String, Vec<i64>)>,
+    links: Vec<(String, String)>,
+    inputs: Vec<(String, String)>,
+    outputs: Vec<(String, String)>,
+    timeout: Option<Duration>,
+}
+
+impl PipelineBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a machine running `program`.
+    pub fn machine(mut self, name: &str, program: &[i64]) -> Self {
+        self.nodes.push(Node {
+            name: name.to_owned(),
+            program: program.to_owned(),
+        });
+        self
+    }
+
+    /// Queue values for a machine to read before anything else, such as a phase setting.
+    pub fn seed(mut self, name: &str, values: &[i64]) -> Self {
+        self.seeds.push((name.to_owned(), values.to_owned()));
+        self
+    }
+
+    /// Send every output of `from` to the input of `to`.
+    pub fn link(mut self, from: &str, to: &str) -> Self {
+        self.links.push((from.to_owned(), to.to_owned()));
+        self
+    }
+
+    /// Link each machine to the next one.
+    pub fn chain(self, names: &[&str]) -> Self {
+        names
+            .windows(2)
+            .fold(self, |builder, pair| builder.link(pair[0], pair[1]))
+    }
+
+    /// Link each machine to the next one, and the last back to the first.
+    pub fn ring(self, names: &[&str]) -> Self {
+        let builder = self.chain(names);
+        match (names.last(), names.first()) {
+            (Some(last), Some(first)) if names.len() > 1 => builder.link(last, first),
+            _ => builder,
+        }
+    }
+
+    /// Link every machine to every other machine.
+    pub fn mesh(self, names: &[&str]) -> Self {
+        names.iter().fold(self, |builder, from| {
+            names
+                .iter()
+                .filter(|&to| to != from)
+                .fold(builder, |builder, to| builder.link(from, to))
+        })
+    }
+
+    /// Expose an external input port feeding the machine `to`.
+    pub fn input(mut self, port: &str, to: &str) -> Self {
+        self.inputs.push((port.to_owned(), to.to_owned()));
+        self
+    }
+
+    /// Expose an external output port receiving everything `from` outputs.
+    pub fn output(mut self, from: &str, port: &str) -> Self {
+        self.outputs.push((from.to_owned(), port.to_owned()));
+        self
+    }
+
+    /// Stop any machine that waits longer than `timeout` for input, and
+    /// report it as stalled, rather than letting a deadlock hang forever.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Start every machine on its own thread.
+    ///
+    /// Returns `None` if a seed, link or port refers to a machine that was
+    /// never added.
+    pub fn build(self) -> Option<Pipeline> {
+        // Setup an input channel for each machine, seeded before it starts
+        let mut senders = HashMap::new();
+        let mut receivers = HashMap::new();
+        for node in &self.nodes {
+            let (tx, rx) = channel();
+            senders.insert(node.name.as_str(), tx);
+            receivers.insert(node.name.as_str(), rx);
+        }
+        for (name, values) in &self.seeds {
+            let sender = senders.get(name.as_str())?;
+            for &v in values {
+                sender.send(v).ok()?;
+            }
+        }
+
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|(port, to)| Some((port.to_owned(), senders.get(to.as_str())?.clone())))
+            .collect::<Option<HashMap<_, _>>>()?;
+
+        let mut port_senders = HashMap::new();
+        let mut outputs = HashMap::new();
+        for (_, port) in &self.outputs {
+            if !port_senders.contains_key(port.as_str()) {
+                let (tx, rx) = channel();
+                port_senders.insert(port.as_str(), tx);
+                outputs.insert(port.to_owned(), rx);
+            }
+        }
+
+        // Collect the destinations of each machine before anything is spawned
+        let mut destinations: HashMap<&str, Vec<Sender<i64>>> = HashMap::new();
+        for (from, to) in &self.links {
+            let sender = senders.get(to.as_str())?.clone();
+            destinations.entry(from.as_str()).or_default().push(sender);
+        }
+        for (from, port) in &self.outputs {
+            let sender = port_senders[port.as_str()].clone();
+            destinations.entry(from.as_str()).or_default().push(sender);
+        }
+        if destinations
+            .keys()
+            .any(|name| !receivers.contains_key(name))
+        {
+            return None;
+        }
+
+        let mut handles = Vec::new();
+        let stalled = Arc::new(Mutex::new(Vec::new()));
+        for node in &self.nodes {
+            let name = node.name.as_str();
+            let (tx_output, rx_output) = channel();
+            let input = receivers.remove(name);
+            let mut im = IntcodeMachine::new(&node.program, input, Some(tx_output));
+            im.set_input_timeout(self.timeout);
+
+            let builder = thread::Builder::new().name(name.to_owned());
+            // A machine stops early when a peer it feeds has gone, so other
+            // faults are ignored
+            let (stalled, owned_name) = (Arc::clone(&stalled), name.to_owned());
+            let machine = builder.spawn(move || {
+                if let Err(IntcodeError::InputTimeout { .. }) = im.run() {
+                    stalled.lock().unwrap().push(owned_name);
+                }
+            });
+            handles.push(machine.ok()?);
+
+            // Forward each output to every destination, ignoring ones that have halted
+            let targets = destinations.remove(name).unwrap_or_default();
+            let builder = thread::Builder::new().name(format!("{} router", name));
+            let router = builder
+                .spawn(move || {
+                    for v in rx_output {
+                        for target in &targets {
+                            target.send(v).ok();
+                        }
+                    }
+                })
+                .ok()?;
+            handles.push(router);
+        }
+
+        Some(Pipeline {
+            handles,
+            inputs,
+            outputs,
+            stalled,
+        })
+    }
+}
+
+/// A running set of connected machines.
+pub struct Pipeline {
+    handles: Vec<JoinHandle<()>>,
+    inputs: HashMap<String, Sender<i64>>,
+    outputs: HashMap<String, Receiver<i64>>,
+    stalled: Arc<Mutex<Vec<String>>>,
+}
+
+impl Pipeline {
+    pub fn send(&self, port: &str, t: i64) -> Result<(), SendError<i64>> {
+        self.inputs
+            .get(port)
+            .ok_or(SendError(t))
+            .and_then(|sender| sender.send(t))
+    }
+
+    pub fn recv(&self, port: &str) -> Result<i64, RecvError> {
+        self.outputs.get(port).ok_or(RecvError)?.recv()
+    }
+
+    /// Iterate over the values arriving on an output port until every
+    /// machine feeding it has halted.
+    pub fn iter(&self, port: &str) -> impl Iterator<Item = i64> + '_ {
+        self.outputs.get(port).into_iter().flat_map(|rx| rx.iter())
+    }
+
+    /// Close an input port, so machines waiting on it only see linked input.
+    pub fn close(&mut self, port: &str) {
+        self.inputs.remove(port);
+    }
+
+    /// Names of the machines stopped so far for waiting too long on input,
+    /// see [`PipelineBuilder::timeout`].
+    pub fn stalled(&self) -> Vec<String> {
+        self.stalled.lock().unwrap().clone()
+    }
+
+    pub fn join(self) -> Vec<thread::Result<()>> {
+        drop(self.inputs);
+        self.handles
+            .into_iter()
+            .map(|handle| handle.join())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Read a value and output double it
+    const DOUBLE: &[i64] = &[3, 0, 1002, 0, 2, 0, 4, 0, 99];
+    // Read two values and output their sum
+    const SUM: &[i64] = &[3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99, 0, 0, 0];
+
+    #[test]
+    fn test_chain() {
+        let pipeline = PipelineBuilder::new()
+            .machine("a", DOUBLE)
+            .machine("b", DOUBLE)
+            .machine("c", DOUBLE)
+            .chain(&["a", "b", "c"])
+            .input("in", "a")
+            .output("c", "out")
+            .build()
+            .unwrap();
+
+        pipeline.send("in", 3).unwrap();
+        assert_eq!(pipeline.recv("out"), Ok(24));
+        assert!(pipeline.join().into_iter().all(|r| r.is_ok()));
+    }
+
+    #[test]
+    fn test_fan_out_fan_in() {
+        let pipeline = PipelineBuilder::new()
+            .machine("split", DOUBLE)
+            .machine("left", DOUBLE)
+            .machine("right", DOUBLE)
+            .machine("join", SUM)
+            .link("split", "left")
+            .link("split", "right")
+            .link("left", "join")
+            .link("right", "join")
+            .input("in", "split")
+            .output("join", "out")
+            .build()
+            .unwrap();
+
+        pipeline.send("in", 3).unwrap();
+        assert_eq!(pipeline.iter("out").collect::<Vec<_>>(), vec![24]);
+        pipeline.join();
+    }
+
+    #[test]
+    fn test_seed() {
+        let pipeline = PipelineBuilder::new()
+            .machine("sum", SUM)
+            .seed("sum", &[5])
+            .input("in", "sum")
+            .output("sum", "out")
+            .build()
+            .unwrap();
+
+        pipeline.send("in", 7).unwrap();
+        assert_eq!(pipeline.recv("out"), Ok(12));
+        pipeline.join();
+    }
+
+    #[test]
+    fn test_timeout() {
+        // The sum waits for a second value that never comes
+        let pipeline = PipelineBuilder::new()
+            .machine("sum", SUM)
+            .input("in", "sum")
+            .output("sum", "out")
+            .timeout(Duration::from_millis(20))
+            .build()
+            .unwrap();
+
+        pipeline.send("in", 1).unwrap();
+        assert_eq!(pipeline.iter("out").count(), 0);
+        assert_eq!(pipeline.stalled(), vec!["sum"]);
+        pipeline.join();
+    }
+
+    #[test]
+    fn test_topologies() {
+        let names = ["a", "b", "c"];
+        assert_eq!(PipelineBuilder::new().chain(&names).links.len(), 2);
+        assert_eq!(PipelineBuilder::new().ring(&names).links.len(), 3);
+        assert_eq!(PipelineBuilder::new().mesh(&names).links.len(), 6);
+    }
+
+    #[test]
+    fn test_unknown_machine() {
+        let builder = PipelineBuilder::new().machine("a", DOUBLE).link("a", "b");
+        assert!(builder.build().is_none());
+
+        let builder = PipelineBuilder::new()
+            .machine("a", DOUBLE)
+            .output("b", "out");
+        assert!(builder.build().is_none());
+
+        let builder = PipelineBuilder::new().machine("a", DOUBLE).seed("b", &[1]);
+        assert!(builder.build().is_none());
+    }
+}
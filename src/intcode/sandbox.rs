@@ -0,0 +1,264 @@
+//! Running untrusted programs within fixed limits.
+
+use super::{IntcodeError, IntcodeMachine, State};
+use std::error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How often the clock is read, in steps.
+const CLOCK_INTERVAL: u64 = 1024;
+
+/// Limits on a sandboxed run, where `None` means unlimited.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Policy {
+    pub max_steps: Option<u64>,
+    /// Most words of memory, writes at or past this address fault. Memory
+    /// is never unbounded, `None` still stops at the machine's
+    /// [`MEMORY_CEILING`](super::MEMORY_CEILING).
+    pub max_memory: Option<usize>,
+    pub max_outputs: Option<usize>,
+    pub timeout: Option<Duration>,
+}
+
+impl Policy {
+    /// No limits but the machine's memory ceiling, so a program that never
+    /// halts runs forever.
+    pub fn unlimited() -> Self {
+        Policy {
+            max_steps: None,
+            max_memory: None,
+            max_outputs: None,
+            timeout: None,
+        }
+    }
+}
+
+/// Generous enough for any puzzle input.
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            max_steps: Some(100_000_000),
+            max_memory: Some(1 << 20),
+            max_outputs: Some(1 << 20),
+            timeout: Some(Duration::from_secs(10)),
+        }
+    }
+}
+
+/// The limit a run broke, or the fault that stopped it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Violation {
+    Steps(u64),
+    Memory { pc: usize, address: i64 },
+    Outputs(usize),
+    Timeout(Duration),
+    Fault(IntcodeError),
+}
+
+impl error::Error for Violation {}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Violation::Steps(limit) => write!(f, "exceeded {} steps", limit),
+            Violation::Memory { pc, address } => {
+                write!(f, "wrote to {} at {}, past the memory limit", address, pc)
+            }
+            Violation::Outputs(limit) => write!(f, "exceeded {} outputs", limit),
+            Violation::Timeout(limit) => write!(f, "exceeded {:?}", limit),
+            Violation::Fault(e) => e.fmt(f),
+        }
+    }
+}
+
+/// How a sandboxed run ended.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Stop {
+    Halted,
+    /// The program read past the end of its input.
+    NeedsInput,
+    Violated(Violation),
+}
+
+/// What a sandboxed run did.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    pub stop: Stop,
+    pub outputs: Vec<i64>,
+    pub steps: u64,
+    /// Words of memory in use at the end.
+    pub memory: usize,
+    pub elapsed: Duration,
+}
+
+impl Report {
+    pub fn violation(&self) -> Option<Violation> {
+        match self.stop {
+            Stop::Violated(violation) => Some(violation),
+            _ => None,
+        }
+    }
+}
+
+/// Run `program` on `inputs` until it halts, runs out of input, or breaks a
+/// limit in `policy`.
+pub fn run(program: &[i64], inputs: &[i64], policy: &Policy) -> Report {
+    let start = Instant::now();
+    let mut im = IntcodeMachine::new(program, None, None);
+    im.set_memory_limit(policy.max_memory);
+    for &v in inputs {
+        im.push_input(v);
+    }
+
+    let mut outputs = Vec::new();
+    let stop = loop {
+        if policy.max_steps.is_some_and(|limit| im.steps() >= limit) {
+            break Stop::Violated(Violation::Steps(im.steps()));
+        }
+        if let Some(limit) = policy.timeout {
+            if im.steps().is_multiple_of(CLOCK_INTERVAL) && start.elapsed() > limit {
+                break Stop::Violated(Violation::Timeout(limit));
+            }
+        }
+        match im.step() {
+            Ok(State::Running) => {}
+            Ok(State::Output(v)) => {
+                if policy
+                    .max_outputs
+                    .is_some_and(|limit| outputs.len() >= limit)
+                {
+                    break Stop::Violated(Violation::Outputs(outputs.len()));
+                }
+                outputs.push(v);
+            }
+            Ok(State::NeedsInput) => break Stop::NeedsInput,
+            Ok(State::Halted) => break Stop::Halted,
+            Err(IntcodeError::MemoryLimit { pc, address }) => {
+                break Stop::Violated(Violation::Memory { pc, address })
+            }
+            Err(e) => break Stop::Violated(Violation::Fault(e)),
+        }
+    };
+
+    Report {
+        stop,
+        outputs,
+        steps: im.steps(),
+        memory: im.mem.len(),
+        elapsed: start.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+    use crate::intcode::MEMORY_CEILING;
+
+    #[test]
+    fn test_halts() {
+        let program = assemble("in [0]\nmul [0], 2, [0]\nout [0]\nhlt").unwrap();
+        let report = run(&program, &[21], &Policy::default());
+        assert_eq!(report.stop, Stop::Halted);
+        assert_eq!(report.outputs, vec![42]);
+        assert_eq!(report.steps, 4);
+        assert_eq!(report.violation(), None);
+
+        let report = run(&program, &[], &Policy::default());
+        assert_eq!(report.stop, Stop::NeedsInput);
+    }
+
+    #[test]
+    fn test_violations() {
+        let forever = assemble("loop: out 1\njz 0, loop").unwrap();
+        let policy = Policy {
+            max_steps: Some(100),
+            ..Policy::unlimited()
+        };
+        let report = run(&forever, &[], &policy);
+        assert_eq!(report.violation(), Some(Violation::Steps(100)));
+        assert_eq!(report.outputs.len(), 50);
+
+        let policy = Policy {
+            max_outputs: Some(3),
+            ..Policy::unlimited()
+        };
+        let report = run(&forever, &[], &policy);
+        assert_eq!(report.violation(), Some(Violation::Outputs(3)));
+        assert_eq!(report.outputs, vec![1, 1, 1]);
+
+        let policy = Policy {
+            timeout: Some(Duration::from_millis(10)),
+            ..Policy::unlimited()
+        };
+        let report = run(&forever, &[], &policy);
+        assert_eq!(
+            report.violation(),
+            Some(Violation::Timeout(Duration::from_millis(10)))
+        );
+
+        let grow = assemble("add 1, 0, [1000000000000]\nhlt").unwrap();
+        let report = run(&grow, &[], &Policy::default());
+        assert_eq!(
+            report.violation(),
+            Some(Violation::Memory {
+                pc: 0,
+                address: 1_000_000_000_000
+            })
+        );
+        assert_eq!(report.memory, grow.len());
+        let report = run(&grow, &[], &Policy::unlimited());
+        assert_eq!(
+            report.violation(),
+            Some(Violation::Memory {
+                pc: 0,
+                address: 1_000_000_000_000
+            })
+        );
+        assert!(report.memory <= MEMORY_CEILING);
+
+        // Negative addresses are faults, not memory violations
+        for policy in &[Policy::default(), Policy::unlimited()] {
+            let report = run(&[1101, 1, 1, -1, 99], &[], policy);
+            assert_eq!(
+                report.violation(),
+                Some(Violation::Fault(IntcodeError::OutOfBounds {
+                    pc: 0,
+                    address: -1
+                }))
+            );
+            let report = run(&[11101, 1, 1, -1, 99], &[], policy);
+            assert_eq!(
+                report.violation(),
+                Some(Violation::Fault(IntcodeError::InvalidMode {
+                    pc: 0,
+                    value: 11101
+                }))
+            );
+        }
+
+        let report = run(
+            &[1102, 4_000_000_000_000_000_000, 4, 0, 99],
+            &[],
+            &Policy::default(),
+        );
+        assert_eq!(
+            report.violation(),
+            Some(Violation::Fault(IntcodeError::Overflow { pc: 0 }))
+        );
+        let report = run(&[109, i64::MAX, 109, 1, 99], &[], &Policy::default());
+        assert_eq!(
+            report.violation(),
+            Some(Violation::Fault(IntcodeError::Overflow { pc: 2 }))
+        );
+
+        let report = run(&[42], &[], &Policy::default());
+        assert_eq!(
+            report.violation(),
+            Some(Violation::Fault(IntcodeError::InvalidOpcode {
+                pc: 0,
+                value: 42
+            }))
+        );
+    }
+}
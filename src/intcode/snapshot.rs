@@ -0,0 +1,135 @@
+use super::IntcodeMachine;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Machine state that can be restored later, see
+/// [`IntcodeMachine::snapshot`].
+///
+/// Memory is kept as the words that differ from the program the machine was
+/// loaded with, which is shared between snapshots, so taking many of a
+/// machine that only writes a little is cheap.
+///
+/// Channels can't be saved, so values already read from an input channel
+/// aren't read again after restoring. Queued input and output are saved.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Snapshot {
+    image: Arc<Vec<i64>>,
+    changes: Vec<(usize, i64)>,
+    len: usize,
+    steps: u64,
+    inputs: u64,
+    outputs: u64,
+    pc: usize,
+    relative_base: i64,
+    input_queue: VecDeque<i64>,
+    output_queue: VecDeque<i64>,
+    halted: bool,
+}
+
+impl Snapshot {
+    pub(super) fn take(machine: &IntcodeMachine) -> Self {
+        let image = &machine.image;
+        let changes = machine
+            .mem
+            .iter()
+            .enumerate()
+            .filter(|&(address, &v)| image.get(address).copied().unwrap_or(0) != v)
+            .map(|(address, &v)| (address, v))
+            .collect();
+        Snapshot {
+            image: Arc::clone(image),
+            changes,
+            len: machine.mem.len(),
+            steps: machine.steps,
+            inputs: machine.inputs,
+            outputs: machine.outputs,
+            pc: machine.pc,
+            relative_base: machine.relative_base,
+            input_queue: machine.input_queue.clone(),
+            output_queue: machine.output_queue.clone(),
+            halted: machine.halted,
+        }
+    }
+
+    pub(super) fn restore(&self, machine: &mut IntcodeMachine) {
+        let mut mem = self.image.to_vec();
+        mem.resize(self.len, 0);
+        for &(address, v) in &self.changes {
+            mem[address] = v;
+        }
+        machine.image = Arc::clone(&self.image);
+        machine.mem = mem;
+        machine.steps = self.steps;
+        machine.inputs = self.inputs;
+        machine.outputs = self.outputs;
+        machine.pc = self.pc;
+        machine.relative_base = self.relative_base;
+        machine.input_queue = self.input_queue.clone();
+        machine.output_queue = self.output_queue.clone();
+        machine.halted = self.halted;
+    }
+
+    /// Instructions executed when the snapshot was taken.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Words of memory that differ from the loaded program.
+    pub fn changes(&self) -> usize {
+        self.changes.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::asm::assemble;
+    use crate::intcode::{Event, IntcodeMachine, State};
+
+    #[test]
+    fn test_fork() {
+        // Add up inputs until a zero, printing the running total
+        let program = assemble(
+            "
+            loop: in [n]
+                  jz [n], done
+                  add [sum], [n], [sum]
+                  out [sum]
+                  jz 0, loop
+            done: hlt
+            n:    .data 0
+            sum:  .data 0
+            ",
+        )
+        .unwrap();
+        let mut im = IntcodeMachine::new(&program, None, None);
+        im.push_input(3);
+        im.push_input(4);
+        im.run_until(Event::Input).unwrap();
+        assert_eq!(im.drain_output(), vec![3, 7]);
+
+        let snapshot = im.snapshot();
+        assert_eq!(snapshot.changes(), 2);
+
+        // Try two branches from the same state
+        im.push_input(10);
+        im.run_until(Event::Input).unwrap();
+        assert_eq!(im.drain_output(), vec![17]);
+
+        im.restore(&snapshot);
+        im.push_input(1);
+        im.push_input(0);
+        assert_eq!(im.run_until(Event::Input), Ok(State::Halted));
+        assert_eq!(im.drain_output(), vec![8]);
+
+        // Snapshots restore into any machine, halted or not
+        let mut other = IntcodeMachine::new(&[99], None, None);
+        other.restore(&snapshot);
+        other.push_input(2);
+        assert_eq!(other.run_until(Event::Output), Ok(State::Output(9)));
+        assert_eq!(other.steps(), snapshot.steps() + 4);
+    }
+}
@@ -0,0 +1,376 @@
+use super::{Instruction, IntcodeMachine, Perm, State};
+use itertools::Itertools;
+use std::fmt;
+use std::ops::Range;
+
+/// One executed instruction, with its operands as they resolved and the
+/// memory write it made, if any.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TraceEntry {
+    pub pc: usize,
+    pub instruction: Instruction,
+    pub write: Option<(usize, i64)>,
+}
+
+/// A record of executed instructions, see [`IntcodeMachine::start_trace`].
+///
+/// Inputs show up as the writes of input instructions, and outputs as the
+/// operands of output instructions, so a trace is enough to replay a run.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Trace {
+    entries: Vec<TraceEntry>,
+    regions: Vec<Range<usize>>,
+    dumps: Vec<RegionDump>,
+}
+
+/// A copy of a watched memory region, taken after a write inside it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RegionDump {
+    /// Index of the trace entry that made the write.
+    pub entry: usize,
+    pub pc: usize,
+    pub address: usize,
+    pub start: usize,
+    pub values: Vec<i64>,
+}
+
+/// Eight words to a row, in decimal and then in hex.
+impl fmt::Display for RegionDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "[{}] written by {}, region {}..{}",
+            self.address,
+            self.pc,
+            self.start,
+            self.start + self.values.len()
+        )?;
+        for (row, chunk) in self.values.chunks(8).enumerate() {
+            let decimal = chunk.iter().map(|v| format!("{:>6}", v)).join(" ");
+            let hex = chunk.iter().map(|v| format!("{:>4x}", v)).join(" ");
+            writeln!(f, "{:>5}: {} | {}", self.start + row * 8, decimal, hex)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a replay first stopped matching its trace, and what it executed
+/// instead. `found` is `None` if the replay couldn't execute anything.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Divergence {
+    pub index: usize,
+    pub found: Option<TraceEntry>,
+}
+
+impl Trace {
+    pub(super) fn record(&mut self, machine: &IntcodeMachine, pc: usize, instruction: Instruction) {
+        let write = instruction
+            .operands()
+            .into_iter()
+            .find(|(_, perm)| matches!(perm, Perm::Write))
+            .map(|(address, _)| (address as usize, machine.load(address as usize)));
+        self.entries.push(TraceEntry {
+            pc,
+            instruction,
+            write,
+        });
+
+        if let Some((address, _)) = write {
+            for region in self.regions.iter().filter(|r| r.contains(&address)) {
+                self.dumps.push(RegionDump {
+                    entry: self.entries.len() - 1,
+                    pc,
+                    address,
+                    start: region.start,
+                    values: region.clone().map(|a| machine.load(a)).collect(),
+                });
+            }
+        }
+    }
+
+    /// Dump `len` words from `start` whenever an instruction writes inside them.
+    pub fn watch_region(&mut self, start: usize, len: usize) {
+        self.regions.push(start..start + len);
+    }
+
+    pub fn dumps(&self) -> &[RegionDump] {
+        &self.dumps
+    }
+
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Values read by input instructions, in order.
+    pub fn inputs(&self) -> impl Iterator<Item = i64> + '_ {
+        self.entries.iter().filter_map(|entry| match entry {
+            TraceEntry {
+                instruction: Instruction::Input(_),
+                write: Some((_, v)),
+                ..
+            } => Some(*v),
+            _ => None,
+        })
+    }
+
+    /// Values written by output instructions, in order.
+    pub fn outputs(&self) -> impl Iterator<Item = i64> + '_ {
+        self.entries
+            .iter()
+            .filter_map(|entry| match entry.instruction {
+                Instruction::Output(v) => Some(v),
+                _ => None,
+            })
+    }
+
+    /// Encode the trace as a JSON array with an object per instruction.
+    pub fn to_json(&self) -> String {
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let operands = entry
+                    .instruction
+                    .operands()
+                    .iter()
+                    .map(|(v, _)| v)
+                    .join(",");
+                let write = match entry.write {
+                    Some((address, v)) => format!("[{},{}]", address, v),
+                    None => "null".to_owned(),
+                };
+                format!(
+                    r#"{{"pc":{},"op":"{}","operands":[{}],"write":{}}}"#,
+                    entry.pc,
+                    entry.instruction.mnemonic(),
+                    operands,
+                    write
+                )
+            })
+            .join(",");
+        format!("[{}]", entries)
+    }
+
+    /// Run `program` again with the recorded inputs, checking every
+    /// instruction against the trace.
+    pub fn replay(&self, program: &[i64]) -> Result<IntcodeMachine, Divergence> {
+        let mut machine = IntcodeMachine::new(program, None, None);
+        machine.start_trace();
+
+        for (index, expected) in self.entries.iter().enumerate() {
+            if let TraceEntry {
+                instruction: Instruction::Input(_),
+                write: Some((_, v)),
+                ..
+            } = *expected
+            {
+                machine.push_input(v);
+            }
+
+            let found = match machine.step() {
+                Ok(State::NeedsInput) | Err(_) => None,
+                Ok(_) => machine
+                    .trace()
+                    .and_then(|trace| trace.entries.last())
+                    .copied(),
+            };
+            if found != Some(*expected) {
+                return Err(Divergence { index, found });
+            }
+        }
+
+        // The replay should stop where the trace does
+        if !machine.is_halted() && machine.step().is_ok_and(|s| s != State::NeedsInput) {
+            return Err(Divergence {
+                index: self.entries.len(),
+                found: machine
+                    .trace()
+                    .and_then(|trace| trace.entries.last())
+                    .copied(),
+            });
+        }
+        machine.take_trace();
+        Ok(machine)
+    }
+}
+
+/// A human readable log, one instruction per line, followed by any region
+/// dumps the instruction caused.
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut dumps = self.dumps.iter().peekable();
+        for (i, entry) in self.entries.iter().enumerate() {
+            match entry.write {
+                Some((address, v)) => writeln!(
+                    f,
+                    "{:>5}: {:<24} [{}] <- {}",
+                    entry.pc, entry.instruction, address, v
+                )?,
+                None => writeln!(f, "{:>5}: {}", entry.pc, entry.instruction)?,
+            }
+            while let Some(dump) = dumps.next_if(|dump| dump.entry == i) {
+                write!(f, "{}", dump)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{parse_program, Event};
+
+    // Day 5 example, output 999, 1000 or 1001 for input below, equal or above 8
+    const COMPARE: &str = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,\
+                           1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,\
+                           999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+
+    fn traced(program: &[i64], input: i64) -> Trace {
+        let mut im = IntcodeMachine::new(program, None, None);
+        im.start_trace();
+        im.push_input(input);
+        im.run().unwrap();
+        im.take_trace().unwrap()
+    }
+
+    #[test]
+    fn test_record() {
+        let program = parse_program("3,0,1002,0,2,0,4,0,99").unwrap();
+        let trace = traced(&program, 21);
+
+        assert_eq!(
+            trace.entries(),
+            &[
+                TraceEntry {
+                    pc: 0,
+                    instruction: Instruction::Input(0),
+                    write: Some((0, 21))
+                },
+                TraceEntry {
+                    pc: 2,
+                    instruction: Instruction::Multiply(21, 2, 0),
+                    write: Some((0, 42))
+                },
+                TraceEntry {
+                    pc: 6,
+                    instruction: Instruction::Output(42),
+                    write: None
+                },
+                TraceEntry {
+                    pc: 8,
+                    instruction: Instruction::Exit,
+                    write: None
+                },
+            ]
+        );
+        assert_eq!(trace.inputs().collect::<Vec<_>>(), vec![21]);
+        assert_eq!(trace.outputs().collect::<Vec<_>>(), vec![42]);
+    }
+
+    #[test]
+    fn test_format() {
+        let program = parse_program("3,0,1002,0,2,0,4,0,99").unwrap();
+        let trace = traced(&program, 21);
+
+        assert_eq!(
+            trace.to_json(),
+            concat!(
+                r#"[{"pc":0,"op":"in","operands":[0],"write":[0,21]},"#,
+                r#"{"pc":2,"op":"mul","operands":[21,2,0],"write":[0,42]},"#,
+                r#"{"pc":6,"op":"out","operands":[42],"write":null},"#,
+                r#"{"pc":8,"op":"hlt","operands":[],"write":null}]"#
+            )
+        );
+        assert_eq!(
+            trace.to_string().lines().collect::<Vec<_>>(),
+            vec![
+                "    0: in 0                     [0] <- 21",
+                "    2: mul 21, 2, 0             [0] <- 42",
+                "    6: out 42",
+                "    8: hlt",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_watch_region() {
+        // Fill four words with 1, 2, 3, then write just past them
+        let program = parse_program("1101,0,1,20,1101,0,2,21,1101,0,3,22,1101,0,-1,24,99").unwrap();
+        let mut im = IntcodeMachine::new(&program, None, None);
+        im.watch_region(20, 4);
+        im.run().unwrap();
+        let trace = im.take_trace().unwrap();
+
+        let dumps = trace.dumps();
+        assert_eq!(dumps.len(), 3);
+        assert_eq!(
+            dumps[2],
+            RegionDump {
+                entry: 2,
+                pc: 8,
+                address: 22,
+                start: 20,
+                values: vec![1, 2, 3, 0]
+            }
+        );
+        assert_eq!(
+            dumps[0].to_string(),
+            "[20] written by 0, region 20..24\n   20:      1      0      0      0 |    1    0    0    0\n"
+        );
+        assert_eq!(
+            trace.to_string().lines().nth(2),
+            Some("   20:      1      0      0      0 |    1    0    0    0")
+        );
+    }
+
+    #[test]
+    fn test_replay() {
+        let program = parse_program(COMPARE).unwrap();
+        let trace = traced(&program, 7);
+        assert_eq!(trace.outputs().collect::<Vec<_>>(), vec![999]);
+
+        let im = trace.replay(&program).unwrap();
+        assert!(im.is_halted());
+        assert!(im.trace().is_none());
+
+        // Changing a constant changes the path taken
+        let mut patched = program.clone();
+        patched[4] = 6;
+        let divergence = trace.replay(&patched).err().unwrap();
+        assert_eq!(divergence.index, 1);
+        assert_eq!(
+            divergence.found.map(|entry| entry.instruction),
+            Some(Instruction::Equals(7, 6, 20))
+        );
+    }
+
+    #[test]
+    fn test_replay_partial() {
+        // A trace that stops early is still replayed exactly
+        let program = parse_program("3,0,4,0,3,0,4,0,99").unwrap();
+        let mut im = IntcodeMachine::new(&program, None, None);
+        im.start_trace();
+        im.push_input(5);
+        im.run_until(Event::Input).unwrap();
+        let trace = im.take_trace().unwrap();
+        assert_eq!(trace.len(), 2);
+
+        let im = trace.replay(&program).unwrap();
+        assert_eq!(im.pc(), 4);
+
+        // But not a program that keeps going past it
+        let program = parse_program("3,0,4,0,99").unwrap();
+        let mut trace = trace;
+        trace.entries.truncate(1);
+        assert_eq!(trace.replay(&program).err().unwrap().index, 1);
+    }
+}
@@ -3,7 +3,12 @@ extern crate aoc_runner;
 #[macro_use]
 extern crate aoc_runner_derive;
 
+pub mod diagnostics;
+pub mod geometry;
+pub mod grid;
 pub mod intcode;
+pub mod search;
+pub mod tree;
 
 pub mod day01;
 pub mod day02;
@@ -17,5 +22,18 @@ pub mod day09;
 pub mod day10;
 pub mod day11;
 pub mod day12;
+pub mod day13;
+pub mod day14;
+pub mod day15;
+pub mod day16;
+pub mod day17;
+pub mod day18;
+pub mod day19;
+pub mod day20;
+pub mod day21;
+pub mod day22;
+pub mod day23;
+pub mod day24;
+pub mod day25;
 
 aoc_lib! { year = 2019 }
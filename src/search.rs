@@ -0,0 +1,209 @@
+//! Shortest paths over graphs given as closures.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+use std::ops::Add;
+
+/// A route found by a search, `start` and the goal included.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Path<N, C> {
+    pub cost: C,
+    pub nodes: Vec<N>,
+}
+
+/// Nodes reached so far, each with the index of the node it was reached from.
+struct Visited<N> {
+    nodes: Vec<N>,
+    parents: Vec<usize>,
+    index: HashMap<N, usize>,
+}
+
+impl<N: Clone + Eq + Hash> Visited<N> {
+    fn new(start: N) -> Self {
+        let mut index = HashMap::new();
+        index.insert(start.clone(), 0);
+        Visited {
+            nodes: vec![start],
+            parents: vec![0],
+            index,
+        }
+    }
+
+    fn insert(&mut self, node: N, parent: usize) -> usize {
+        let i = self.nodes.len();
+        self.index.insert(node.clone(), i);
+        self.nodes.push(node);
+        self.parents.push(parent);
+        i
+    }
+
+    fn path(&self, mut i: usize) -> Vec<N> {
+        let mut path = vec![self.nodes[i].clone()];
+        while i != 0 {
+            i = self.parents[i];
+            path.push(self.nodes[i].clone());
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// Fewest steps from `start` to a node `goal` accepts.
+pub fn bfs<N, FN, I, FG>(start: N, mut neighbors: FN, mut goal: FG) -> Option<Path<N, usize>>
+where
+    N: Clone + Eq + Hash,
+    FN: FnMut(&N) -> I,
+    I: IntoIterator<Item = N>,
+    FG: FnMut(&N) -> bool,
+{
+    let mut visited = Visited::new(start);
+    let mut queue = VecDeque::new();
+    queue.push_back((0, 0));
+
+    while let Some((i, steps)) = queue.pop_front() {
+        let node = visited.nodes[i].clone();
+        if goal(&node) {
+            let nodes = visited.path(i);
+            return Some(Path { cost: steps, nodes });
+        }
+        for next in neighbors(&node) {
+            if !visited.index.contains_key(&next) {
+                let j = visited.insert(next, i);
+                queue.push_back((j, steps + 1));
+            }
+        }
+    }
+    None
+}
+
+/// Cheapest route from `start` to a node `goal` accepts, where `neighbors`
+/// gives each node's neighbours with the cost of getting to them.
+pub fn dijkstra<N, C, FN, I, FG>(start: N, neighbors: FN, goal: FG) -> Option<Path<N, C>>
+where
+    N: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    FN: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+    FG: FnMut(&N) -> bool,
+{
+    astar(start, neighbors, |_| C::default(), goal)
+}
+
+/// Like [`dijkstra`], visiting nodes in order of their cost so far plus
+/// `heuristic`, which must never overestimate the cost left to the goal.
+pub fn astar<N, C, FN, I, FH, FG>(
+    start: N,
+    mut neighbors: FN,
+    mut heuristic: FH,
+    mut goal: FG,
+) -> Option<Path<N, C>>
+where
+    N: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    FN: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+    FH: FnMut(&N) -> C,
+    FG: FnMut(&N) -> bool,
+{
+    let mut heap = BinaryHeap::new();
+    heap.push(Reverse((heuristic(&start), C::default(), 0)));
+    let mut visited = Visited::new(start);
+    let mut costs = vec![C::default()];
+
+    while let Some(Reverse((_, cost, i))) = heap.pop() {
+        if cost > costs[i] {
+            continue;
+        }
+        let node = visited.nodes[i].clone();
+        if goal(&node) {
+            let nodes = visited.path(i);
+            return Some(Path { cost, nodes });
+        }
+        for (next, step) in neighbors(&node) {
+            let cost = cost + step;
+            let estimate = cost + heuristic(&next);
+            let j = match visited.index.get(&next) {
+                Some(&j) if costs[j] <= cost => continue,
+                Some(&j) => {
+                    visited.parents[j] = i;
+                    costs[j] = cost;
+                    j
+                }
+                None => {
+                    costs.push(cost);
+                    visited.insert(next, i)
+                }
+            };
+            heap.push(Reverse((estimate, cost, j)));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORBITS: &str = "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN";
+
+    #[test]
+    fn test_bfs() {
+        let mut graph: HashMap<&str, Vec<&str>> = HashMap::new();
+        for line in ORBITS.lines() {
+            let (parent, child) = line.split_at(line.find(')').unwrap());
+            let child = &child[1..];
+            graph.entry(parent).or_default().push(child);
+            graph.entry(child).or_default().push(parent);
+        }
+
+        // From the object YOU orbit to the one SAN orbits
+        let path = bfs("K", |node| graph[node].clone(), |&node| node == "I").unwrap();
+        assert_eq!(path.cost, 4);
+        assert_eq!(path.nodes, vec!["K", "J", "E", "D", "I"]);
+
+        let path = bfs("YOU", |node| graph[node].clone(), |&node| node == "SAN").unwrap();
+        assert_eq!(path.cost, 4 + 2);
+        assert_eq!(bfs("YOU", |node| graph[node].clone(), |_| false), None);
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        // Going the long way round is cheaper
+        let edges: HashMap<char, Vec<(char, u32)>> = vec![
+            ('a', vec![('b', 10), ('c', 1)]),
+            ('b', vec![('d', 1)]),
+            ('c', vec![('e', 1)]),
+            ('e', vec![('b', 1)]),
+            ('d', vec![]),
+        ]
+        .into_iter()
+        .collect();
+        let path = dijkstra('a', |n| edges[n].clone(), |&n| n == 'd').unwrap();
+        assert_eq!(path.cost, 4);
+        assert_eq!(path.nodes, vec!['a', 'c', 'e', 'b', 'd']);
+        assert_eq!(dijkstra('d', |n| edges[n].clone(), |&n| n == 'a'), None);
+    }
+
+    #[test]
+    fn test_astar() {
+        // Around a wall on a 5x5 grid, from the top left to the bottom left
+        let wall = [(0, 2), (1, 2), (2, 2), (3, 2)];
+        let neighbors = |&(x, y): &(i32, i32)| {
+            vec![(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+                .into_iter()
+                .filter(|&(x, y)| (0..5).contains(&x) && (0..5).contains(&y))
+                .filter(|p| !wall.contains(p))
+                .map(|p| (p, 1))
+                .collect::<Vec<_>>()
+        };
+        let manhattan = |&(x, y): &(i32, i32)| x.abs() + (4 - y).abs();
+        let path = astar((0, 0), neighbors, manhattan, |&p| p == (0, 4)).unwrap();
+        assert_eq!(path.cost, 12);
+        assert_eq!(path.nodes.len(), 13);
+        assert_eq!(
+            dijkstra((0, 0), neighbors, |&p| p == (0, 4)).map(|p| p.cost),
+            Some(12)
+        );
+    }
+}
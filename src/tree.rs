@@ -0,0 +1,187 @@
+//! Rooted trees given as a map from each node to its parent.
+
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::iter::FromIterator;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Tree<T: Eq + Hash> {
+    parents: HashMap<T, T>,
+    nodes: HashSet<T>,
+}
+
+impl<T: Clone + Eq + Hash> Tree<T> {
+    /// A tree, or forest, from each node's parent. Roots are the nodes with
+    /// no parent.
+    pub fn new(parents: HashMap<T, T>) -> Self {
+        let nodes = parents
+            .iter()
+            .flat_map(|(child, parent)| vec![child.clone(), parent.clone()])
+            .collect();
+        Tree { parents, nodes }
+    }
+
+    pub fn contains<Q>(&self, node: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.nodes.contains(node)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &T> {
+        self.nodes.iter()
+    }
+
+    pub fn parent<Q>(&self, node: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.parents.get(node)
+    }
+
+    /// `node` and its ancestors, ending with its root.
+    pub fn path_to_root<Q>(&self, node: &Q) -> impl Iterator<Item = &T>
+    where
+        T: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        std::iter::successors(self.nodes.get(node), move |&node| {
+            self.parents.get::<T>(node)
+        })
+    }
+
+    /// Edges between `node` and its root.
+    pub fn depth<Q>(&self, node: &Q) -> Option<usize>
+    where
+        T: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        if !self.contains(node) {
+            return None;
+        }
+        Some(self.path_to_root(node).count() - 1)
+    }
+
+    /// Depths of every node, sharing the walk up from nodes on the same path.
+    pub fn depths(&self) -> HashMap<&T, usize> {
+        let mut depths = HashMap::new();
+        for node in &self.nodes {
+            let mut path = Vec::new();
+            let mut current = node;
+            let base = loop {
+                if let Some(&depth) = depths.get(current) {
+                    break depth;
+                }
+                match self.parent(current) {
+                    Some(parent) => {
+                        path.push(current);
+                        current = parent;
+                    }
+                    None => {
+                        depths.insert(current, 0);
+                        break 0;
+                    }
+                }
+            };
+            for (i, node) in path.into_iter().rev().enumerate() {
+                depths.insert(node, base + i + 1);
+            }
+        }
+        depths
+    }
+
+    /// The deepest node that both `a` and `b` descend from, if they're in
+    /// the same tree.
+    pub fn lowest_common_ancestor<Q>(&self, a: &Q, b: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let (mut a, mut b) = (self.nodes.get(a)?, self.nodes.get(b)?);
+        let (mut depth_a, mut depth_b) = (self.depth::<T>(a)?, self.depth::<T>(b)?);
+        while depth_a > depth_b {
+            a = self.parent::<T>(a)?;
+            depth_a -= 1;
+        }
+        while depth_b > depth_a {
+            b = self.parent::<T>(b)?;
+            depth_b -= 1;
+        }
+        while a != b {
+            a = self.parent::<T>(a)?;
+            b = self.parent::<T>(b)?;
+        }
+        Some(a)
+    }
+
+    /// Edges on the path between `a` and `b`.
+    pub fn distance<Q>(&self, a: &Q, b: &Q) -> Option<usize>
+    where
+        T: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let ancestor = self.lowest_common_ancestor(a, b)?;
+        Some(self.depth(a)? + self.depth(b)? - 2 * self.depth::<T>(ancestor)?)
+    }
+}
+
+/// Collect `(child, parent)` pairs.
+impl<T: Clone + Eq + Hash> FromIterator<(T, T)> for Tree<T> {
+    fn from_iter<I: IntoIterator<Item = (T, T)>>(iter: I) -> Self {
+        Tree::new(iter.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //     a
+    //    / \
+    //   b   c     x
+    //  / \   \    |
+    // d   e   f   y
+    fn forest() -> Tree<char> {
+        vec![
+            ('b', 'a'),
+            ('c', 'a'),
+            ('d', 'b'),
+            ('e', 'b'),
+            ('f', 'c'),
+            ('y', 'x'),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn test_depth() {
+        let tree = forest();
+        assert_eq!(tree.path_to_root(&'e').collect::<String>(), "eba");
+        assert_eq!(tree.path_to_root(&'z').count(), 0);
+        assert_eq!(tree.depth(&'a'), Some(0));
+        assert_eq!(tree.depth(&'f'), Some(2));
+        assert_eq!(tree.depth(&'z'), None);
+
+        let depths = tree.depths();
+        assert_eq!(depths.len(), 8);
+        for node in tree.nodes() {
+            assert_eq!(Some(depths[node]), tree.depth(node));
+        }
+    }
+
+    #[test]
+    fn test_lowest_common_ancestor() {
+        let tree = forest();
+        assert_eq!(tree.lowest_common_ancestor(&'d', &'e'), Some(&'b'));
+        assert_eq!(tree.lowest_common_ancestor(&'d', &'f'), Some(&'a'));
+        assert_eq!(tree.lowest_common_ancestor(&'b', &'d'), Some(&'b'));
+        assert_eq!(tree.lowest_common_ancestor(&'d', &'y'), None);
+        assert_eq!(tree.distance(&'d', &'f'), Some(4));
+        assert_eq!(tree.distance(&'e', &'e'), Some(0));
+        assert_eq!(tree.distance(&'x', &'y'), Some(1));
+    }
+}
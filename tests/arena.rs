@@ -0,0 +1,64 @@
+//! Counts allocations while probing with an arena, with a global allocator
+//! that only counts on the thread that asks it to.
+
+use advent_of_code_2019::intcode::arena::Arena;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.with(Cell::get) {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    COUNTING.with(|c| c.set(true));
+    f();
+    COUNTING.with(|c| c.set(false));
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+#[test]
+fn test_probe_allocations() {
+    // Output whether x * y is zero, writing past the end of the program
+    let program = vec![
+        3, 100, 3, 101, 2, 100, 101, 102, 1008, 102, 0, 103, 4, 103, 99,
+    ];
+    let probes = (0..100).flat_map(|y| (0..100).map(move |x| [x, y]));
+
+    let fresh = allocations(|| {
+        for inputs in probes.clone() {
+            advent_of_code_2019::intcode::IntcodeMachine::evaluate(&program, &inputs).unwrap();
+        }
+    });
+    assert!(fresh >= 10_000);
+
+    let arena = Arena::new(&program);
+    arena.evaluate(&[0, 0]).unwrap();
+    let pooled = allocations(|| {
+        for inputs in probes {
+            arena.evaluate(&inputs).unwrap();
+        }
+    });
+    assert_eq!(pooled, 0);
+}
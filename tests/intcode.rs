@@ -0,0 +1,174 @@
+use advent_of_code_2019::intcode::{
+    parse_program, Event, Instruction, IntcodeError, IntcodeMachine, State, MEMORY_CEILING,
+};
+use std::sync::mpsc::channel;
+use std::thread;
+
+#[test]
+fn test_run_to_completion() {
+    let program = parse_program("1,9,10,3,2,3,11,0,99,30,40,50\n").unwrap();
+    let mut im = IntcodeMachine::new(&program, None, None);
+    im.run().unwrap();
+
+    assert!(im.is_halted());
+    assert_eq!(im.load(0), 3500);
+    assert_eq!(im.load(3), 70);
+}
+
+#[test]
+fn test_store_before_run() {
+    let program = parse_program("1,0,0,0,99").unwrap();
+    let mut im = IntcodeMachine::new(&program, None, None);
+    im.store(1, 4);
+    im.store(2, 4);
+    im.run().unwrap();
+
+    assert_eq!(im.load(0), 198);
+}
+
+#[test]
+fn test_step() {
+    let program = parse_program("109,19,1101,2,3,5,99").unwrap();
+    let mut im = IntcodeMachine::new(&program, None, None);
+    assert_eq!((im.pc(), im.relative_base()), (0, 0));
+
+    assert_eq!(im.step(), Ok(State::Running));
+    assert_eq!((im.pc(), im.relative_base()), (2, 19));
+
+    assert_eq!(im.step(), Ok(State::Running));
+    assert_eq!(im.pc(), 6);
+    assert_eq!(im.load(5), 5);
+    assert!(!im.is_halted());
+
+    assert_eq!(im.step(), Ok(State::Halted));
+    assert!(im.is_halted());
+    assert_eq!(im.step(), Ok(State::Halted));
+}
+
+#[test]
+fn test_channels() {
+    // Sum numbers until a zero is read
+    let program = parse_program("3,20,1006,20,13,1,20,21,21,1105,1,0,99,4,21,99").unwrap();
+    let (tx_input, rx_input) = channel();
+    let (tx_output, rx_output) = channel();
+
+    let mut im = IntcodeMachine::new(&program, None, None);
+    im.set_input(rx_input);
+    im.set_output(tx_output);
+    let handle = thread::spawn(move || im.run());
+
+    for v in &[4, 8, 15, 16, 23, 42, 0] {
+        tx_input.send(*v).unwrap();
+    }
+    assert_eq!(rx_output.iter().collect::<Vec<_>>(), vec![108]);
+    assert_eq!(handle.join().unwrap(), Ok(()));
+}
+
+#[test]
+fn test_disconnected_input() {
+    let program = parse_program("3,0,99").unwrap();
+    let (tx_input, rx_input) = channel::<i64>();
+    drop(tx_input);
+
+    let mut im = IntcodeMachine::new(&program, Some(rx_input), None);
+    assert_eq!(im.step(), Err(IntcodeError::InputExhausted { pc: 0 }));
+    assert_eq!(im.pc(), 0);
+    assert!(!im.is_halted());
+}
+
+#[test]
+fn test_faults() {
+    let run = |s: &str| IntcodeMachine::new(&parse_program(s).unwrap(), None, None).run();
+
+    assert_eq!(
+        run("1101,1,1,5,42,0"),
+        Err(IntcodeError::InvalidOpcode { pc: 4, value: 42 })
+    );
+    assert_eq!(
+        run("301,1,1,0,99"),
+        Err(IntcodeError::InvalidMode { pc: 0, value: 301 })
+    );
+    assert_eq!(
+        run("1,-1,0,0,99"),
+        Err(IntcodeError::OutOfBounds { pc: 0, address: -1 })
+    );
+    assert_eq!(
+        run("1105,1,-3"),
+        Err(IntcodeError::OutOfBounds { pc: 0, address: -3 })
+    );
+    assert_eq!(run("3,0,99"), Err(IntcodeError::InputExhausted { pc: 0 }));
+    assert_eq!(
+        run(&format!("1101,1,1,{},99", MEMORY_CEILING)),
+        Err(IntcodeError::MemoryLimit {
+            pc: 0,
+            address: MEMORY_CEILING as i64
+        })
+    );
+
+    // Writes can't be immediate, and can't land on a negative address even
+    // when a debug hook rewrites them
+    assert_eq!(
+        run("11101,1,1,-1,99"),
+        Err(IntcodeError::InvalidMode {
+            pc: 0,
+            value: 11101
+        })
+    );
+    let mut im = IntcodeMachine::new(&parse_program("1101,1,1,0,99").unwrap(), None, None);
+    im.set_debug(|_, _, instruction| match instruction {
+        Instruction::Add(a, b, _) => Instruction::Add(a, b, -1),
+        instruction => instruction,
+    });
+    assert_eq!(
+        im.run(),
+        Err(IntcodeError::OutOfBounds { pc: 0, address: -1 })
+    );
+
+    let (tx_output, rx_output) = channel();
+    drop(rx_output);
+    let program = parse_program("104,7,99").unwrap();
+    let mut im = IntcodeMachine::new(&program, None, Some(tx_output));
+    assert_eq!(
+        im.run(),
+        Err(IntcodeError::OutputClosed { pc: 0, value: 7 })
+    );
+    assert_eq!(im.pc(), 0);
+}
+
+#[test]
+fn test_cooperative() {
+    // Sum numbers until a zero is read, without channels or threads
+    let program = parse_program("3,20,1006,20,13,1,20,21,21,1105,1,0,99,4,21,99").unwrap();
+    let mut im = IntcodeMachine::new(&program, None, None);
+
+    for v in &[4, 8, 15, 16, 23, 42] {
+        assert_eq!(im.run_until(Event::Output), Ok(State::NeedsInput));
+        im.push_input(*v);
+    }
+    im.push_input(0);
+    assert_eq!(im.run_until(Event::Output), Ok(State::Output(108)));
+    assert_eq!(im.run_until(Event::Output), Ok(State::Halted));
+}
+
+#[test]
+fn test_debug_hook() {
+    // Turn every multiply into an add
+    fn hook(_: &mut IntcodeMachine, _: usize, instruction: Instruction) -> Instruction {
+        match instruction {
+            Instruction::Multiply(a, b, c) => Instruction::Add(a, b, c),
+            other => other,
+        }
+    }
+
+    let program = parse_program("1102,6,7,0,99").unwrap();
+    let mut im = IntcodeMachine::new(&program, None, None);
+    im.set_debug(hook);
+    im.run().unwrap();
+    assert_eq!(im.load(0), 13);
+
+    let mut im = IntcodeMachine::new(&program, None, None);
+    im.set_debug(hook);
+    im.unset_debug();
+    im.run().unwrap();
+    assert_eq!(im.load(0), 42);
+}